@@ -0,0 +1,221 @@
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+/// Number of ticks after which the world clamps all ticks that are too old (see `Tick::check`).
+pub(crate) const CHECK_TICK_THRESHOLD: u32 = 518_400_000;
+
+/// The maximum age of a tick, after which it's clamped.
+///
+/// Ticks are clamped every `CHECK_TICK_THRESHOLD` ticks, so no tick ever gets older than
+/// `u32::MAX` (which is when it would seem newer again after the tick counter wraps).
+pub(crate) const MAX_CHANGE_AGE: u32 = u32::MAX - (2 * CHECK_TICK_THRESHOLD - 1);
+
+/// A point in time used for change detection.
+///
+/// The world's tick counter is incremented every time a system runs, so systems can tell
+/// which changes happened since their last run. The counter wraps around, so ticks are only
+/// compared relative to the current tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Tick(u32);
+
+impl Tick {
+    /// Creates a tick with the specified value.
+    pub(crate) const fn new(tick: u32) -> Self {
+        Self(tick)
+    }
+
+    /// Gets the value of the tick.
+    pub(crate) fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Gets the tick that is `ticks` before this one.
+    pub(crate) fn before(self, ticks: u32) -> Self {
+        Self(self.0.wrapping_sub(ticks))
+    }
+
+    /// Checks if this tick is after `last_run`, as seen from `this_run`.
+    pub(crate) fn is_newer_than(self, last_run: Tick, this_run: Tick) -> bool {
+        // Ticks older than the maximum age are treated as being exactly that old
+        let ticks_since_change = this_run.0.wrapping_sub(self.0).min(MAX_CHANGE_AGE);
+        let ticks_since_run = this_run.0.wrapping_sub(last_run.0).min(MAX_CHANGE_AGE);
+
+        ticks_since_run > ticks_since_change
+    }
+
+    /// Clamps the tick if it's older than `MAX_CHANGE_AGE`, so it doesn't seem newer than it is
+    /// once the tick counter wraps around.
+    pub(crate) fn check(&mut self, now: Tick) {
+        if now.0.wrapping_sub(self.0) > MAX_CHANGE_AGE {
+            *self = now.before(MAX_CHANGE_AGE);
+        }
+    }
+}
+
+/// The ticks at which a component was added to its entity, and last changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct ComponentTicks {
+    pub(crate) added: Tick,
+    pub(crate) changed: Tick,
+}
+
+impl ComponentTicks {
+    /// Creates the ticks of a component that was added at `tick`.
+    pub(crate) fn new(tick: Tick) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    /// Clamps the ticks that are too old (see `Tick::check`).
+    pub(crate) fn check(&mut self, now: Tick) {
+        self.added.check(now);
+        self.changed.check(now);
+    }
+}
+
+/// A mutable reference to a component that marks it as changed when it's mutably dereferenced.
+///
+/// Queries return this for components that are queried mutably (`&mut T`).
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    ticks: &'a mut ComponentTicks,
+    last_run: Tick,
+    this_run: Tick,
+}
+
+impl<'a, T> Mut<'a, T> {
+    /// Creates a mutable reference to a component with the specified ticks.
+    pub(crate) fn new(
+        value: &'a mut T,
+        ticks: &'a mut ComponentTicks,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self {
+        Self {
+            value,
+            ticks,
+            last_run,
+            this_run,
+        }
+    }
+
+    /// Checks if the component was added since the system last ran.
+    pub fn is_added(&self) -> bool {
+        self.ticks.added.is_newer_than(self.last_run, self.this_run)
+    }
+
+    /// Checks if the component was added or changed since the system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.ticks
+            .changed
+            .is_newer_than(self.last_run, self.this_run)
+    }
+
+    /// Marks the component as changed without dereferencing it.
+    pub fn set_changed(&mut self) {
+        self.ticks.changed = self.this_run;
+    }
+
+    /// Gets a mutable reference to the component without marking it as changed.
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value
+    }
+
+    /// Converts this into a plain mutable reference, marking the component as changed.
+    pub fn into_inner(mut self) -> &'a mut T {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Mut<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Mut").field(&self.value).finish()
+    }
+}
+
+/// An immutable reference to a component that can tell whether the component was added or
+/// changed since the system last ran.
+///
+/// Query `Ref<T>` instead of `&T` to get one.
+pub struct Ref<'a, T> {
+    value: &'a T,
+    ticks: &'a ComponentTicks,
+    last_run: Tick,
+    this_run: Tick,
+}
+
+impl<'a, T> Ref<'a, T> {
+    /// Creates a reference to a component with the specified ticks.
+    pub(crate) fn new(
+        value: &'a T,
+        ticks: &'a ComponentTicks,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self {
+        Self {
+            value,
+            ticks,
+            last_run,
+            this_run,
+        }
+    }
+
+    /// Checks if the component was added since the system last ran.
+    pub fn is_added(&self) -> bool {
+        self.ticks.added.is_newer_than(self.last_run, self.this_run)
+    }
+
+    /// Checks if the component was added or changed since the system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.ticks
+            .changed
+            .is_newer_than(self.last_run, self.this_run)
+    }
+
+    /// Converts this into a plain reference.
+    pub fn into_inner(self) -> &'a T {
+        self.value
+    }
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Ref<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Ref").field(&self.value).finish()
+    }
+}
+
+/// A query filter that only matches entities whose component of type `T` was added since the
+/// system last ran.
+pub struct Added<T>(PhantomData<fn() -> T>);
+
+/// A query filter that only matches entities whose component of type `T` was added or changed
+/// since the system last ran.
+pub struct Changed<T>(PhantomData<fn() -> T>);
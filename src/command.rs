@@ -0,0 +1,170 @@
+use crate::{
+    resource::Resource,
+    system::SystemParam,
+    world::{World, WorldCell},
+    Component, Context, EcsResult, EntityId,
+};
+
+/// A change to the world that was recorded to be applied later.
+type Command = Box<dyn FnOnce(&mut World) -> EcsResult<()> + Send + 'static>;
+
+/// A queue of recorded changes to the world.
+///
+/// Every system that takes `Commands` has its own queue, which the scheduler applies at sync
+/// points (see `Commands`).
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl CommandQueue {
+    /// Returns the number of queued commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Checks if no commands are queued.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Moves all commands of `other` to the end of this queue, leaving `other` empty.
+    pub(crate) fn append(&mut self, other: &mut CommandQueue) {
+        self.commands.append(&mut other.commands);
+    }
+
+    /// Applies all queued commands to the world, in the order they were recorded.
+    ///
+    /// Entities that were reserved are spawned first. Every command is applied even if one of
+    /// them fails; the first error is returned.
+    pub fn apply(&mut self, world: &mut World) -> EcsResult<()> {
+        let mut result = world.flush_entities();
+        for command in self.commands.drain(..) {
+            let applied = command(world);
+            if result.is_ok() {
+                result = applied;
+            }
+        }
+
+        result
+    }
+}
+
+impl std::fmt::Debug for CommandQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandQueue")
+            .field("len", &self.commands.len())
+            .finish()
+    }
+}
+
+/// A system parameter that records structural changes (spawning entities, inserting components,
+/// etc.) to be applied later.
+///
+/// This lets any system make structural changes, including systems that declared their access
+/// and systems that are iterating a query. The commands of every system in a schedule are
+/// applied when the schedule completes (in the order the systems ran), and before each
+/// exclusive system runs, so exclusive systems see the changes of the systems before them.
+pub struct Commands<'w, 's> {
+    queue: &'s mut CommandQueue,
+    world: WorldCell<'w>,
+}
+
+impl<'w, 's> Commands<'w, 's> {
+    /// Creates commands that are recorded to the queue.
+    pub(crate) fn new(queue: &'s mut CommandQueue, world: WorldCell<'w>) -> Self {
+        Self { queue, world }
+    }
+
+    /// Reserves the ID of a new entity, which is spawned (without any components) when the
+    /// commands are applied.
+    ///
+    /// The ID can be used by other commands right away.
+    pub fn reserve_entity(&mut self) -> EntityId {
+        // SAFETY: Reserving entities only needs a shared reference to the world
+        unsafe { self.world.world() }.reserve_entity()
+    }
+
+    /// Reserves a new entity, and returns an `EntityCommands` which is used to add components
+    /// to it.
+    pub fn spawn(&mut self) -> EntityCommands<'_, 'w, 's> {
+        let entity = self.reserve_entity();
+
+        EntityCommands {
+            entity,
+            commands: self,
+        }
+    }
+
+    /// Despawns the entity.
+    pub fn despawn(&mut self, entity: EntityId) {
+        self.add(move |world: &mut World| world.despawn(entity));
+    }
+
+    /// Adds a component to the entity, replacing any existing component of type `T`.
+    pub fn insert<T: Component>(&mut self, entity: EntityId, component: T) {
+        self.add(move |world: &mut World| world.insert(entity, component));
+    }
+
+    /// Removes the component of type `T` from the entity.
+    pub fn remove<T: Component>(&mut self, entity: EntityId) {
+        self.add(move |world: &mut World| world.remove::<T>(entity).map(|_| ()));
+    }
+
+    /// Inserts a resource into the world, replacing any existing value.
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) {
+        self.add(move |world: &mut World| {
+            world.insert_resource(resource);
+            Ok(())
+        });
+    }
+
+    /// Removes the resource of type `R` from the world.
+    pub fn remove_resource<R: Resource>(&mut self) {
+        self.add(|world: &mut World| {
+            world.remove_resource::<R>();
+            Ok(())
+        });
+    }
+
+    /// Records a custom command, which is given exclusive access to the world.
+    pub fn add(&mut self, command: impl FnOnce(&mut World) -> EcsResult<()> + Send + 'static) {
+        self.queue.commands.push(Box::new(command));
+    }
+}
+
+impl SystemParam for Commands<'_, '_> {
+    type State = CommandQueue;
+    type Item<'w, 's> = Commands<'w, 's>;
+
+    fn init_state() -> Self::State {
+        CommandQueue::default()
+    }
+
+    fn get_param<'w, 's>(state: &'s mut Self::State, ctx: &Context<'w>) -> Self::Item<'w, 's> {
+        Commands::new(state, ctx.world_cell())
+    }
+
+    fn apply(state: &mut Self::State, world: &mut World) -> EcsResult<()> {
+        state.apply(world)
+    }
+}
+
+/// Records commands for a single (possibly reserved) entity.
+pub struct EntityCommands<'c, 'w, 's> {
+    entity: EntityId,
+    commands: &'c mut Commands<'w, 's>,
+}
+
+impl EntityCommands<'_, '_, '_> {
+    /// Adds a component to the entity.
+    pub fn with<T: Component>(self, component: T) -> Self {
+        self.commands.insert(self.entity, component);
+        self
+    }
+
+    /// Returns the ID of the entity.
+    pub fn build(self) -> EntityId {
+        self.entity
+    }
+}
@@ -1,111 +1,442 @@
-use std::{
-    cell::{RefCell, RefMut},
-    rc::Rc,
+use std::any::TypeId;
+
+use crate::{
+    change_detection::Tick,
+    event::Event,
+    observer::Observer,
+    resource::{NonSend, NonSendMut, Res, ResMut, Resource, ResourceError},
+    schedule::{
+        self,
+        access::Access,
+        registry::{self, SystemId},
+        ScheduleId, ScheduleLabel,
+    },
+    state::{NextState, State, StateData, StateError, States},
+    time::{FixedTime, Time},
+    world::{World, WorldCell},
+    Component, EcsResult, EntityId, Query, QueryFilter, QueryParam,
 };
 
-use crate::{world::World, Component, ComponentId, EcsResult, EntityId, Query, QueryParam};
+pub struct Context<'w> {
+    world: WorldCell<'w>,
+
+    /// The access declared by the running system.
+    ///
+    /// `None` means the system has exclusive access to the world.
+    access: Option<&'w Access>,
+
+    /// The name of the running system.
+    system: &'w str,
+
+    /// The tick of the system's last run.
+    last_run: Tick,
 
-#[derive(Clone)]
-pub struct Context {
-    world: Rc<RefCell<World>>,
+    /// The tick of the current run.
+    this_run: Tick,
 }
 
-impl Context {
+impl<'w> Context<'w> {
     /// Creates a new context.
-    pub(crate) fn new(world: Rc<RefCell<World>>) -> Self {
-        Self { world }
+    pub(crate) fn new(
+        world: WorldCell<'w>,
+        access: Option<&'w Access>,
+        system: &'w str,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self {
+        Self {
+            world,
+            access,
+            system,
+            last_run,
+            this_run,
+        }
+    }
+
+    /// Reborrows the context for a shorter lifetime, so it can be passed on (e.g. to a run
+    /// condition) without giving up the original.
+    pub(crate) fn reborrow(&mut self) -> Context<'_> {
+        Context {
+            world: self.world,
+            access: self.access,
+            system: self.system,
+            last_run: self.last_run,
+            this_run: self.this_run,
+        }
+    }
+
+    /// Gets the tick of the system's last run, which change detection is relative to.
+    pub(crate) fn last_run(&self) -> Tick {
+        self.last_run
+    }
+
+    /// Gets the world the system is running on.
+    pub(crate) fn world_cell(&self) -> WorldCell<'w> {
+        self.world
+    }
+
+    /// Gets the name of the running system.
+    pub(crate) fn system_name(&self) -> &'w str {
+        self.system
+    }
+
+    /// Panics if the running system doesn't have exclusive access to the world.
+    ///
+    /// Systems that declared their access may run in parallel with other systems, so they can't
+    /// do anything that touches the whole world.
+    fn assert_exclusive(&self, operation: &str) {
+        if self.access.is_some() {
+            panic!(
+                "System `{}` can't {} since it declared its access",
+                self.system, operation
+            );
+        }
+    }
+
+    /// Gets a mutable reference to the world.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub(crate) fn world_mut(&mut self) -> &mut World {
+        self.assert_exclusive("access the world directly");
+
+        // SAFETY: The system has exclusive access to the world
+        unsafe { self.world.world_mut() }
     }
 
     /// Creates an `EntityBuilder` which is used to spawn an entity.
-    pub fn spawn(&mut self) -> EcsResult<EntityBuilder> {
-        let entity = self.world.borrow_mut().spawn_entity()?;
+    ///
+    /// Systems that declared their access (or are iterating a query) can spawn entities with
+    /// `Commands` instead.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub fn spawn(&mut self) -> EcsResult<EntityBuilder<'_>> {
+        self.assert_exclusive("spawn entities");
+
+        // SAFETY: The system has exclusive access to the world
+        let entity = unsafe { self.world.world_mut() }.spawn_entity()?;
+
+        Ok(EntityBuilder::new(self.world, entity))
+    }
+
+    /// Reserves the ID of an entity, which is spawned (without any components) at the next
+    /// flush (see `World::reserve_entity`).
+    ///
+    /// Unlike `spawn`, this can be used by systems that declared their access.
+    pub fn reserve_entity(&self) -> EntityId {
+        // SAFETY: Reserving entities only needs a shared reference to the world
+        unsafe { self.world.world() }.reserve_entity()
+    }
 
-        Ok(EntityBuilder::new(self.world.clone(), entity))
+    /// Triggers the event for the specified entity, immediately running its observers.
+    ///
+    /// See `World::trigger` for the order observers run in.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub fn trigger<E: Event>(&mut self, event: E, target: EntityId) -> EcsResult<()> {
+        self.world_mut().trigger(event, target)
     }
 
     /// Creates a `QueryBuilder` which is used to build a query.
-    pub fn query<'a, Params: QueryParam<'a>>(&'a mut self) -> Query<Params> {
-        // Get hash of all queried components combined
-        let query_hash = self
-            .world
-            .borrow_mut()
-            .get_component_hash(&Params::typeids());
-
-        // Get all associated archetype tables for the queried type
-        let world: RefMut<'a, World> = self.world.borrow_mut();
-        let mut associated_archetypes_hashes = world.get_associated_archetypes(query_hash);
-        let associated_archetypes =
-            if let Some(associated_archetypes_hashes) = &mut associated_archetypes_hashes {
-                // Get only unique archteypes
-                associated_archetypes_hashes.sort();
-                associated_archetypes_hashes.dedup();
-
-                associated_archetypes_hashes
-                    .iter()
-                    .map(|h| {
-                        world
-                            .get_archetype_table_mut(*h)
-                            .expect("Unable to get associated archetype table")
-                    })
-                    .collect::<Vec<_>>()
-            } else {
-                vec![world
-                    .get_archetype_table_mut(query_hash)
-                    .expect("Unable to get associated archetype table")]
-            };
-
-        let total_entities = associated_archetypes.iter().fold(0, |mut acc, table| {
-            acc += table.num_entities();
-            acc
-        });
-
-        Query::new(self.world.clone(), total_entities, associated_archetypes)
+    ///
+    /// `Ref` and `Mut` report the changes made since the system last ran.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access, but not for all of the queried
+    /// components.
+    pub fn query<'a, Params: QueryParam<'a>>(&'a mut self) -> Query<'a, Params> {
+        self.query_filtered()
+    }
+
+    /// Creates a query that only contains the entities that match the filter (e.g.
+    /// `Changed<T>`).
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access, but not for all of the queried (or
+    /// filtered) components.
+    pub fn query_filtered<'a, Params: QueryParam<'a>, F: QueryFilter>(
+        &'a mut self,
+    ) -> Query<'a, Params, F> {
+        if let Some(access) = self.access {
+            let readable = Params::typeids()
+                .into_iter()
+                .chain(F::typeids())
+                .all(|id| access.has_component_read(id));
+            let writable = Params::mut_typeids()
+                .into_iter()
+                .all(|id| access.has_component_write(id));
+
+            if !readable || !writable {
+                panic!(
+                    "System `{}` queried `{}` without declaring access to all of its components",
+                    self.system,
+                    std::any::type_name::<Params>()
+                );
+            }
+        }
+
+        Query::new(self.world, self.last_run, self.this_run)
+    }
+
+    /// Borrows the resource of type `R` immutably.
+    ///
+    /// Returns an error if the resource doesn't exist or is borrowed mutably (e.g. by the same
+    /// system).
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access, but not for the resource.
+    pub fn resource<R: Resource>(&self) -> EcsResult<Res<'w, R>> {
+        if let Some(access) = self.access {
+            if !access.has_resource_read(TypeId::of::<R>()) {
+                self.undeclared_resource::<R>();
+            }
+        }
+
+        // SAFETY: Borrows of resources are tracked
+        Ok(unsafe { self.world.world() }.resources().borrow()?)
+    }
+
+    /// Borrows the resource of type `R` mutably.
+    ///
+    /// Returns an error if the resource doesn't exist or is already borrowed.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access, but not write access to the resource.
+    pub fn resource_mut<R: Resource>(&self) -> EcsResult<ResMut<'w, R>> {
+        if let Some(access) = self.access {
+            if !access.has_resource_write(TypeId::of::<R>()) {
+                self.undeclared_resource::<R>();
+            }
+        }
+
+        // SAFETY: Borrows of resources are tracked
+        Ok(unsafe { self.world.world() }.resources().borrow_mut()?)
+    }
+
+    /// Panics because the system accessed a resource it didn't declare.
+    fn undeclared_resource<R: Resource>(&self) -> ! {
+        panic!(
+            "System `{}` accessed the resource `{}` without declaring it",
+            self.system,
+            std::any::type_name::<R>()
+        );
+    }
+
+    /// Checks if the world has a resource of type `R`.
+    pub fn contains_resource<R: Resource>(&self) -> bool {
+        // SAFETY: Only the keys of the resources are read
+        unsafe { self.world.world() }.contains_resource::<R>()
+    }
+
+    /// Checks if the world has a non-send resource of type `R`.
+    pub fn contains_non_send_resource<R: 'static>(&self) -> bool {
+        // SAFETY: Only the keys of the non-send resources are read
+        unsafe { self.world.world() }.contains_non_send_resource::<R>()
+    }
+
+    /// Inserts a resource into the world, replacing (and returning) any existing value.
+    ///
+    /// Returns an error if the existing value is borrowed.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> EcsResult<Option<R>> {
+        let world = self.world_mut();
+        Self::check_not_borrowed::<R>(world)?;
+        Ok(world.insert_resource(resource))
+    }
+
+    /// Inserts the default value of the resource `R`, unless the world already has one.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub fn init_resource<R: Resource + Default>(&mut self) {
+        self.world_mut().init_resource::<R>()
+    }
+
+    /// Removes the resource of type `R` from the world and returns it.
+    ///
+    /// Returns an error if the resource is borrowed.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub fn remove_resource<R: Resource>(&mut self) -> EcsResult<Option<R>> {
+        let world = self.world_mut();
+        Self::check_not_borrowed::<R>(world)?;
+        Ok(world.remove_resource())
+    }
+
+    /// Returns an error if the resource of type `R` is borrowed.
+    fn check_not_borrowed<R: Resource>(world: &World) -> Result<(), ResourceError> {
+        if world.resources().is_borrowed::<R>() {
+            return Err(ResourceError::AlreadyBorrowed(std::any::type_name::<R>()));
+        }
+        Ok(())
+    }
+
+    /// Borrows the non-send resource of type `R` immutably.
+    ///
+    /// Returns an error if the resource doesn't exist or is borrowed mutably.
+    ///
+    /// ## Panics
+    /// This will panic if the system isn't running on the thread that created the ECS. Systems
+    /// that take `NonSend` parameters (or that are configured with
+    /// `IntoSystemConfig::on_main_thread`) always run on that thread.
+    pub fn non_send_resource<R: 'static>(&self) -> EcsResult<NonSend<'w, R>> {
+        // SAFETY: Borrows of non-send resources are tracked
+        Ok(unsafe { self.world.world() }
+            .non_send_resources()
+            .borrow()?)
+    }
+
+    /// Borrows the non-send resource of type `R` mutably.
+    ///
+    /// Returns an error if the resource doesn't exist or is already borrowed.
+    ///
+    /// ## Panics
+    /// See `Context::non_send_resource`.
+    pub fn non_send_resource_mut<R: 'static>(&self) -> EcsResult<NonSendMut<'w, R>> {
+        // SAFETY: Borrows of non-send resources are tracked
+        Ok(unsafe { self.world.world() }
+            .non_send_resources()
+            .borrow_mut()?)
+    }
+
+    /// Returns the current tick and the virtual time that has elapsed.
+    pub fn time(&self) -> Time {
+        // SAFETY: The time is only modified between ticks
+        *unsafe { self.world.world() }.time()
+    }
+
+    /// Returns the state of the fixed timestep.
+    ///
+    /// Systems in the `FixedUpdate` schedule can use this to get the fixed delta, and systems
+    /// in the other schedules can use its `alpha` to interpolate between fixed steps.
+    pub fn fixed_time(&self) -> FixedTime {
+        // SAFETY: The fixed time is only modified between schedule runs
+        *unsafe { self.world.world() }.fixed_time()
+    }
+
+    /// Gets the current value of the state `S`.
+    pub fn state<S: States>(&self) -> EcsResult<State<S>> {
+        self.with_state_data(|data: &mut StateData<S>| data.current.clone())
+    }
+
+    /// Gets the queued next value of the state `S`.
+    pub fn next_state<S: States>(&self) -> EcsResult<NextState<S>> {
+        self.with_state_data(|data: &mut StateData<S>| data.next.clone())
+    }
+
+    /// Queues a transition of the state `S` to the specified value.
+    ///
+    /// The transition is applied the next time the `StateTransition` schedule is run.
+    pub fn set_next_state<S: States>(&mut self, state: S) -> EcsResult<()> {
+        self.with_state_data(|data: &mut StateData<S>| data.next.set(state))
+    }
+
+    /// Takes the queued next value of the state `S`.
+    pub(crate) fn take_next_state<S: States>(&self) -> EcsResult<Option<S>> {
+        self.with_state_data(|data: &mut StateData<S>| data.next.take())
+    }
+
+    /// Replaces the current value of the state `S`.
+    pub(crate) fn replace_state<S: States>(&self, state: S) -> EcsResult<()> {
+        self.with_state_data(|data: &mut StateData<S>| data.current = State(state))
+    }
+
+    /// Calls `f` with the current and next values of the state `S`.
+    fn with_state_data<S: States, R>(
+        &self,
+        f: impl FnOnce(&mut StateData<S>) -> R,
+    ) -> EcsResult<R> {
+        // SAFETY: States are behind a lock in the world
+        let world = unsafe { self.world.world() };
+
+        Ok(world
+            .with_state_data(f)
+            .ok_or(StateError::StateNotFound(std::any::type_name::<S>()))?)
+    }
+
+    /// Runs the schedule with the specified label to completion.
+    ///
+    /// This can be used to run custom schedules on demand.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub fn run_schedule<L: ScheduleLabel>(&mut self, label: L) -> EcsResult<()> {
+        self.assert_exclusive("run schedules");
+        schedule::run_schedule(self.world, &ScheduleId::of(&label))
+    }
+
+    /// Runs the registered system with the specified id, returning its result.
+    ///
+    /// ## Panics
+    /// This will panic if the system declared its access.
+    pub fn run_system(&mut self, id: SystemId) -> EcsResult<()> {
+        self.assert_exclusive("run systems");
+        registry::run_system(self.world, id)
+    }
+
+    /// Queues the registered system with the specified id to run once the current schedule
+    /// completes.
+    ///
+    /// Unlike `Context::run_system`, this can be used by any system. Errors returned by the
+    /// queued system are returned from the schedule.
+    pub fn run_system_deferred(&self, id: SystemId) {
+        // SAFETY: The queue is behind a lock
+        unsafe { self.world.world() }.registered_systems().queue(id);
+    }
+
+    /// Runs the schedule with the specified id to completion if it exists.
+    pub(crate) fn run_schedule_if_exists(&self, id: &ScheduleId) -> EcsResult<()> {
+        self.assert_exclusive("run schedules");
+
+        // SAFETY: The system has exclusive access to the world
+        if !unsafe { self.world.world() }.schedules().contains(id) {
+            return Ok(());
+        }
+
+        schedule::run_schedule(self.world, id)
     }
 }
 
 /// Builds an entity to be spawned by specifying the components to add to it.
-pub struct EntityBuilder {
+pub struct EntityBuilder<'w> {
     entity: EntityId,
-    world: Rc<RefCell<World>>,
-    component_ids: Vec<ComponentId>,
+    world: WorldCell<'w>,
 }
 
-impl EntityBuilder {
+impl<'w> EntityBuilder<'w> {
     /// Creates a new entity builder.
-    fn new(world: Rc<RefCell<World>>, entity: EntityId) -> Self {
-        Self {
-            entity,
-            world,
-            component_ids: vec![],
-        }
+    pub(crate) fn new(world: WorldCell<'w>, entity: EntityId) -> Self {
+        Self { entity, world }
     }
 
     /// Adds a component to the entity being built.
-    pub fn with<T: Component>(mut self, component: T) -> EcsResult<Self> {
-        self.world
-            .borrow_mut()
-            .add_component_to_entity(self.entity, component)?;
-
-        self.component_ids.push(ComponentId::of::<T>());
+    ///
+    /// `OnAdd` and `OnInsert` observers of the component are run before this returns.
+    pub fn with<T: Component>(self, component: T) -> EcsResult<Self> {
+        // SAFETY: Only systems with exclusive access to the world can spawn entities
+        unsafe { self.world.world_mut() }.insert(self.entity, component)?;
 
         Ok(self)
     }
 
+    /// Attaches an observer to the entity being built, which runs when the event `E` is
+    /// triggered for the entity (for a component of type `C`, or any component if `C` is `()`).
+    pub fn observe<E: Event, C: 'static>(self, observer: impl Observer<E, C>) -> Self {
+        // SAFETY: Only systems with exclusive access to the world can spawn entities
+        unsafe { self.world.world_mut() }
+            .observers_mut()
+            .add(Some(self.entity), observer);
+
+        self
+    }
+
     /// Spawns the entity and returns its ID.
-    ///
-    ///
-    /// This also updates the associated archetypes table for each of the components added.
     pub fn build(self) -> EntityId {
-        let ent_archetype_hash = self.world.borrow().get_entity_archetype_hash(self.entity);
-
-        // Add archetype hash to all components' associated archetype lists
-        for component_id in self.component_ids {
-            let component_hash = self.world.borrow_mut().get_component_hash(&[component_id]);
-            self.world
-                .borrow_mut()
-                .add_associated_archetype(component_hash, ent_archetype_hash);
-        }
-
         self.entity
     }
 }
@@ -1,68 +1,396 @@
-use std::{cell::RefCell, collections::hash_map::DefaultHasher, rc::Rc};
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    rc::Rc,
+    time::Duration,
+};
 
-use crate::{world::World, Context, EcsResult, System};
+use crate::{
+    event::{self, Event, Events},
+    observer::Observer,
+    plugin::{Plugin, PluginGroup},
+    resource::Resource,
+    schedule::{
+        self,
+        config::IntoSystemConfig,
+        edit,
+        error_policy::{ErrorPolicy, PanicPolicy},
+        executor::ExecutorKind,
+        registry::{RegisteredSystems, SystemId},
+        First, FixedUpdate, Last, PostUpdate, PreUpdate, ScheduleId, ScheduleLabel, Startup,
+        Update,
+    },
+    state::{ApplyStateTransition, StateTransition, States},
+    system::IntoSystem,
+    time::FixedTime,
+    world::{World, WorldCell},
+    EcsResult,
+};
 
-impl<F> System for F
-where
-    F: Fn(Context) -> EcsResult<()> + 'static,
-{
-    fn run(&mut self, ctx: Context) -> EcsResult<()> {
-        self(ctx)
-    }
+pub struct Ecs {
+    world: World,
+
+    /// The plugins that were added to the ECS.
+    plugins: Vec<Rc<dyn Plugin>>,
+
+    /// The number of plugins (from the start of `plugins`) that were already finished.
+    finished_plugins: usize,
+
+    /// Names of the plugins that were added to the ECS.
+    plugin_names: HashSet<String>,
+
+    /// Whether the `Startup` schedule has already been run.
+    started: bool,
 }
 
-struct Scheduler {
-    systems: Vec<Box<dyn System>>,
+impl Default for Ecs {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Scheduler {
-    /// Creates a new scheduler.
-    fn new() -> Self {
+impl Ecs {
+    /// Creates new Entity Component System.
+    pub fn new() -> Self {
+        let mut world = World::new(DefaultHasher::new());
+
+        // Create the built-in schedules so they can always be run, even when empty
+        for id in [
+            ScheduleId::of(&First),
+            ScheduleId::of(&Startup),
+            ScheduleId::of(&PreUpdate),
+            ScheduleId::of(&StateTransition),
+            ScheduleId::of(&FixedUpdate),
+            ScheduleId::of(&Update),
+            ScheduleId::of(&PostUpdate),
+            ScheduleId::of(&Last),
+        ] {
+            world.schedules_mut().entry(id);
+        }
+
         Self {
-            systems: Vec::new(),
+            world,
+            plugins: Vec::new(),
+            finished_plugins: 0,
+            plugin_names: HashSet::new(),
+            started: false,
         }
     }
 
-    /// Adds a system to the scheduler.
-    fn add_system<F: System>(&mut self, system: Box<F>) {
-        self.systems.push(system)
+    /// Adds a plugin to the ECS, which immediately registers its systems, resources, etc.
+    ///
+    /// ## Panics
+    /// This will panic if the plugin is unique (the default) and was already added.
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        self.add_boxed_plugin(Box::new(plugin))
     }
 
-    /// Runs all systems in the scheduler.
-    fn run(self, ctx: Context) -> EcsResult<()> {
-        for mut system in self.systems {
-            system.run(ctx.clone())?
+    /// Adds a group of plugins (or a tuple of plugins) to the ECS, in order.
+    ///
+    /// ## Panics
+    /// This will panic if any of the plugins is unique (the default) and was already added.
+    pub fn add_plugins(&mut self, plugins: impl PluginGroup) -> &mut Self {
+        for plugin in plugins.build().into_plugins() {
+            self.add_boxed_plugin(plugin);
         }
 
-        Ok(())
+        self
     }
-}
 
-pub struct Ecs {
-    world: World,
-    scheduler: Scheduler,
-}
+    /// Builds a plugin and keeps it around so it can be finished before the first tick.
+    ///
+    /// Plugins that are added after the first tick started are finished right away.
+    fn add_boxed_plugin(&mut self, plugin: Box<dyn Plugin>) -> &mut Self {
+        let name = plugin.name().to_string();
+        let first = self.plugin_names.insert(name.clone());
+        if plugin.is_unique() && !first {
+            panic!("Plugin `{}` was already added", name);
+        }
 
-impl Ecs {
-    /// Creates new Entity Component System.
-    pub fn new() -> Self {
-        Self {
-            world: World::new(DefaultHasher::new()),
-            scheduler: Scheduler::new(),
+        plugin.build(self);
+        self.plugins.push(plugin.into());
+
+        if self.started {
+            self.finish_plugins();
         }
+        self
+    }
+
+    /// Checks if a plugin with the specified name was added.
+    pub fn has_plugin(&self, name: &str) -> bool {
+        self.plugin_names.contains(name)
+    }
+
+    /// Runs the `finish` hook of every plugin that wasn't finished yet, in the order they were
+    /// added.
+    ///
+    /// Plugins that are added while finishing are finished too.
+    fn finish_plugins(&mut self) {
+        while let Some(plugin) = self.plugins.get(self.finished_plugins).cloned() {
+            self.finished_plugins += 1;
+            plugin.finish(self);
+        }
+    }
+
+    /// Inserts a resource into the world, replacing any existing value.
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+        self.world.insert_resource(resource);
+        self
+    }
+
+    /// Inserts the default value of the resource `R`, unless the world already has one.
+    pub fn init_resource<R: Resource + Default>(&mut self) -> &mut Self {
+        self.world.init_resource::<R>();
+        self
+    }
+
+    /// Removes the resource of type `R` from the world and returns it.
+    pub fn remove_resource<R: Resource>(&mut self) -> Option<R> {
+        self.world.remove_resource()
+    }
+
+    /// Inserts a non-send resource into the world, replacing any existing value.
+    ///
+    /// Non-send resources can only be accessed on the thread that created the ECS.
+    pub fn insert_non_send_resource<R: 'static>(&mut self, resource: R) -> &mut Self {
+        self.world.insert_non_send_resource(resource);
+        self
+    }
+
+    /// Inserts the default value of the non-send resource `R`, unless the world already has one.
+    pub fn init_non_send_resource<R: Default + 'static>(&mut self) -> &mut Self {
+        if !self.world.contains_non_send_resource::<R>() {
+            self.world.insert_non_send_resource(R::default());
+        }
+        self
+    }
+
+    /// Removes the non-send resource of type `R` from the world and returns it.
+    pub fn remove_non_send_resource<R: 'static>(&mut self) -> Option<R> {
+        self.world.remove_non_send_resource()
     }
 
     /// Adds a system to the ECS.
     ///
-    /// The scheduler will then run the system when `Ecs::run()` is called.
-    pub fn add_system<F: System>(mut self, system: F) -> Self {
-        self.scheduler.add_system(Box::new(system));
+    /// The system is added to the `Update` schedule, so the scheduler will run it every time
+    /// `Ecs::update()` (or `Ecs::run()`) is called.
+    pub fn add_system<M>(&mut self, system: impl IntoSystemConfig<M>) -> &mut Self {
+        self.add_system_to(Update, system)
+    }
+
+    /// Adds a system to the schedule with the specified label.
+    ///
+    /// The schedule is created if it doesn't exist yet; custom schedules are only run when
+    /// explicitly requested with `Context::run_schedule()`.
+    pub fn add_system_to<L: ScheduleLabel, M>(
+        &mut self,
+        label: L,
+        system: impl IntoSystemConfig<M>,
+    ) -> &mut Self {
+        self.world
+            .schedules_mut()
+            .entry(ScheduleId::of(&label))
+            .add_system(system.into_config());
+        self
+    }
+
+    /// Registers a system that is only run on demand, and returns its id.
+    ///
+    /// Registered systems aren't part of any schedule; they are run with `Ecs::run_system` or
+    /// `Context::run_system` (or queued with `Context::run_system_deferred`), and keep their state
+    /// between runs.
+    pub fn register_system<M>(
+        &mut self,
+        system: impl IntoSystem<(), EcsResult<()>, M>,
+    ) -> SystemId {
+        self.world.register_system(system)
+    }
+
+    /// Runs the registered system with the specified id, returning its result.
+    pub fn run_system(&mut self, id: SystemId) -> EcsResult<()> {
+        self.world.run_system(id)
+    }
+
+    /// Adds an empty schedule with the specified label (if it doesn't exist already).
+    pub fn add_schedule<L: ScheduleLabel>(&mut self, label: L) -> &mut Self {
+        self.world.schedules_mut().entry(ScheduleId::of(&label));
+        self
+    }
+
+    /// Gets an immutable reference to the world.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Gets a mutable reference to the world.
+    ///
+    /// This can be used to set up the world before running the ECS.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Sets the executor used to run the systems of every schedule.
+    ///
+    /// Defaults to `ExecutorKind::SingleThreaded`, which runs systems one at a time in a
+    /// deterministic order.
+    pub fn with_executor(&mut self, executor: ExecutorKind) -> &mut Self {
+        self.world.schedules_mut().set_executor(executor);
+        self
+    }
+
+    /// Sets how errors returned by systems are handled.
+    ///
+    /// Defaults to `ErrorPolicy::Abort`, which stops at the first error. Systems can override
+    /// this with `IntoSystemConfig::on_error`.
+    pub fn with_error_policy(&mut self, error_policy: ErrorPolicy) -> &mut Self {
+        self.world.schedules_mut().set_error_policy(error_policy);
+        self
+    }
+
+    /// Sets what happens when a system panics.
+    ///
+    /// Defaults to `PanicPolicy::Propagate`. With one of the catching policies, a panicking
+    /// system is turned into an `EcsError::SystemPanicked` (handled by its error policy), and
+    /// the world stays usable.
+    pub fn with_panic_policy(&mut self, panic_policy: PanicPolicy) -> &mut Self {
+        self.world.schedules_mut().set_panic_policy(panic_policy);
+        self
+    }
+
+    /// Adds the state `S` to the ECS with the specified initial value.
+    ///
+    /// Transitions queued with `Context::set_next_state` are applied in the `StateTransition`
+    /// schedule (which runs right after `PreUpdate`), running the `OnExit` schedule of the old
+    /// state and then the `OnEnter` schedule of the new one. The `OnEnter` schedule of the
+    /// initial state is run the first time transitions are processed.
+    ///
+    /// Does nothing if the state was already added.
+    pub fn add_state<S: States>(&mut self, initial: S) -> &mut Self {
+        if !self.world.insert_state(initial) {
+            return self;
+        }
+
+        self.add_system_to(StateTransition, ApplyStateTransition::<S>::new())
+    }
+
+    /// Adds an observer that runs whenever the event `E` is triggered for a component of type
+    /// `C` (or for any component if `C` is `()`).
+    ///
+    /// For example, an observer taking a `Trigger<OnInsert, Dead>` runs every time a `Dead`
+    /// component is inserted into an entity. Observers run in the order they were added.
+    pub fn add_observer<E: Event, C: 'static>(
+        &mut self,
+        observer: impl Observer<E, C>,
+    ) -> &mut Self {
+        self.world.add_observer(observer);
         self
     }
 
-    /// Runs the ECS; the scheduler will run all registered systems.
-    pub fn run(self) -> EcsResult<()> {
-        self.scheduler
-            .run(Context::new(Rc::new(RefCell::new(self.world))))
+    /// Adds storage for the events of type `E`, and a system in the `First` schedule that swaps
+    /// its buffers every tick.
+    ///
+    /// Adding the same event type again does nothing.
+    pub fn add_event<E: Event>(&mut self) -> &mut Self {
+        if self.world.contains_resource::<Events<E>>() {
+            return self;
+        }
+
+        self.insert_resource(Events::<E>::default()).add_system_to(
+            First,
+            event::update_events::<E>.writes_resource::<Events<E>>(),
+        )
+    }
+
+    /// Sets the duration of a single step of the `FixedUpdate` schedule (defaults to 60 Hz).
+    pub fn with_fixed_timestep(&mut self, timestep: Duration) -> &mut Self {
+        let mut fixed_time = FixedTime::new(timestep);
+        fixed_time.set_max_steps(self.world.fixed_time().max_steps());
+        *self.world.fixed_time_mut() = fixed_time;
+        self
+    }
+
+    /// Sets the maximum number of `FixedUpdate` steps that are run in a single tick.
+    ///
+    /// If more steps than this are owed (e.g. after a long frame), the extra steps are dropped
+    /// instead of being run in later ticks.
+    pub fn with_max_fixed_steps(&mut self, max_steps: u32) -> &mut Self {
+        self.world.fixed_time_mut().set_max_steps(max_steps);
+        self
+    }
+
+    /// Runs a single tick of the ECS, where `elapsed` is the real time since the last tick.
+    ///
+    /// The `First` schedule is run at the start of every tick. The `finish` hook of every plugin
+    /// and the `Startup` schedule are run after it the first time this is called, followed by the
+    /// `PreUpdate`, `StateTransition`, `FixedUpdate` (zero or more times), `Update`, `PostUpdate`
+    /// and `Last` schedules (in that order).
+    ///
+    /// Schedule changes queued with `World::add_system_to`, `World::remove_system`, etc. are
+    /// applied before and after the tick.
+    pub fn update(&mut self, elapsed: Duration) -> EcsResult<()> {
+        edit::apply_edits(&mut self.world)?;
+        self.world.time_mut().advance(elapsed);
+        self.run_schedule(&ScheduleId::of(&First))?;
+
+        if !self.started {
+            self.started = true;
+            self.finish_plugins();
+            self.run_schedule(&ScheduleId::of(&Startup))?;
+        }
+
+        self.run_schedule(&ScheduleId::of(&PreUpdate))?;
+        self.run_schedule(&ScheduleId::of(&StateTransition))?;
+        self.run_fixed_update(elapsed)?;
+        self.run_schedule(&ScheduleId::of(&Update))?;
+        self.run_schedule(&ScheduleId::of(&PostUpdate))?;
+        self.run_schedule(&ScheduleId::of(&Last))?;
+
+        // Changes made outside of systems from now on are seen by `World::query` next tick
+        self.world.end_change_detection_tick();
+        self.world.update_removed_components();
+        edit::apply_edits(&mut self.world)
+    }
+
+    /// Runs the `FixedUpdate` schedule once for every whole timestep that has elapsed.
+    fn run_fixed_update(&mut self, elapsed: Duration) -> EcsResult<()> {
+        self.world.fixed_time_mut().accumulate(elapsed);
+        let max_steps = self.world.fixed_time().max_steps();
+
+        let mut steps = 0;
+        loop {
+            if steps == max_steps {
+                self.world.fixed_time_mut().discard_overflow();
+                break;
+            }
+            if !self.world.fixed_time_mut().expend() {
+                break;
+            }
+
+            self.run_schedule(&ScheduleId::of(&FixedUpdate))?;
+            steps += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the schedule with the specified id.
+    fn run_schedule(&mut self, id: &ScheduleId) -> EcsResult<()> {
+        schedule::run_schedule(WorldCell::new(&mut self.world), id)
+    }
+
+    /// Runs the ECS; the scheduler will run a single tick of all registered schedules.
+    pub fn run(&mut self) -> EcsResult<()> {
+        self.update(Duration::ZERO)
+    }
+}
+
+impl Drop for Ecs {
+    /// Shuts down every initialized system: the systems of each schedule (sorted by label, in the
+    /// order they were added), followed by the registered systems.
+    fn drop(&mut self) {
+        for mut schedule in self.world.schedules_mut().take_all() {
+            schedule.shutdown(&mut self.world);
+        }
+
+        RegisteredSystems::shutdown_all(&mut self.world);
     }
 }
@@ -0,0 +1,257 @@
+use std::{any::TypeId, fmt::Debug};
+
+use crate::{
+    resource::{Res, ResMut, Resource, ResourceError},
+    schedule::access::Access,
+    system::SystemParam,
+    Context, EcsResult,
+};
+
+/// A message that systems can send to each other, or trigger for an entity.
+///
+/// Events must be `Send + Sync` since they're stored in a resource.
+pub trait Event: Send + Sync + 'static {
+    /// Whether triggers of the event propagate to the parent of the entity they were triggered
+    /// for by default (observers can change this with `Trigger::propagate`).
+    const PROPAGATE: bool = false;
+}
+
+/// Double-buffered storage for the events of type `E`.
+///
+/// Events are kept for two ticks (the buffers are swapped at the start of every tick by
+/// `Ecs::add_event`), so every reader sees every event exactly once, whether it runs before or
+/// after the system that sent it.
+pub struct Events<E: Event> {
+    /// Events sent during the previous tick.
+    older: Vec<E>,
+
+    /// The id of the first event in `older`.
+    older_start: usize,
+
+    /// Events sent during the current tick.
+    newer: Vec<E>,
+
+    /// The id of the first event in `newer`.
+    newer_start: usize,
+
+    /// The total number of events that have been sent (which is the id of the next event).
+    event_count: usize,
+}
+
+impl<E: Event> Resource for Events<E> {}
+
+impl<E: Event> Default for Events<E> {
+    fn default() -> Self {
+        Self {
+            older: Vec::new(),
+            older_start: 0,
+            newer: Vec::new(),
+            newer_start: 0,
+            event_count: 0,
+        }
+    }
+}
+
+impl<E: Event> Events<E> {
+    /// Sends an event.
+    pub fn send(&mut self, event: E) {
+        self.newer.push(event);
+        self.event_count += 1;
+    }
+
+    /// Sends all events in the iterator, in order.
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = E>) {
+        for event in events {
+            self.send(event);
+        }
+    }
+
+    /// Swaps the buffers, dropping the events that were sent before the previous tick.
+    ///
+    /// This is called at the start of every tick for events added with `Ecs::add_event`.
+    pub fn update(&mut self) {
+        self.older = std::mem::take(&mut self.newer);
+        self.older_start = self.newer_start;
+        self.newer_start = self.event_count;
+    }
+
+    /// Removes all stored events and returns them, oldest first.
+    ///
+    /// Readers won't see the drained events, even if they haven't read them yet.
+    pub fn drain(&mut self) -> impl Iterator<Item = E> + '_ {
+        self.older_start = self.event_count;
+        self.newer_start = self.event_count;
+        self.older.drain(..).chain(self.newer.drain(..))
+    }
+
+    /// Removes all stored events.
+    pub fn clear(&mut self) {
+        self.older.clear();
+        self.newer.clear();
+        self.older_start = self.event_count;
+        self.newer_start = self.event_count;
+    }
+
+    /// Returns the number of stored events.
+    pub fn len(&self) -> usize {
+        self.older.len() + self.newer.len()
+    }
+
+    /// Checks if no events are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total number of events that have been sent (which is the id of the next
+    /// event).
+    pub(crate) fn event_count(&self) -> usize {
+        self.event_count
+    }
+
+    /// Iterates over the stored events with an id of at least `cursor`, oldest first.
+    pub(crate) fn events_since(&self, cursor: usize) -> impl Iterator<Item = &E> {
+        let older = self
+            .older
+            .iter()
+            .skip(cursor.saturating_sub(self.older_start));
+        let newer = self
+            .newer
+            .iter()
+            .skip(cursor.saturating_sub(self.newer_start));
+
+        older.chain(newer)
+    }
+
+    /// Returns the number of stored events with an id of at least `cursor`.
+    pub(crate) fn len_since(&self, cursor: usize) -> usize {
+        self.event_count - cursor.max(self.older_start)
+    }
+}
+
+impl<E: Event> Debug for Events<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Events")
+            .field("len", &self.len())
+            .field("event_count", &self.event_count)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A system parameter that sends events of type `E`.
+///
+/// Systems that declare their access get write access to `Events<E>` for the parameter.
+pub struct EventWriter<'w, E: Event> {
+    events: ResMut<'w, Events<E>>,
+}
+
+impl<E: Event> EventWriter<'_, E> {
+    /// Sends an event.
+    pub fn send(&mut self, event: E) {
+        self.events.send(event);
+    }
+
+    /// Sends all events in the iterator, in order.
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = E>) {
+        self.events.send_batch(events);
+    }
+}
+
+impl<E: Event> SystemParam for EventWriter<'_, E> {
+    type State = ();
+    type Item<'w, 's> = EventWriter<'w, E>;
+
+    fn init_state() -> Self::State {}
+
+    fn access(access: &mut Access) {
+        access.add_resource_write(TypeId::of::<Events<E>>());
+    }
+
+    fn validate(ctx: &Context) -> EcsResult<()> {
+        validate_events::<E>(ctx)
+    }
+
+    fn get_param<'w, 's>(_state: &'s mut Self::State, ctx: &Context<'w>) -> Self::Item<'w, 's> {
+        let events = ctx
+            .resource_mut()
+            .unwrap_or_else(|error| panic!("System `{}` failed: {}", ctx.system_name(), error));
+
+        EventWriter { events }
+    }
+}
+
+/// A system parameter that reads events of type `E`.
+///
+/// Each reader keeps its own cursor, so it only sees the events that were sent since it last
+/// ran. Systems that declare their access get read access to `Events<E>` for the parameter.
+pub struct EventReader<'w, 's, E: Event> {
+    events: Res<'w, Events<E>>,
+
+    /// The id of the first event the reader hasn't read yet.
+    cursor: &'s mut usize,
+}
+
+impl<E: Event> EventReader<'_, '_, E> {
+    /// Iterates over the events that haven't been read yet, oldest first, and marks them as
+    /// read.
+    pub fn read(&mut self) -> impl Iterator<Item = &E> {
+        let cursor = std::mem::replace(self.cursor, self.events.event_count);
+        self.events.events_since(cursor)
+    }
+
+    /// Returns the number of events that haven't been read yet.
+    pub fn len(&self) -> usize {
+        self.events.len_since(*self.cursor)
+    }
+
+    /// Checks if all events have been read.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Marks all events as read.
+    pub fn clear(&mut self) {
+        *self.cursor = self.events.event_count;
+    }
+}
+
+impl<E: Event> SystemParam for EventReader<'_, '_, E> {
+    type State = usize;
+    type Item<'w, 's> = EventReader<'w, 's, E>;
+
+    fn init_state() -> Self::State {
+        0
+    }
+
+    fn access(access: &mut Access) {
+        access.add_resource_read(TypeId::of::<Events<E>>());
+    }
+
+    fn validate(ctx: &Context) -> EcsResult<()> {
+        validate_events::<E>(ctx)
+    }
+
+    fn get_param<'w, 's>(state: &'s mut Self::State, ctx: &Context<'w>) -> Self::Item<'w, 's> {
+        let events = ctx
+            .resource()
+            .unwrap_or_else(|error| panic!("System `{}` failed: {}", ctx.system_name(), error));
+
+        EventReader {
+            events,
+            cursor: state,
+        }
+    }
+}
+
+/// Returns an error if the events of type `E` weren't added (see `Ecs::add_event`).
+fn validate_events<E: Event>(ctx: &Context) -> EcsResult<()> {
+    if !ctx.contains_resource::<Events<E>>() {
+        return Err(ResourceError::ResourceNotFound(std::any::type_name::<Events<E>>()).into());
+    }
+    Ok(())
+}
+
+/// A system that swaps the buffers of the events of type `E`.
+pub(crate) fn update_events<E: Event>(ctx: Context) -> EcsResult<()> {
+    ctx.resource_mut::<Events<E>>()?.update();
+    Ok(())
+}
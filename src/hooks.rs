@@ -0,0 +1,90 @@
+use crate::{
+    command::{CommandQueue, Commands},
+    resource::Resource,
+    world::{World, WorldCell},
+    Component, EcsResult, EntityId,
+};
+
+/// A lifecycle hook of a component type (see `Component::on_add`).
+pub(crate) type ComponentHook = fn(DeferredWorld, EntityId) -> EcsResult<()>;
+
+/// The lifecycle hooks of a component type.
+///
+/// Every component table keeps the hooks of its component type, so they can be run when the
+/// type of a component isn't known (e.g. when an entity is despawned).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ComponentHooks {
+    pub(crate) on_add: ComponentHook,
+    pub(crate) on_insert: ComponentHook,
+    pub(crate) on_replace: ComponentHook,
+    pub(crate) on_remove: ComponentHook,
+}
+
+impl ComponentHooks {
+    /// Gets the hooks of the component type `T`.
+    pub(crate) fn of<T: Component>() -> Self {
+        Self {
+            on_add: T::on_add,
+            on_insert: T::on_insert,
+            on_replace: T::on_replace,
+            on_remove: T::on_remove,
+        }
+    }
+}
+
+/// Access to the world from component hooks.
+///
+/// Hooks run in the middle of structural changes, so they can read and change component values
+/// and resources, but entities, components and resources can only be added or removed through
+/// `commands`. The commands are applied once the structural change (and its observers) have
+/// finished.
+pub struct DeferredWorld<'w> {
+    world: &'w mut World,
+
+    /// The commands recorded by the hook, which are handed to the world when it returns.
+    queue: CommandQueue,
+}
+
+impl<'w> DeferredWorld<'w> {
+    /// Creates deferred access to the world.
+    pub(crate) fn new(world: &'w mut World) -> Self {
+        Self {
+            world,
+            queue: CommandQueue::default(),
+        }
+    }
+
+    /// Gets an immutable reference to the component of type `T` for the specified entity.
+    pub fn get<T: Component>(&self, entity: EntityId) -> EcsResult<Option<&T>> {
+        self.world.get(entity)
+    }
+
+    /// Gets a mutable reference to the component of type `T` for the specified entity.
+    pub fn get_mut<T: Component>(&mut self, entity: EntityId) -> EcsResult<Option<&mut T>> {
+        self.world.get_mut(entity)
+    }
+
+    /// Gets an immutable reference to the resource of type `R`.
+    pub fn resource<R: Resource>(&self) -> Option<&R> {
+        self.world.resource()
+    }
+
+    /// Gets a mutable reference to the resource of type `R`.
+    pub fn resource_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        self.world.resource_mut()
+    }
+
+    /// Records structural changes, which are applied once the current structural change has
+    /// finished.
+    pub fn commands(&mut self) -> Commands<'_, '_> {
+        Commands::new(&mut self.queue, WorldCell::new(self.world))
+    }
+}
+
+impl Drop for DeferredWorld<'_> {
+    fn drop(&mut self) {
+        if !self.queue.is_empty() {
+            self.world.queue_commands(&mut self.queue);
+        }
+    }
+}
@@ -1,15 +1,58 @@
 #![allow(dead_code)] // FIXME: Remove after public API is set
 
-use std::any::TypeId;
+use std::{any::TypeId, borrow::Cow};
 
+use schedule::access::Access;
+
+mod change_detection;
+mod command;
 mod context;
 mod ecs;
+mod event;
+mod hooks;
+mod observer;
+mod plugin;
 mod query;
 mod query_params;
+mod removal_detection;
+mod resource;
+mod schedule;
+mod state;
 mod storage;
+mod system;
+mod time;
 mod world;
 
-pub use {context::Context, ecs::Ecs, query::Query, query_params::QueryParam};
+pub use {
+    change_detection::{Added, Changed, Mut, Ref},
+    command::{CommandQueue, Commands, EntityCommands},
+    context::{Context, EntityBuilder},
+    ecs::Ecs,
+    event::{Event, EventReader, EventWriter, Events},
+    hooks::DeferredWorld,
+    observer::{Observer, OnAdd, OnInsert, OnRemove, Parent, Trigger},
+    plugin::{Plugin, PluginGroup, PluginGroupBuilder},
+    query::{Budget, Query, QueryCursor},
+    query_params::{QueryFilter, QueryParam},
+    removal_detection::RemovedComponents,
+    resource::{NonSend, NonSendMut, Res, ResMut, Resource, ResourceError},
+    schedule::{
+        config::{Condition, IntoSystemConfig, SystemConfig},
+        error_policy::{ErrorHandler, ErrorPolicy, PanicPolicy},
+        executor::ExecutorKind,
+        registry::SystemId,
+        run_rate::RunRate,
+        First, FixedUpdate, Last, PostUpdate, PreUpdate, ScheduleError, ScheduleLabel, Startup,
+        Update,
+    },
+    state::{in_state, NextState, OnEnter, OnExit, State, StateTransition, States},
+    system::{
+        ExclusiveSystemMarker, FunctionSystemMarker, In, IntoSystem, Local, PipeSystem,
+        SystemParam, SystemParamFunction, SystemParamItem,
+    },
+    time::{FixedTime, Time},
+    world::{World, WorldError},
+};
 
 /// An entity in the ECS.
 ///
@@ -20,11 +63,91 @@ pub(crate) type EntityId = usize;
 pub(crate) type ComponentId = TypeId;
 
 /// A component in the ECS.
-pub trait Component: 'static {}
+///
+/// Components must be `Send + Sync` since systems that access them may run on other threads.
+///
+/// Component types can define lifecycle hooks, which run whenever a component of the type is
+/// added to, replaced on or removed from any entity (right as it happens, before any observers).
+/// Hooks are given deferred access to the world; an error returned by a hook is returned by the
+/// operation that ran it, once the operation has completed.
+pub trait Component: Send + Sync + 'static {
+    /// Runs after the component is added to an entity that didn't have one.
+    fn on_add(_world: DeferredWorld, _entity: EntityId) -> EcsResult<()> {
+        Ok(())
+    }
+
+    /// Runs after the component is inserted into an entity (whether it was added or replaced
+    /// an existing value), after `on_add`.
+    fn on_insert(_world: DeferredWorld, _entity: EntityId) -> EcsResult<()> {
+        Ok(())
+    }
+
+    /// Runs before the value of the component is replaced or removed (so the old value can
+    /// still be read).
+    fn on_replace(_world: DeferredWorld, _entity: EntityId) -> EcsResult<()> {
+        Ok(())
+    }
+
+    /// Runs before the component is removed from an entity (or the entity is despawned), after
+    /// `on_replace`.
+    fn on_remove(_world: DeferredWorld, _entity: EntityId) -> EcsResult<()> {
+        Ok(())
+    }
+}
 
 /// A system to be run by the ECS.
-pub trait System: 'static {
-    fn run(&mut self, ctx: Context) -> EcsResult<()>;
+///
+/// Systems must be `Send` since the multi-threaded executor may run them on other threads.
+/// Only systems without an input that return `EcsResult<()>` can be added to schedules; other
+/// systems can be piped into them.
+pub trait System: Send + 'static {
+    /// The input passed to the system when it's run (`()` for systems without an input).
+    type In;
+
+    /// The value returned by the system.
+    type Out;
+
+    fn run(&mut self, input: Self::In, ctx: Context) -> Self::Out;
+
+    /// The name of the system (defaults to its type name).
+    fn name(&self) -> Cow<'static, str> {
+        std::any::type_name::<Self>().into()
+    }
+
+    /// Called once before the system first runs (when its schedule is built, or when it's
+    /// registered), e.g. to insert the resources it needs.
+    fn initialize(&mut self, _world: &mut World) {}
+
+    /// Called when the ECS is dropped (or the system is removed), if the system was initialized.
+    ///
+    /// This can be used to release external handles deterministically.
+    fn shutdown(&mut self, _world: &mut World) {}
+
+    /// Applies the changes the system deferred while it ran (e.g. with `Commands`).
+    ///
+    /// The scheduler calls this at sync points, when no other system is running.
+    fn apply_deferred(&mut self, _world: &mut World) -> EcsResult<()> {
+        Ok(())
+    }
+
+    /// Adds the components and resources the system's parameters access (e.g. `Events<E>` for
+    /// an `EventWriter<E>`) to the access the system declared.
+    fn param_access(&self, _access: &mut Access) {}
+
+    /// Checks that the system's parameters can be created (e.g. that the resources they borrow
+    /// exist) before the system is run.
+    ///
+    /// Errors are handled by the system's error policy like the errors it returns.
+    fn validate_params(&self, _ctx: &Context) -> EcsResult<()> {
+        Ok(())
+    }
+
+    /// Whether the system may run on threads other than the one that created the ECS.
+    ///
+    /// Systems that access non-send resources return `false`, and are always run on that thread.
+    fn is_send(&self) -> bool {
+        true
+    }
 }
 
 /// Possible errors returned from the ECS.
@@ -35,6 +158,25 @@ pub enum EcsError {
 
     #[error("StorageError: {0}")]
     StorageError(#[from] storage::StorageError),
+
+    #[error("ScheduleError: {0}")]
+    ScheduleError(#[from] schedule::ScheduleError),
+
+    #[error("StateError: {0}")]
+    StateError(#[from] state::StateError),
+
+    #[error("ResourceError: {0}")]
+    ResourceError(#[from] resource::ResourceError),
+
+    #[error("System `{system}` panicked: {message}")]
+    SystemPanicked { system: String, message: String },
+
+    #[error("System `{system}` failed in the schedule {schedule}: {source}")]
+    SystemFailed {
+        system: String,
+        schedule: String,
+        source: Box<EcsError>,
+    },
 }
 
 /// Result type returned by the ECS.
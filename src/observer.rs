@@ -0,0 +1,424 @@
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{event::Event, world::World, Component, ComponentId, EcsResult, EntityId};
+
+/// Triggered when a component is added to an entity that didn't have one of its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OnAdd;
+impl Event for OnAdd {}
+
+/// Triggered every time a component is inserted into an entity (whether it's added or replaces
+/// an existing value).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OnInsert;
+impl Event for OnInsert {}
+
+/// Triggered when a component is removed from an entity, including when the entity is
+/// despawned.
+///
+/// Observers run after the component was removed, so they can't access its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OnRemove;
+impl Event for OnRemove {}
+
+/// The parent of an entity.
+///
+/// Triggers that propagate are passed from an entity to its parent, and so on up the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Parent(pub EntityId);
+impl Component for Parent {}
+
+/// The trigger that an observer is run for.
+///
+/// `E` is the event that was triggered, and `C` is the component type the observer watches
+/// (`()` to watch all component types, or for events that aren't about components).
+pub struct Trigger<'a, E: Event, C: 'static = ()> {
+    event: &'a mut E,
+
+    /// The entity the event was triggered for.
+    target: EntityId,
+
+    /// The entity the trigger has propagated to (the target, unless it propagated).
+    entity: EntityId,
+
+    /// Whether the trigger will propagate to the parent of `entity`.
+    propagate: &'a mut bool,
+
+    _marker: PhantomData<fn() -> C>,
+}
+
+impl<E: Event, C: 'static> Trigger<'_, E, C> {
+    /// Gets the event that was triggered.
+    pub fn event(&self) -> &E {
+        self.event
+    }
+
+    /// Gets a mutable reference to the event, so observers can pass data to the ones that run
+    /// after them.
+    pub fn event_mut(&mut self) -> &mut E {
+        self.event
+    }
+
+    /// Gets the entity that the trigger is currently at.
+    ///
+    /// This is the entity the event was triggered for, or one of its ancestors if the trigger
+    /// propagated.
+    pub fn entity(&self) -> EntityId {
+        self.entity
+    }
+
+    /// Gets the entity that the event was originally triggered for.
+    pub fn target(&self) -> EntityId {
+        self.target
+    }
+
+    /// Sets whether the trigger propagates to the parent of the current entity once all of its
+    /// observers have run (defaults to `Event::PROPAGATE`).
+    pub fn propagate(&mut self, propagate: bool) {
+        *self.propagate = propagate;
+    }
+
+    /// Stops the trigger from propagating to the parent of the current entity.
+    ///
+    /// The remaining observers of the current entity still run.
+    pub fn stop_propagation(&mut self) {
+        self.propagate(false);
+    }
+}
+
+/// Logic that runs whenever the event `E` is triggered (for a component of type `C`).
+///
+/// Observers run with exclusive access to the world, so they can make structural changes or
+/// trigger other events; these are handled once the current observers have finished.
+pub trait Observer<E: Event, C: 'static = ()>: Send + 'static {
+    fn observe(&mut self, trigger: Trigger<E, C>, world: &mut World) -> EcsResult<()>;
+}
+
+impl<E, C, F> Observer<E, C> for F
+where
+    E: Event,
+    C: 'static,
+    F: FnMut(Trigger<E, C>, &mut World) -> EcsResult<()> + Send + 'static,
+{
+    fn observe(&mut self, trigger: Trigger<E, C>, world: &mut World) -> EcsResult<()> {
+        self(trigger, world)
+    }
+}
+
+/// A trigger with its event type erased.
+struct ErasedTrigger<'a> {
+    event: &'a mut dyn Any,
+    target: EntityId,
+    entity: EntityId,
+    propagate: &'a mut bool,
+}
+
+/// An observer with its event type erased.
+type BoxedObserver = Box<dyn FnMut(ErasedTrigger, &mut World) -> EcsResult<()> + Send + 'static>;
+
+/// Identifies the triggers that an observer is run for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ObserverKey {
+    event: TypeId,
+
+    /// The component type the observer watches (`None` to watch all component types).
+    component: Option<ComponentId>,
+
+    /// The entity the observer is attached to (`None` for global observers).
+    entity: Option<EntityId>,
+}
+
+/// An observer along with the triggers it's run for.
+struct ObserverEntry {
+    key: ObserverKey,
+    observer: BoxedObserver,
+}
+
+/// An event that was triggered, but hasn't been passed to observers yet.
+struct PendingTrigger {
+    event: Box<dyn Any + Send>,
+    event_type: TypeId,
+    target: EntityId,
+
+    /// The component types the event was triggered for (empty for events that aren't about
+    /// components).
+    components: Vec<ComponentId>,
+
+    /// Whether the trigger propagates to the parent of the entity it's at.
+    propagate: bool,
+}
+
+impl PendingTrigger {
+    /// Checks if the observer with the specified key should be run for this trigger while it's
+    /// at `entity`.
+    fn matches(&self, key: &ObserverKey, entity: EntityId) -> bool {
+        key.event == self.event_type
+            && key
+                .component
+                .is_none_or(|component| self.components.contains(&component))
+            && key.entity.is_none_or(|observed| observed == entity)
+    }
+}
+
+/// The observers of the world, and the triggers waiting to be passed to them.
+#[derive(Default)]
+pub(crate) struct Observers {
+    /// The observers, in the order they were added.
+    entries: Vec<ObserverEntry>,
+
+    /// The number of observers of each event (and component type), so triggers that no observer
+    /// watches aren't queued.
+    counts: HashMap<(TypeId, Option<ComponentId>), usize>,
+
+    /// Triggers waiting to be passed to observers, oldest first.
+    pending: VecDeque<PendingTrigger>,
+
+    /// Despawned entities whose observers are removed once the pending triggers are handled.
+    despawned: Vec<EntityId>,
+
+    /// Whether the pending triggers are currently being passed to observers.
+    flushing: bool,
+}
+
+impl Observers {
+    /// Adds an observer that runs whenever `E` is triggered (for a component of type `C`),
+    /// optionally only for the specified entity.
+    pub(crate) fn add<E: Event, C: 'static>(
+        &mut self,
+        entity: Option<EntityId>,
+        mut observer: impl Observer<E, C>,
+    ) {
+        let component = (TypeId::of::<C>() != TypeId::of::<()>()).then(TypeId::of::<C>);
+        let key = ObserverKey {
+            event: TypeId::of::<E>(),
+            component,
+            entity,
+        };
+
+        let observer: BoxedObserver = Box::new(move |trigger, world| {
+            let trigger = Trigger {
+                event: trigger
+                    .event
+                    .downcast_mut()
+                    .expect("Trigger has the wrong type"),
+                target: trigger.target,
+                entity: trigger.entity,
+                propagate: trigger.propagate,
+                _marker: PhantomData,
+            };
+            observer.observe(trigger, world)
+        });
+
+        self.entries.push(ObserverEntry { key, observer });
+        *self.counts.entry((key.event, key.component)).or_default() += 1;
+    }
+
+    /// Checks if any observer watches `E` for a component of any of the specified types.
+    fn is_observed<E: Event>(&self, components: &[ComponentId]) -> bool {
+        let event = TypeId::of::<E>();
+        let observed = |component| self.counts.contains_key(&(event, component));
+
+        observed(None) || components.iter().any(|&id| observed(Some(id)))
+    }
+
+    /// Queues `E` to be triggered for an entity, if any observer watches it.
+    pub(crate) fn queue_event<E: Event>(&mut self, event: E, target: EntityId) {
+        if !self.is_observed::<E>(&[]) {
+            return;
+        }
+
+        self.pending.push_back(PendingTrigger {
+            event: Box::new(event),
+            event_type: TypeId::of::<E>(),
+            target,
+            components: Vec::new(),
+            propagate: E::PROPAGATE,
+        });
+    }
+
+    /// Queues `E` to be triggered for the specified components of an entity, if any observer
+    /// watches it.
+    pub(crate) fn queue_component_event<E: Event>(
+        &mut self,
+        event: E,
+        target: EntityId,
+        components: Vec<ComponentId>,
+    ) {
+        if components.is_empty() || !self.is_observed::<E>(&components) {
+            return;
+        }
+
+        self.pending.push_back(PendingTrigger {
+            event: Box::new(event),
+            event_type: TypeId::of::<E>(),
+            target,
+            components,
+            propagate: E::PROPAGATE,
+        });
+    }
+
+    /// Removes the observers attached to the entity once the pending triggers are handled.
+    pub(crate) fn entity_despawned(&mut self, entity: EntityId) {
+        // While flushing, the observers are taken out of the world (see `flush`), so the entity
+        // always has to be recorded
+        if self.flushing
+            || self
+                .entries
+                .iter()
+                .any(|entry| entry.key.entity == Some(entity))
+        {
+            self.despawned.push(entity);
+        }
+    }
+
+    /// Removes the observers attached to despawned entities.
+    fn remove_despawned(&mut self) {
+        if self.despawned.is_empty() {
+            return;
+        }
+
+        let despawned = std::mem::take(&mut self.despawned);
+        let counts = &mut self.counts;
+        self.entries.retain(|entry| {
+            if !entry
+                .key
+                .entity
+                .is_some_and(|entity| despawned.contains(&entity))
+            {
+                return true;
+            }
+
+            let count_key = (entry.key.event, entry.key.component);
+            if let Some(count) = counts.get_mut(&count_key) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&count_key);
+                }
+            }
+            false
+        });
+    }
+}
+
+impl std::fmt::Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Observers")
+            .field("num_observers", &self.entries.len())
+            .field("num_pending", &self.pending.len())
+            .finish()
+    }
+}
+
+/// Passes all pending triggers to the observers that watch them.
+///
+/// Triggers are handled in the order they were queued, and the observers of each trigger run in
+/// the order they were added. Triggers queued by observers (e.g. because they inserted a
+/// component) are handled after the ones that were already pending, so observers never run
+/// re-entrantly. Once all triggers are handled, the commands queued by component hooks are
+/// applied (and the triggers they cause are handled the same way).
+///
+/// Every trigger is handled even if an observer fails; the first error is returned.
+pub(crate) fn flush(world: &mut World) -> EcsResult<()> {
+    // Observers that make structural changes end up here again, but the outer call will handle
+    // the triggers they queued
+    if world.observers().flushing {
+        return Ok(());
+    }
+    world.observers_mut().flushing = true;
+
+    let mut result = Ok(());
+    loop {
+        let run = match world.observers_mut().pending.pop_front() {
+            Some(mut trigger) => {
+                // Observers are taken out of the world while they run, so they can be given
+                // `&mut World`
+                let mut entries = std::mem::take(&mut world.observers_mut().entries);
+
+                let run = panic::catch_unwind(AssertUnwindSafe(|| {
+                    run_observers(&mut entries, &mut trigger, world)
+                }));
+
+                // Keep any observers that were added while running after the existing ones
+                let observers = world.observers_mut();
+                entries.append(&mut observers.entries);
+                observers.entries = entries;
+                run
+            }
+            None => {
+                let mut commands = world.take_queued_commands();
+                if commands.is_empty() {
+                    break;
+                }
+
+                panic::catch_unwind(AssertUnwindSafe(|| commands.apply(world)))
+            }
+        };
+
+        match run {
+            Ok(observed) => {
+                if result.is_ok() {
+                    result = observed;
+                }
+            }
+            Err(payload) => {
+                world.observers_mut().flushing = false;
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+
+    let observers = world.observers_mut();
+    observers.remove_despawned();
+    observers.flushing = false;
+    result
+}
+
+/// Runs the observers that match the trigger, starting at its target and following the chain of
+/// parents for as long as the trigger propagates.
+///
+/// At every entity, the matching global observers and the ones attached to the entity run in the
+/// order they were added.
+fn run_observers(
+    entries: &mut [ObserverEntry],
+    trigger: &mut PendingTrigger,
+    world: &mut World,
+) -> EcsResult<()> {
+    let mut result = Ok(());
+    let mut visited = Vec::new();
+    let mut entity = trigger.target;
+
+    loop {
+        for entry in entries.iter_mut() {
+            if !trigger.matches(&entry.key, entity) {
+                continue;
+            }
+
+            let erased = ErasedTrigger {
+                event: trigger.event.as_mut(),
+                target: trigger.target,
+                entity,
+                propagate: &mut trigger.propagate,
+            };
+            let observed = (entry.observer)(erased, world);
+            if result.is_ok() {
+                result = observed;
+            }
+        }
+
+        if !trigger.propagate {
+            return result;
+        }
+
+        // Stop at the root (and at cycles in the chain of parents)
+        visited.push(entity);
+        match world.get::<Parent>(entity) {
+            Ok(Some(&Parent(parent))) if !visited.contains(&parent) => entity = parent,
+            _ => return result,
+        }
+    }
+}
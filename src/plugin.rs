@@ -0,0 +1,89 @@
+use crate::Ecs;
+
+/// A modular piece of functionality (e.g. physics or audio) that can be added to the ECS.
+pub trait Plugin: 'static {
+    /// Registers the plugin's systems, resources, etc.
+    fn build(&self, ecs: &mut Ecs);
+
+    /// Runs once all plugins have been added (right before the first tick).
+    ///
+    /// Plugins that are added after that (including by other plugins' `finish`) are finished
+    /// right after they are built.
+    ///
+    /// This can be used to do setup that depends on other plugins.
+    fn finish(&self, _ecs: &mut Ecs) {}
+
+    /// The name of the plugin, used to detect duplicates (defaults to the type name).
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Whether the plugin may only be added once (defaults to `true`).
+    fn is_unique(&self) -> bool {
+        true
+    }
+}
+
+/// A group of plugins that are added together.
+pub trait PluginGroup {
+    /// Creates a builder containing the plugins of the group.
+    fn build(self) -> PluginGroupBuilder;
+}
+
+/// The plugins of a plugin group, in the order they are added.
+#[derive(Default)]
+pub struct PluginGroupBuilder {
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl PluginGroupBuilder {
+    /// Creates an empty plugin group builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a plugin to the end of the group.
+    pub fn add_plugin(mut self, plugin: impl Plugin) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Adds all plugins of another group to the end of this group.
+    pub fn add_group(mut self, group: impl PluginGroup) -> Self {
+        self.plugins.extend(group.build().plugins);
+        self
+    }
+
+    /// Returns the plugins of the group.
+    pub(crate) fn into_plugins(self) -> Vec<Box<dyn Plugin>> {
+        self.plugins
+    }
+}
+
+impl PluginGroup for PluginGroupBuilder {
+    fn build(self) -> PluginGroupBuilder {
+        self
+    }
+}
+
+/// Implements `PluginGroup` for tuples of plugins.
+macro_rules! impl_plugin_group_for_tuple {
+    ($($plugin:ident),*) => {
+        impl<$($plugin: Plugin),*> PluginGroup for ($($plugin,)*) {
+            #[allow(non_snake_case)]
+            fn build(self) -> PluginGroupBuilder {
+                let ($($plugin,)*) = self;
+                PluginGroupBuilder::new()$(.add_plugin($plugin))*
+            }
+        }
+    };
+}
+
+impl_plugin_group_for_tuple!(P1);
+impl_plugin_group_for_tuple!(P1, P2);
+impl_plugin_group_for_tuple!(P1, P2, P3);
+impl_plugin_group_for_tuple!(P1, P2, P3, P4);
+impl_plugin_group_for_tuple!(P1, P2, P3, P4, P5);
+impl_plugin_group_for_tuple!(P1, P2, P3, P4, P5, P6);
+impl_plugin_group_for_tuple!(P1, P2, P3, P4, P5, P6, P7);
+impl_plugin_group_for_tuple!(P1, P2, P3, P4, P5, P6, P7, P8);
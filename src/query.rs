@@ -1,29 +1,80 @@
-use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+use std::{
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
 
-use crate::{query_params::QueryParam, storage::archetype_table::ArchetypeTable, world::World};
+use crate::{
+    change_detection::Tick,
+    query_params::{Fetched, FilterRow, QueryFilter, QueryParam},
+    storage::archetype_table::ArchetypeTable,
+    world::WorldCell,
+    Component, ComponentId, EntityId,
+};
 
-pub struct Query<'a, Params: QueryParam<'a>> {
-    world: Rc<RefCell<World>>,
+pub struct Query<'a, Params: QueryParam<'a>, F: QueryFilter = ()> {
+    world: WorldCell<'a>,
     num_entities: usize,
-    archetype_tables: Vec<&'a mut ArchetypeTable>,
-    _marker: PhantomData<Params>,
+    archetype_tables: Vec<&'a ArchetypeTable>,
+
+    /// The components that are queried mutably, which are the only ones fetched mutably.
+    mutable: Vec<ComponentId>,
+
+    /// The tick that `Ref`, `Mut` and the filters report changes since.
+    last_run: Tick,
+
+    /// The tick that changes made through `Mut` are recorded at.
+    this_run: Tick,
+
+    _marker: PhantomData<(Params, F)>,
 }
 
-impl<'a, Params: QueryParam<'a>> Query<'a, Params> {
-    /// Creates a new query.
-    pub(crate) fn new(
-        world: Rc<RefCell<World>>,
-        num_entities: usize,
-        archetype_tables: Vec<&'a mut ArchetypeTable>,
-    ) -> Self {
+impl<'a, Params: QueryParam<'a>, F: QueryFilter> Query<'a, Params, F> {
+    /// Creates a new query over all entities that have the queried components and match the
+    /// filter.
+    ///
+    /// ## Panics
+    /// This will panic if a component that is queried mutably is queried more than once.
+    pub(crate) fn new(world: WorldCell<'a>, last_run: Tick, this_run: Tick) -> Self {
+        let mutable = Params::mut_typeids();
+        let typeids = Params::typeids();
+        for id in &mutable {
+            assert!(
+                typeids.iter().filter(|&other| other == id).count() == 1,
+                "Components that are queried mutably can only be queried once"
+            );
+        }
+
+        let mut component_ids = typeids;
+        component_ids.extend(F::typeids());
+
+        // SAFETY: Queries are only created by systems that are allowed to access the queried
+        // components
+        let archetype_tables = unsafe { world.world() }.matching_archetype_tables(&component_ids);
+        let num_entities = archetype_tables
+            .iter()
+            .map(|table| {
+                (0..table.num_entities())
+                    .filter(|&row| F::matches(&FilterRow::new(table, row, last_run, this_run)))
+                    .count()
+            })
+            .sum();
+
         Self {
             world,
             num_entities,
             archetype_tables,
+            mutable,
+            last_run,
+            this_run,
             _marker: PhantomData,
         }
     }
 
+    /// Checks if the entity in the row of the table matches the query's filter.
+    fn matches(&self, table: &ArchetypeTable, row: usize) -> bool {
+        F::matches(&FilterRow::new(table, row, self.last_run, self.this_run))
+    }
+
     /// Gets a single value from the query.
     ///
     /// ## Panics
@@ -40,12 +91,140 @@ impl<'a, Params: QueryParam<'a>> Query<'a, Params> {
     pub fn num_entities(&self) -> usize {
         self.num_entities
     }
+
+    /// Calls `f` for the entities in the query until the budget is used up, continuing from
+    /// where the last call with the same cursor stopped.
+    ///
+    /// This spreads an expensive pass over the entities across multiple ticks. Entities are
+    /// visited in the order of their IDs (not the rows of their tables), so each entity is
+    /// visited exactly once per pass, even if other entities were despawned or it was moved to
+    /// another archetype while the pass was paused. Despawned entities are skipped, and entities
+    /// spawned during a pass are visited if the cursor hasn't passed their IDs yet (otherwise
+    /// they are visited by the next pass).
+    ///
+    /// A time budget always lets at least one entity be processed, so every call makes progress.
+    ///
+    /// Returns `true` if the pass was completed, in which case the cursor starts over.
+    pub fn for_each_budgeted(
+        self,
+        cursor: &mut QueryCursor,
+        budget: impl Into<Budget>,
+        mut f: impl FnMut(Params::ResultType),
+    ) -> bool {
+        let budget = budget.into();
+        let start = Instant::now();
+
+        // SAFETY: Only the locations of entities are read
+        let world = unsafe { self.world.world() };
+
+        let mut processed = 0;
+        while cursor.next_entity < world.num_spawned_entities() {
+            let exhausted = match budget {
+                Budget::Count(count) => processed >= count,
+                Budget::Time(duration) => processed > 0 && start.elapsed() >= duration,
+            };
+            if exhausted {
+                return false;
+            }
+
+            let entity = cursor.next_entity;
+            cursor.next_entity += 1;
+
+            let Ok(location) = world.location(entity) else {
+                continue;
+            };
+
+            // The tables are sorted by their hashes
+            let Ok(idx) = self
+                .archetype_tables
+                .binary_search_by_key(&location.hash, |table| table.get_hash())
+            else {
+                continue;
+            };
+            let table = self.archetype_tables[idx];
+
+            if !self.matches(table, location.row) {
+                continue;
+            }
+
+            // SAFETY: The query has access to the queried components of every entity in its
+            // tables, and each entity is only fetched once
+            let item = unsafe {
+                fetch::<Params>(
+                    table,
+                    location.row,
+                    &self.mutable,
+                    self.last_run,
+                    self.this_run,
+                )
+            };
+            if let Some(item) = item {
+                f(item);
+                processed += 1;
+            }
+        }
+
+        cursor.next_entity = 0;
+        cursor.passes += 1;
+        true
+    }
+}
+
+/// How much work a single call to `Query::for_each_budgeted` may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// Process at most this many entities.
+    Count(usize),
+
+    /// Process entities until this much (real) time has passed.
+    Time(Duration),
 }
 
-impl<'a, Params: QueryParam<'a>> IntoIterator for Query<'a, Params> {
+impl From<usize> for Budget {
+    fn from(count: usize) -> Self {
+        Self::Count(count)
+    }
+}
+
+impl From<Duration> for Budget {
+    fn from(duration: Duration) -> Self {
+        Self::Time(duration)
+    }
+}
+
+/// Remembers how far a pass of `Query::for_each_budgeted` got, so it can be resumed later.
+///
+/// Systems usually keep their cursor in a `Local`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryCursor {
+    /// The entity the pass continues from.
+    next_entity: EntityId,
+
+    /// The number of completed passes.
+    passes: u64,
+}
+
+impl QueryCursor {
+    /// Creates a cursor at the start of a pass.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of passes that were completed with this cursor.
+    pub fn passes(&self) -> u64 {
+        self.passes
+    }
+
+    /// Moves the cursor back to the start of the current pass.
+    pub fn restart(&mut self) {
+        self.next_entity = 0;
+    }
+}
+
+impl<'a, Params: QueryParam<'a>, F: QueryFilter> IntoIterator for Query<'a, Params, F> {
     type Item = Params::ResultType;
 
-    type IntoIter = QueryIter<'a, Params>;
+    type IntoIter = QueryIter<'a, Params, F>;
 
     fn into_iter(self) -> Self::IntoIter {
         Self::IntoIter {
@@ -66,106 +245,104 @@ struct ArchetypeInfo {
 }
 
 /// An iterator over `Query`.
-pub struct QueryIter<'a, Params: QueryParam<'a>> {
-    query: Query<'a, Params>,
+pub struct QueryIter<'a, Params: QueryParam<'a>, F: QueryFilter = ()> {
+    query: Query<'a, Params, F>,
     archetype_info: ArchetypeInfo,
 }
 
-impl<'a, 'b, Params: QueryParam<'a>> Iterator for QueryIter<'a, Params> {
+impl<'a, Params: QueryParam<'a>, F: QueryFilter> Iterator for QueryIter<'a, Params, F> {
     type Item = Params::ResultType;
 
     fn next(&mut self) -> Option<Self::Item> {
-        use crate::query_params::QueryParamType::*;
-
-        // Get archetype table for the current entity
-        if self.archetype_info.entity_idx
-            >= self.query.archetype_tables[self.archetype_info.table_idx].num_entities()
-        {
-            self.archetype_info.table_idx += 1;
-            self.archetype_info.entity_idx = 0;
-
-            if self.archetype_info.table_idx >= self.query.num_entities {
+        loop {
+            // Get archetype table for the current entity (skipping any empty tables)
+            if self.archetype_info.table_idx >= self.query.archetype_tables.len() {
                 return None;
             }
-        }
-        let archetype_table = &mut self.query.archetype_tables[self.archetype_info.table_idx];
-
-        // Get the component values for the current entity
-        match Params::param_type() {
-            Type1 => {
-                let component = archetype_table
-                    .get_component::<Params::Type1>(self.archetype_info.entity_idx)
-                    .ok()??;
-                self.archetype_info.entity_idx += 1;
-
-                let component = unsafe {
-                    ((component as *const Params::Type1) as *mut Params::Type1)
-                        .as_mut()
-                        .expect("Unable to copy component value")
-                };
-
-                Some(Params::result_from_components(
-                    component,
-                    Params::empty_component2(),
-                    Params::empty_component3(),
-                ))
-            }
-            Type2 => {
-                let component1 = archetype_table
-                    .get_component::<Params::Type1>(self.archetype_info.entity_idx)
-                    .ok()??;
-                let component2 = archetype_table
-                    .get_component::<Params::Type2>(self.archetype_info.entity_idx)
-                    .ok()??;
-                self.archetype_info.entity_idx += 1;
-
-                let (component1, component2) = unsafe {
-                    (
-                        ((component1 as *const Params::Type1) as *mut Params::Type1)
-                            .as_mut()
-                            .expect("Unable to copy component value"),
-                        ((component2 as *const Params::Type2) as *mut Params::Type2)
-                            .as_mut()
-                            .expect("Unable to copy component value"),
-                    )
-                };
-
-                Some(Params::result_from_components(
-                    component1,
-                    component2,
-                    Params::empty_component3(),
-                ))
+
+            let archetype_table = self.query.archetype_tables[self.archetype_info.table_idx];
+            if self.archetype_info.entity_idx >= archetype_table.num_entities() {
+                self.archetype_info.table_idx += 1;
+                self.archetype_info.entity_idx = 0;
+                continue;
             }
-            Type3 => {
-                let component1 = archetype_table
-                    .get_component::<Params::Type1>(self.archetype_info.entity_idx)
-                    .ok()??;
-                let component2 = archetype_table
-                    .get_component::<Params::Type2>(self.archetype_info.entity_idx)
-                    .ok()??;
-                let component3 = archetype_table
-                    .get_component::<Params::Type3>(self.archetype_info.entity_idx)
-                    .ok()??;
-                self.archetype_info.entity_idx += 1;
-
-                let (component1, component2, component3) = unsafe {
-                    (
-                        ((component1 as *const Params::Type1) as *mut Params::Type1)
-                            .as_mut()
-                            .expect("Unable to copy component value"),
-                        ((component2 as *const Params::Type2) as *mut Params::Type2)
-                            .as_mut()
-                            .expect("Unable to copy component value"),
-                        ((component3 as *const Params::Type3) as *mut Params::Type3)
-                            .as_mut()
-                            .expect("Unable to copy component value"),
-                    )
-                };
-
-                Some(Params::result_from_components(
-                    component1, component2, component3,
-                ))
+
+            let row = self.archetype_info.entity_idx;
+            self.archetype_info.entity_idx += 1;
+
+            // Skip entities that don't match the filter
+            if !self.query.matches(archetype_table, row) {
+                continue;
             }
+
+            // SAFETY: The query has access to the queried components of every entity in its
+            // tables
+            return unsafe {
+                fetch::<Params>(
+                    archetype_table,
+                    row,
+                    &self.query.mutable,
+                    self.query.last_run,
+                    self.query.this_run,
+                )
+            };
         }
     }
 }
+
+/// Gets a component (and its ticks) of the entity in the specified row of the table, mutably if
+/// the component is in `mutable`.
+///
+/// ## Safety
+/// The caller must make sure the component isn't accessed mutably elsewhere for the lifetime `'a`
+/// (or accessed at all, if it's fetched mutably).
+unsafe fn fetch_component<'a, T: Component>(
+    archetype_table: &ArchetypeTable,
+    row: usize,
+    mutable: &[ComponentId],
+    last_run: Tick,
+    this_run: Tick,
+) -> Option<Fetched<'a, T>> {
+    if mutable.contains(&ComponentId::of::<T>()) {
+        let (component, ticks) = unsafe { archetype_table.get_component_unchecked_mut(row)? };
+        Some(Fetched::new_mut(component, ticks, last_run, this_run))
+    } else {
+        let (component, ticks) = unsafe { archetype_table.get_component_unchecked(row)? };
+        Some(Fetched::new(component, ticks, last_run, this_run))
+    }
+}
+
+/// Gets the queried components of the entity in the specified row of the table.
+///
+/// ## Safety
+/// The caller must make sure the components aren't aliased for the lifetime `'a`.
+unsafe fn fetch<'a, Params: QueryParam<'a>>(
+    archetype_table: &ArchetypeTable,
+    row: usize,
+    mutable: &[ComponentId],
+    last_run: Tick,
+    this_run: Tick,
+) -> Option<Params::ResultType> {
+    use crate::query_params::QueryParamType::*;
+
+    let component1 =
+        fetch_component::<Params::Type1>(archetype_table, row, mutable, last_run, this_run)?;
+    let (component2, component3) = match Params::param_type() {
+        Type1 => (
+            Fetched::empty(Params::empty_component2()),
+            Fetched::empty(Params::empty_component3()),
+        ),
+        Type2 => (
+            fetch_component::<Params::Type2>(archetype_table, row, mutable, last_run, this_run)?,
+            Fetched::empty(Params::empty_component3()),
+        ),
+        Type3 => (
+            fetch_component::<Params::Type2>(archetype_table, row, mutable, last_run, this_run)?,
+            fetch_component::<Params::Type3>(archetype_table, row, mutable, last_run, this_run)?,
+        ),
+    };
+
+    Some(Params::result_from_components(
+        component1, component2, component3,
+    ))
+}
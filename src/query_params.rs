@@ -1,6 +1,12 @@
 // FIXME: Move to query module
 
-use crate::{Component, ComponentId};
+use std::{marker::PhantomData, ptr::NonNull};
+
+use crate::{
+    change_detection::{Added, Changed, ComponentTicks, Mut, Ref, Tick},
+    storage::archetype_table::ArchetypeTable,
+    Component, ComponentId,
+};
 
 pub enum QueryParamType {
     /// Single component
@@ -24,10 +30,13 @@ pub trait QueryParam<'a> {
     // NOTE: Change to Vec<ComponentId> if HashSet doesn't preserve order
     fn typeids() -> Vec<ComponentId>;
 
+    /// The ids of the components that are queried mutably.
+    fn mut_typeids() -> Vec<ComponentId>;
+
     fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
+        c1: Fetched<'a, Self::Type1>,
+        c2: Fetched<'a, Self::Type2>,
+        c3: Fetched<'a, Self::Type3>,
     ) -> Self::ResultType;
 
     fn empty_component2() -> &'static mut Self::Type2;
@@ -35,289 +44,263 @@ pub trait QueryParam<'a> {
     fn empty_component3() -> &'static mut Self::Type3;
 }
 
-// TODO: Write a macro to expand this!!
-
-//              Single Component
-// ===============================================
-
-impl<'a, P> QueryParam<'a> for &P
-where
-    P: Component,
-{
-    type Type1 = P;
-    type Type2 = ();
-    type Type3 = ();
-    type ResultType = &'a P;
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type1
-    }
+/// A component fetched by a query, along with its change ticks.
+///
+/// Components are only fetched mutably if the query declared them as mutable (see
+/// `QueryParam::mut_typeids`), so that queries that only read a component never hold a mutable
+/// reference to it.
+pub struct Fetched<'a, T> {
+    value: NonNull<T>,
 
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P>());
-        types
-    }
+    /// `None` for the placeholder components of queries with fewer than 3 components.
+    ticks: Option<NonNull<ComponentTicks>>,
 
-    fn result_from_components(c1: &'a mut Self::Type1, _: &mut (), _: &mut ()) -> Self::ResultType {
-        c1
-    }
+    /// Whether the value (and its ticks) may be mutated.
+    mutable: bool,
 
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
+    last_run: Tick,
+    this_run: Tick,
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
+    _marker: PhantomData<&'a mut T>,
 }
 
-impl<'a, P> QueryParam<'a> for &mut P
-where
-    P: Component,
-{
-    type Type1 = P;
-    type Type2 = ();
-    type Type3 = ();
-    type ResultType = &'a mut P;
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type1
-    }
-
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P>());
-        types
+impl<'a, T> Fetched<'a, T> {
+    /// Creates a fetched component that is only read, with its ticks.
+    pub(crate) fn new(
+        value: &'a T,
+        ticks: &'a ComponentTicks,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self {
+        Self {
+            value: NonNull::from(value),
+            ticks: Some(NonNull::from(ticks)),
+            mutable: false,
+            last_run,
+            this_run,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a fetched component that may be mutated, with its ticks.
+    pub(crate) fn new_mut(
+        value: &'a mut T,
+        ticks: &'a mut ComponentTicks,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self {
+        Self {
+            value: NonNull::from(value),
+            ticks: Some(NonNull::from(ticks)),
+            mutable: true,
+            last_run,
+            this_run,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a placeholder for a component that isn't queried.
+    pub(crate) fn empty(value: &'a mut T) -> Self {
+        Self {
+            value: NonNull::from(value),
+            ticks: None,
+            mutable: true,
+            last_run: Tick::default(),
+            this_run: Tick::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    fn into_ref(self) -> &'a T {
+        // SAFETY: The value was borrowed for `'a` when it was fetched
+        unsafe { self.value.as_ref() }
+    }
+
+    fn into_ticks(self) -> (&'a T, &'a ComponentTicks, Tick, Tick) {
+        let ticks = self.ticks.expect("Queried components always have ticks");
+
+        // SAFETY: The value and its ticks were borrowed for `'a` when they were fetched
+        unsafe {
+            (
+                self.value.as_ref(),
+                ticks.as_ref(),
+                self.last_run,
+                self.this_run,
+            )
+        }
+    }
+
+    fn into_ticks_mut(mut self) -> (&'a mut T, &'a mut ComponentTicks, Tick, Tick) {
+        assert!(self.mutable, "Component was fetched immutably");
+        let mut ticks = self.ticks.expect("Queried components always have ticks");
+
+        // SAFETY: The value and its ticks were borrowed mutably for `'a` when they were fetched
+        unsafe {
+            (
+                self.value.as_mut(),
+                ticks.as_mut(),
+                self.last_run,
+                self.this_run,
+            )
+        }
     }
+}
 
-    fn result_from_components(c1: &'a mut Self::Type1, _: &mut (), _: &mut ()) -> Self::ResultType {
-        c1
-    }
+/// A single component in a query: `&T`, `&mut T` or `Ref<T>`.
+pub trait QueryTerm<'a> {
+    type Component: Component;
+    type Item;
 
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
+    /// Whether the component is queried mutably.
+    const MUTABLE: bool;
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
+    fn item(fetched: Fetched<'a, Self::Component>) -> Self::Item;
 }
 
-impl<'a, P> QueryParam<'a> for (&P,)
-where
-    P: Component,
-{
-    type Type1 = P;
-    type Type2 = ();
-    type Type3 = ();
-    type ResultType = &'a P;
+impl<'a, P: Component> QueryTerm<'a> for &P {
+    type Component = P;
+    type Item = &'a P;
 
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type1
-    }
+    const MUTABLE: bool = false;
 
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P>());
-        types
+    fn item(fetched: Fetched<'a, P>) -> Self::Item {
+        fetched.into_ref()
     }
+}
 
-    fn result_from_components(c1: &'a mut Self::Type1, _: &mut (), _: &mut ()) -> Self::ResultType {
-        c1
-    }
+impl<'a, P: Component> QueryTerm<'a> for &mut P {
+    type Component = P;
+    type Item = Mut<'a, P>;
 
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
+    const MUTABLE: bool = true;
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
+    fn item(fetched: Fetched<'a, P>) -> Self::Item {
+        let (value, ticks, last_run, this_run) = fetched.into_ticks_mut();
+        Mut::new(value, ticks, last_run, this_run)
     }
 }
 
-impl<'a, P> QueryParam<'a> for (&mut P,)
-where
-    P: Component,
-{
-    type Type1 = P;
-    type Type2 = ();
-    type Type3 = ();
-    type ResultType = &'a mut Self::Type1;
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type1
-    }
+impl<'a, P: Component> QueryTerm<'a> for Ref<'_, P> {
+    type Component = P;
+    type Item = Ref<'a, P>;
 
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P>());
-        types
-    }
-
-    fn result_from_components(c1: &'a mut Self::Type1, _: &mut (), _: &mut ()) -> Self::ResultType {
-        c1
-    }
+    const MUTABLE: bool = false;
 
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
+    fn item(fetched: Fetched<'a, P>) -> Self::Item {
+        let (value, ticks, last_run, this_run) = fetched.into_ticks();
+        Ref::new(value, ticks, last_run, this_run)
     }
+}
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
+/// Returns a placeholder for a component that isn't queried.
+fn empty_component() -> &'static mut () {
+    unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
 }
 
-//              2 Components
+//              Single Component
 // ===============================================
 
-impl<'a, P1, P2> QueryParam<'a> for (&P1, &P2)
-where
-    P1: Component,
-    P2: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = ();
-    type ResultType = (&'a Self::Type1, &'a Self::Type2);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type2
-    }
-
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types
-    }
-
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        _: &mut (),
-    ) -> Self::ResultType {
-        (c1, c2)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have only 1 component"
-        )
-    }
-
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
+/// Implements `QueryParam` for a single component, which can also be queried as a 1-tuple.
+///
+/// Each parameter is mapped to its `QueryTerm` (the lifetime of the term doesn't matter, since
+/// the items borrow for `'a` either way).
+macro_rules! impl_query_param_single {
+    ($($param:ty => $term:ty),*) => {
+        $(
+            impl<'a, P: Component> QueryParam<'a> for $param {
+                type Type1 = P;
+                type Type2 = ();
+                type Type3 = ();
+                type ResultType = <$term as QueryTerm<'a>>::Item;
+
+                fn param_type() -> QueryParamType {
+                    QueryParamType::Type1
+                }
+
+                fn typeids() -> Vec<ComponentId> {
+                    vec![ComponentId::of::<P>()]
+                }
+
+                fn mut_typeids() -> Vec<ComponentId> {
+                    mut_typeids!($term)
+                }
+
+                fn result_from_components(
+                    c1: Fetched<'a, P>,
+                    _: Fetched<'a, ()>,
+                    _: Fetched<'a, ()>,
+                ) -> Self::ResultType {
+                    <$term as QueryTerm<'a>>::item(c1)
+                }
+
+                fn empty_component2() -> &'static mut Self::Type2 {
+                    empty_component()
+                }
+
+                fn empty_component3() -> &'static mut Self::Type3 {
+                    empty_component()
+                }
+            }
+        )*
+    };
 }
 
-impl<'a, P1, P2> QueryParam<'a> for (&P1, &mut P2)
-where
-    P1: Component,
-    P2: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = ();
-    type ResultType = (&'a Self::Type1, &'a mut Self::Type2);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type2
-    }
-
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types
-    }
-
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        _: &mut (),
-    ) -> Self::ResultType {
-        (c1, c2)
-    }
+/// Returns the ids of the components that the terms query mutably.
+macro_rules! mut_typeids {
+    ($($term:ty),*) => {{
+        let mut ids = Vec::new();
+        $(
+            if <$term as QueryTerm>::MUTABLE {
+                ids.push(ComponentId::of::<<$term as QueryTerm>::Component>());
+            }
+        )*
+        ids
+    }};
+}
 
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have only 1 component"
-        )
-    }
+impl_query_param_single!(
+    &P => &'static P,
+    &mut P => &'static mut P,
+    Ref<'_, P> => Ref<'static, P>,
+    (&P,) => &'static P,
+    (&mut P,) => &'static mut P,
+    (Ref<'_, P>,) => Ref<'static, P>
+);
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
-}
+//              2 Components
+// ===============================================
 
-impl<'a, P1, P2> QueryParam<'a> for (&mut P1, &P2)
+impl<'a, A, B> QueryParam<'a> for (A, B)
 where
-    P1: Component,
-    P2: Component,
+    A: QueryTerm<'a>,
+    B: QueryTerm<'a>,
 {
-    type Type1 = P1;
-    type Type2 = P2;
+    type Type1 = A::Component;
+    type Type2 = B::Component;
     type Type3 = ();
-    type ResultType = (&'a mut Self::Type1, &'a Self::Type2);
+    type ResultType = (A::Item, B::Item);
 
     fn param_type() -> QueryParamType {
         QueryParamType::Type2
     }
 
     fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types
+        vec![
+            ComponentId::of::<A::Component>(),
+            ComponentId::of::<B::Component>(),
+        ]
     }
 
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        _: &mut (),
-    ) -> Self::ResultType {
-        (c1, c2)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have only 1 component"
-        )
-    }
-
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
-    }
-}
-
-impl<'a, P1, P2> QueryParam<'a> for (&mut P1, &mut P2)
-where
-    P1: Component,
-    P2: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = ();
-    type ResultType = (&'a mut Self::Type1, &'a mut Self::Type2);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type2
-    }
-
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types
+    fn mut_typeids() -> Vec<ComponentId> {
+        mut_typeids!(A, B)
     }
 
     fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        _: &mut (),
+        c1: Fetched<'a, Self::Type1>,
+        c2: Fetched<'a, Self::Type2>,
+        _: Fetched<'a, ()>,
     ) -> Self::ResultType {
-        (c1, c2)
+        (A::item(c1), B::item(c2))
     }
 
     fn empty_component2() -> &'static mut Self::Type2 {
@@ -327,90 +310,46 @@ where
     }
 
     fn empty_component3() -> &'static mut Self::Type3 {
-        unsafe { Box::into_raw(Box::new(())).as_mut().unwrap() }
+        empty_component()
     }
 }
 
 //              3 Components
 // ===============================================
 
-impl<'a, P1, P2, P3> QueryParam<'a> for (&P1, &P2, &P3)
+impl<'a, A, B, C> QueryParam<'a> for (A, B, C)
 where
-    P1: Component,
-    P2: Component,
-    P3: Component,
+    A: QueryTerm<'a>,
+    B: QueryTerm<'a>,
+    C: QueryTerm<'a>,
 {
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (&'a Self::Type1, &'a Self::Type2, &'a Self::Type3);
+    type Type1 = A::Component;
+    type Type2 = B::Component;
+    type Type3 = C::Component;
+    type ResultType = (A::Item, B::Item, C::Item);
 
     fn param_type() -> QueryParamType {
         QueryParamType::Type3
     }
 
     fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
-    }
-
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
-    ) -> Self::ResultType {
-        (c1, c2, c3)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less components"
-        )
-    }
-
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
-    }
-}
-
-impl<'a, P1, P2, P3> QueryParam<'a> for (&mut P1, &mut P2, &mut P3)
-where
-    P1: Component,
-    P2: Component,
-    P3: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (
-        &'a mut Self::Type1,
-        &'a mut Self::Type2,
-        &'a mut Self::Type3,
-    );
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type3
+        vec![
+            ComponentId::of::<A::Component>(),
+            ComponentId::of::<B::Component>(),
+            ComponentId::of::<C::Component>(),
+        ]
     }
 
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
+    fn mut_typeids() -> Vec<ComponentId> {
+        mut_typeids!(A, B, C)
     }
 
     fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
+        c1: Fetched<'a, Self::Type1>,
+        c2: Fetched<'a, Self::Type2>,
+        c3: Fetched<'a, Self::Type3>,
     ) -> Self::ResultType {
-        (c1, c2, c3)
+        (A::item(c1), B::item(c2), C::item(c3))
     }
 
     fn empty_component2() -> &'static mut Self::Type2 {
@@ -420,272 +359,106 @@ where
     }
 
     fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
-    }
-}
-
-impl<'a, P1, P2, P3> QueryParam<'a> for (&mut P1, &P2, &P3)
-where
-    P1: Component,
-    P2: Component,
-    P3: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (&'a mut Self::Type1, &'a Self::Type2, &'a Self::Type3);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type3
-    }
-
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
-    }
-
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
-    ) -> Self::ResultType {
-        (c1, c2, c3)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
         unimplemented!(
             "This method is only implemented for query parameters that have 2 or less components"
         )
     }
-
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
-    }
 }
 
-impl<'a, P1, P2, P3> QueryParam<'a> for (&P1, &mut P2, &P3)
-where
-    P1: Component,
-    P2: Component,
-    P3: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (&'a Self::Type1, &'a mut Self::Type2, &'a Self::Type3);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type3
-    }
-
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
-    }
-
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
-    ) -> Self::ResultType {
-        (c1, c2, c3)
-    }
+//              Filters
+// ===============================================
 
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less components"
-        )
-    }
+/// A filter that restricts which entities a query contains (e.g. `Changed<T>`).
+///
+/// Tuples of up to three filters match the entities that match all of them.
+pub trait QueryFilter {
+    /// The ids of the components the filter reads (matched entities must have all of them).
+    fn typeids() -> Vec<ComponentId>;
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
-    }
+    /// Checks if the entity in the row matches the filter.
+    fn matches(row: &FilterRow) -> bool;
 }
 
-impl<'a, P1, P2, P3> QueryParam<'a> for (&P1, &P2, &mut P3)
-where
-    P1: Component,
-    P2: Component,
-    P3: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (&'a Self::Type1, &'a Self::Type2, &'a mut Self::Type3);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type3
-    }
-
-    fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
-    }
-
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
-    ) -> Self::ResultType {
-        (c1, c2, c3)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less components"
-        )
-    }
-
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
-    }
+/// A row of an archetype table that a query filter is checked for.
+pub struct FilterRow<'a> {
+    table: &'a ArchetypeTable,
+    row: usize,
+    last_run: Tick,
+    this_run: Tick,
 }
 
-impl<'a, P1, P2, P3> QueryParam<'a> for (&mut P1, &mut P2, &P3)
-where
-    P1: Component,
-    P2: Component,
-    P3: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (&'a mut Self::Type1, &'a mut Self::Type2, &'a Self::Type3);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type3
+impl<'a> FilterRow<'a> {
+    /// Creates the row to check filters for.
+    pub(crate) fn new(
+        table: &'a ArchetypeTable,
+        row: usize,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self {
+        Self {
+            table,
+            row,
+            last_run,
+            this_run,
+        }
+    }
+
+    /// Checks if one of the ticks (selected by `tick`) of the component of type `T` is newer than
+    /// the system's last run.
+    fn is_newer<T: Component>(&self, tick: impl FnOnce(&ComponentTicks) -> Tick) -> bool {
+        self.table
+            .get_component_ticks(ComponentId::of::<T>(), self.row)
+            .is_some_and(|ticks| tick(ticks).is_newer_than(self.last_run, self.this_run))
     }
+}
 
+impl QueryFilter for () {
     fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
+        vec![]
     }
 
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
-    ) -> Self::ResultType {
-        (c1, c2, c3)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less components"
-        )
-    }
-
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
+    fn matches(_row: &FilterRow) -> bool {
+        true
     }
 }
 
-impl<'a, P1, P2, P3> QueryParam<'a> for (&mut P1, &P2, &mut P3)
-where
-    P1: Component,
-    P2: Component,
-    P3: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (&'a mut Self::Type1, &'a Self::Type2, &'a mut Self::Type3);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type3
-    }
-
+impl<T: Component> QueryFilter for Added<T> {
     fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
-    }
-
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
-    ) -> Self::ResultType {
-        (c1, c2, c3)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less components"
-        )
+        vec![ComponentId::of::<T>()]
     }
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
+    fn matches(row: &FilterRow) -> bool {
+        row.is_newer::<T>(|ticks| ticks.added)
     }
 }
 
-impl<'a, P1, P2, P3> QueryParam<'a> for (&P1, &mut P2, &mut P3)
-where
-    P1: Component,
-    P2: Component,
-    P3: Component,
-{
-    type Type1 = P1;
-    type Type2 = P2;
-    type Type3 = P3;
-    type ResultType = (&'a Self::Type1, &'a mut Self::Type2, &'a mut Self::Type3);
-
-    fn param_type() -> QueryParamType {
-        QueryParamType::Type3
-    }
-
+impl<T: Component> QueryFilter for Changed<T> {
     fn typeids() -> Vec<ComponentId> {
-        let mut types = Vec::new();
-        types.push(ComponentId::of::<P1>());
-        types.push(ComponentId::of::<P2>());
-        types.push(ComponentId::of::<P3>());
-        types
+        vec![ComponentId::of::<T>()]
     }
 
-    fn result_from_components(
-        c1: &'a mut Self::Type1,
-        c2: &'a mut Self::Type2,
-        c3: &'a mut Self::Type3,
-    ) -> Self::ResultType {
-        (c1, c2, c3)
-    }
-
-    fn empty_component2() -> &'static mut Self::Type2 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less components"
-        )
+    fn matches(row: &FilterRow) -> bool {
+        row.is_newer::<T>(|ticks| ticks.changed)
     }
+}
 
-    fn empty_component3() -> &'static mut Self::Type3 {
-        unimplemented!(
-            "This method is only implemented for query parameters that have 2 or less 1 components"
-        )
-    }
+/// Implements `QueryFilter` for tuples of filters.
+macro_rules! impl_query_filter {
+    ($($filter:ident),*) => {
+        impl<$($filter: QueryFilter),*> QueryFilter for ($($filter,)*) {
+            fn typeids() -> Vec<ComponentId> {
+                let mut ids = Vec::new();
+                $(ids.extend($filter::typeids());)*
+                ids
+            }
+
+            fn matches(row: &FilterRow) -> bool {
+                $($filter::matches(row))&&*
+            }
+        }
+    };
 }
+
+impl_query_filter!(F1);
+impl_query_filter!(F1, F2);
+impl_query_filter!(F1, F2, F3);
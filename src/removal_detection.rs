@@ -0,0 +1,191 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use crate::{system::SystemParam, Component, ComponentId, Context, EntityId};
+
+/// The removals of a single component type.
+#[derive(Debug, Default)]
+struct RemovalLog {
+    /// The entities whose component was removed, oldest first.
+    entities: VecDeque<EntityId>,
+
+    /// The id of the oldest removal in `entities` (ids keep counting up as removals are dropped).
+    start: usize,
+}
+
+impl RemovalLog {
+    /// Returns the id the next removal will get.
+    fn event_count(&self) -> usize {
+        self.start + self.entities.len()
+    }
+
+    /// Iterates over the removals from the one with the specified id on, oldest first.
+    fn entities_since(&self, id: usize) -> impl Iterator<Item = EntityId> + '_ {
+        self.entities
+            .iter()
+            .skip(id.saturating_sub(self.start))
+            .copied()
+    }
+}
+
+/// The cursor of a system's `RemovedComponents` parameter, shared with the world so removals
+/// are kept until every reader has read them.
+type ReaderCursor = Arc<AtomicUsize>;
+
+/// The logs of removed components, one for each component type.
+///
+/// Removals are kept until every system that reads them (with a `RemovedComponents` parameter)
+/// has read them, so systems that don't run every tick (e.g. because of a run rate, a run
+/// condition or `FixedUpdate`) still see every removal since their last run exactly once.
+#[derive(Debug, Default)]
+pub(crate) struct RemovedComponentEvents {
+    logs: HashMap<ComponentId, RemovalLog>,
+
+    /// The cursors of the readers of each component type.
+    ///
+    /// Readers are registered the first time their system runs, while other systems may be
+    /// running, which is why this is behind a lock.
+    readers: Mutex<HashMap<ComponentId, Vec<ReaderCursor>>>,
+}
+
+impl RemovedComponentEvents {
+    /// Records that the component with the specified id was removed from the entity.
+    pub(crate) fn send(&mut self, component_id: ComponentId, entity: EntityId) {
+        self.logs
+            .entry(component_id)
+            .or_default()
+            .entities
+            .push_back(entity);
+    }
+
+    /// Drops the removals that every reader has read, along with the readers whose systems
+    /// were dropped.
+    pub(crate) fn update(&mut self) {
+        let readers = self
+            .readers
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        for (component_id, log) in &mut self.logs {
+            let cursors = readers.entry(*component_id).or_default();
+            // The world holds the only reference to the cursors of dropped systems
+            cursors.retain(|cursor| Arc::strong_count(cursor) > 1);
+
+            let read = cursors
+                .iter()
+                .map(|cursor| cursor.load(Ordering::Relaxed))
+                .min()
+                .unwrap_or(log.event_count())
+                .clamp(log.start, log.event_count());
+
+            log.entities.drain(..read - log.start);
+            log.start = read;
+        }
+    }
+
+    /// Registers the cursor of a reader of the component with the specified id.
+    fn register_reader(&self, component_id: ComponentId, cursor: ReaderCursor) {
+        self.readers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(component_id)
+            .or_default()
+            .push(cursor);
+    }
+
+    /// Gets the log of the component with the specified id (if it was ever removed).
+    fn get(&self, component_id: ComponentId) -> Option<&RemovalLog> {
+        self.logs.get(&component_id)
+    }
+}
+
+/// A system parameter that reads the entities whose component of type `T` was removed (or that
+/// were despawned).
+///
+/// Each system keeps its own cursor, so it sees every removal since it last ran exactly once,
+/// however many ticks it skipped. In its first run, it sees the removals that haven't been
+/// dropped yet (at least those made since the end of the previous tick). Entities are reported
+/// even if they were despawned since, so their IDs may already have been reused.
+///
+/// The removals are copied when the system starts, so removals made while it runs (e.g. by a
+/// schedule it runs) are read in its next run.
+pub struct RemovedComponents<'w, 's, T: Component> {
+    /// The removals the system hasn't read yet, oldest first.
+    unread: Vec<EntityId>,
+
+    /// The id of the first removal after `unread`.
+    event_count: usize,
+
+    /// The id of the first removal the system hasn't read yet.
+    cursor: &'s AtomicUsize,
+
+    _marker: PhantomData<&'w fn() -> T>,
+}
+
+impl<T: Component> RemovedComponents<'_, '_, T> {
+    /// Iterates over the entities whose component was removed since they were last read, oldest
+    /// first, and marks them as read.
+    pub fn read(&mut self) -> impl Iterator<Item = EntityId> + '_ {
+        self.cursor.store(self.event_count, Ordering::Relaxed);
+        self.unread.drain(..)
+    }
+
+    /// Returns the number of removals that haven't been read yet.
+    pub fn len(&self) -> usize {
+        self.unread.len()
+    }
+
+    /// Checks if all removals have been read.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Marks all removals as read.
+    pub fn clear(&mut self) {
+        self.cursor.store(self.event_count, Ordering::Relaxed);
+        self.unread.clear();
+    }
+}
+
+impl<T: Component> SystemParam for RemovedComponents<'_, '_, T> {
+    /// The cursor of the system (`None` until it's registered with the world).
+    type State = Option<ReaderCursor>;
+    type Item<'w, 's> = RemovedComponents<'w, 's, T>;
+
+    fn init_state() -> Self::State {
+        None
+    }
+
+    fn get_param<'w, 's>(state: &'s mut Self::State, ctx: &Context<'w>) -> Self::Item<'w, 's> {
+        // SAFETY: Components are only removed by systems with exclusive access to the world,
+        // which can't run while the parameter is being created
+        let world = unsafe { ctx.world_cell().world() };
+        let removed_components = world.removed_components();
+
+        let cursor = state.get_or_insert_with(|| {
+            let cursor = ReaderCursor::default();
+            removed_components.register_reader(ComponentId::of::<T>(), cursor.clone());
+            cursor
+        });
+
+        // Copy the removals, since the logs can change while the system runs
+        let read = cursor.load(Ordering::Relaxed);
+        let (unread, event_count) = match removed_components.get(ComponentId::of::<T>()) {
+            Some(log) => (log.entities_since(read).collect(), log.event_count()),
+            None => (Vec::new(), read),
+        };
+
+        RemovedComponents {
+            unread,
+            event_count,
+            cursor,
+            _marker: PhantomData,
+        }
+    }
+}
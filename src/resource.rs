@@ -0,0 +1,444 @@
+use std::{
+    any::{Any, TypeId},
+    cell::UnsafeCell,
+    collections::HashMap,
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicIsize, Ordering},
+    thread::{self, ThreadId},
+};
+
+/// A global, unique value stored in the world (e.g. the score or the game settings).
+///
+/// Resources must be `Send + Sync` since systems that access them may run on other threads.
+/// Values that aren't (e.g. window or audio handles) can be stored as non-send resources instead.
+pub trait Resource: Send + Sync + 'static {}
+
+/// Possible errors caused by resources.
+#[derive(Debug, thiserror::Error)]
+pub enum ResourceError {
+    #[error("The resource {0} does not exist")]
+    ResourceNotFound(&'static str),
+
+    #[error("The resource {0} is already borrowed mutably")]
+    AlreadyBorrowedMutably(&'static str),
+
+    #[error("The resource {0} is already borrowed")]
+    AlreadyBorrowed(&'static str),
+}
+
+/// The borrow state of a resource that is mutably borrowed.
+const MUTABLY_BORROWED: isize = -1;
+
+/// A type-erased resource along with its borrow state.
+struct ResourceCell {
+    value: UnsafeCell<Box<dyn Any>>,
+
+    /// The number of immutable borrows of the value, or `MUTABLY_BORROWED`.
+    borrows: AtomicIsize,
+}
+
+// SAFETY: The value is only accessed through `&self` while it's borrowed (which is tracked by
+// `borrows`). `Resources` only stores values that are `Send + Sync`, and `NonSendResources` only
+// accesses its values from the thread that owns them.
+unsafe impl Send for ResourceCell {}
+unsafe impl Sync for ResourceCell {}
+
+impl ResourceCell {
+    /// Creates a new, unborrowed cell.
+    fn new<R: 'static>(resource: R) -> Self {
+        Self {
+            value: UnsafeCell::new(Box::new(resource)),
+            borrows: AtomicIsize::new(0),
+        }
+    }
+
+    /// Checks if the value is borrowed (mutably, if `mutably_only` is set).
+    fn is_borrowed(&self, mutably_only: bool) -> bool {
+        let borrows = self.borrows.load(Ordering::Acquire);
+        borrows == MUTABLY_BORROWED || (borrows > 0 && !mutably_only)
+    }
+
+    /// Panics if the value is borrowed (mutably, if `mutably_only` is set).
+    ///
+    /// This is what keeps untracked accesses (e.g. from an exclusive system that was run while a
+    /// borrow was held) sound.
+    fn assert_not_borrowed(&self, name: &str, mutably_only: bool) {
+        if self.is_borrowed(mutably_only) {
+            panic!(
+                "The resource {} can't be accessed while it's borrowed",
+                name
+            );
+        }
+    }
+
+    /// Takes the value out of the cell.
+    fn into_inner<R: 'static>(self) -> Option<R> {
+        self.value.into_inner().downcast().ok().map(|value| *value)
+    }
+}
+
+impl Debug for ResourceCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourceCell")
+            .field("borrows", &self.borrows)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Maps resource types to their values, and tracks borrows of the values.
+#[derive(Debug, Default)]
+struct ResourceMap {
+    /// Maps resource types to their (type-erased) values.
+    ///
+    /// The cells are boxed so borrows stay valid when other resources are inserted.
+    values: HashMap<TypeId, Box<ResourceCell>>,
+}
+
+impl ResourceMap {
+    fn insert<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.assert_not_borrowed::<R>(false);
+        self.values
+            .insert(TypeId::of::<R>(), Box::new(ResourceCell::new(resource)))
+            .and_then(|cell| cell.into_inner())
+    }
+
+    fn remove<R: 'static>(&mut self) -> Option<R> {
+        self.assert_not_borrowed::<R>(false);
+        self.values
+            .remove(&TypeId::of::<R>())
+            .and_then(|cell| cell.into_inner())
+    }
+
+    fn contains<R: 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<R>())
+    }
+
+    fn is_borrowed<R: 'static>(&self) -> bool {
+        self.values
+            .get(&TypeId::of::<R>())
+            .is_some_and(|cell| cell.is_borrowed(false))
+    }
+
+    fn assert_not_borrowed<R: 'static>(&self, mutably_only: bool) {
+        if let Some(cell) = self.values.get(&TypeId::of::<R>()) {
+            cell.assert_not_borrowed(std::any::type_name::<R>(), mutably_only);
+        }
+    }
+
+    fn get<R: 'static>(&self) -> Option<&R> {
+        let cell = self.values.get(&TypeId::of::<R>())?;
+        cell.assert_not_borrowed(std::any::type_name::<R>(), true);
+
+        // SAFETY: The value isn't borrowed mutably
+        unsafe { &*cell.value.get() }.downcast_ref()
+    }
+
+    fn get_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        let cell = self.values.get_mut(&TypeId::of::<R>())?;
+        cell.assert_not_borrowed(std::any::type_name::<R>(), false);
+
+        cell.value.get_mut().downcast_mut()
+    }
+
+    fn borrow<R: 'static>(&self) -> Result<Borrowed<'_, R>, ResourceError> {
+        let name = std::any::type_name::<R>();
+        let cell = self
+            .values
+            .get(&TypeId::of::<R>())
+            .ok_or(ResourceError::ResourceNotFound(name))?;
+
+        cell.borrows
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |borrows| {
+                (borrows != MUTABLY_BORROWED).then_some(borrows + 1)
+            })
+            .map_err(|_| ResourceError::AlreadyBorrowedMutably(name))?;
+
+        // SAFETY: The value isn't borrowed mutably (and can't be until the borrow is dropped)
+        let value = unsafe { &*cell.value.get() }
+            .downcast_ref()
+            .expect("Resource has the wrong type");
+
+        Ok(Borrowed {
+            value,
+            borrows: &cell.borrows,
+        })
+    }
+
+    fn borrow_mut<R: 'static>(&self) -> Result<BorrowedMut<'_, R>, ResourceError> {
+        let name = std::any::type_name::<R>();
+        let cell = self
+            .values
+            .get(&TypeId::of::<R>())
+            .ok_or(ResourceError::ResourceNotFound(name))?;
+
+        cell.borrows
+            .compare_exchange(0, MUTABLY_BORROWED, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| ResourceError::AlreadyBorrowed(name))?;
+
+        // SAFETY: The value isn't borrowed (and can't be until the borrow is dropped)
+        let value = unsafe { &mut *cell.value.get() }
+            .downcast_mut()
+            .expect("Resource has the wrong type");
+
+        Ok(BorrowedMut {
+            value,
+            borrows: &cell.borrows,
+        })
+    }
+}
+
+/// Stores the resources of the world.
+#[derive(Debug, Default)]
+pub(crate) struct Resources {
+    map: ResourceMap,
+}
+
+impl Resources {
+    /// Inserts a resource, replacing (and returning) the existing value of the same type.
+    ///
+    /// ## Panics
+    /// This will panic if the existing value is borrowed.
+    pub(crate) fn insert<R: Resource>(&mut self, resource: R) -> Option<R> {
+        self.map.insert(resource)
+    }
+
+    /// Removes the resource of type `R`.
+    ///
+    /// ## Panics
+    /// This will panic if the resource is borrowed.
+    pub(crate) fn remove<R: Resource>(&mut self) -> Option<R> {
+        self.map.remove()
+    }
+
+    /// Checks if a resource of type `R` exists.
+    pub(crate) fn contains<R: Resource>(&self) -> bool {
+        self.map.contains::<R>()
+    }
+
+    /// Checks if the resource of type `R` is borrowed.
+    pub(crate) fn is_borrowed<R: Resource>(&self) -> bool {
+        self.map.is_borrowed::<R>()
+    }
+
+    /// Gets an immutable reference to the resource of type `R`.
+    ///
+    /// ## Panics
+    /// This will panic if the resource is borrowed mutably.
+    pub(crate) fn get<R: Resource>(&self) -> Option<&R> {
+        self.map.get()
+    }
+
+    /// Gets a mutable reference to the resource of type `R`.
+    ///
+    /// ## Panics
+    /// This will panic if the resource is borrowed.
+    pub(crate) fn get_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        self.map.get_mut()
+    }
+
+    /// Borrows the resource of type `R` immutably, failing if it's borrowed mutably.
+    pub(crate) fn borrow<R: Resource>(&self) -> Result<Res<'_, R>, ResourceError> {
+        self.map.borrow().map(Res)
+    }
+
+    /// Borrows the resource of type `R` mutably, failing if it's already borrowed.
+    pub(crate) fn borrow_mut<R: Resource>(&self) -> Result<ResMut<'_, R>, ResourceError> {
+        self.map.borrow_mut().map(ResMut)
+    }
+}
+
+/// Stores the resources of the world that aren't `Send` (or `Sync`).
+///
+/// Non-send resources can only be accessed from the thread that created the world; the
+/// multi-threaded executor runs systems that use them on that thread.
+#[derive(Debug)]
+pub(crate) struct NonSendResources {
+    map: ResourceMap,
+
+    /// The thread that created the world.
+    owner: ThreadId,
+}
+
+impl Default for NonSendResources {
+    fn default() -> Self {
+        Self {
+            map: ResourceMap::default(),
+            owner: thread::current().id(),
+        }
+    }
+}
+
+impl NonSendResources {
+    /// Panics if the current thread isn't the one that created the world.
+    fn assert_owner_thread<R: 'static>(&self) {
+        if thread::current().id() != self.owner {
+            panic!(
+                "The non-send resource {} was accessed from a thread other than the one that \
+                 created the ECS",
+                std::any::type_name::<R>()
+            );
+        }
+    }
+
+    /// Inserts a non-send resource, replacing (and returning) the existing value of the same type.
+    ///
+    /// ## Panics
+    /// This will panic if the existing value is borrowed, or if this isn't the owner thread.
+    pub(crate) fn insert<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.assert_owner_thread::<R>();
+        self.map.insert(resource)
+    }
+
+    /// Removes the non-send resource of type `R`.
+    ///
+    /// ## Panics
+    /// This will panic if the resource is borrowed, or if this isn't the owner thread.
+    pub(crate) fn remove<R: 'static>(&mut self) -> Option<R> {
+        self.assert_owner_thread::<R>();
+        self.map.remove()
+    }
+
+    /// Checks if a non-send resource of type `R` exists.
+    pub(crate) fn contains<R: 'static>(&self) -> bool {
+        self.map.contains::<R>()
+    }
+
+    /// Checks if the non-send resource of type `R` is borrowed.
+    pub(crate) fn is_borrowed<R: 'static>(&self) -> bool {
+        self.map.is_borrowed::<R>()
+    }
+
+    /// Gets an immutable reference to the non-send resource of type `R`.
+    ///
+    /// ## Panics
+    /// This will panic if the resource is borrowed mutably, or if this isn't the owner thread.
+    pub(crate) fn get<R: 'static>(&self) -> Option<&R> {
+        self.assert_owner_thread::<R>();
+        self.map.get()
+    }
+
+    /// Gets a mutable reference to the non-send resource of type `R`.
+    ///
+    /// ## Panics
+    /// This will panic if the resource is borrowed, or if this isn't the owner thread.
+    pub(crate) fn get_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.assert_owner_thread::<R>();
+        self.map.get_mut()
+    }
+
+    /// Borrows the non-send resource of type `R` immutably, failing if it's borrowed mutably.
+    ///
+    /// ## Panics
+    /// This will panic if this isn't the owner thread.
+    pub(crate) fn borrow<R: 'static>(&self) -> Result<NonSend<'_, R>, ResourceError> {
+        self.assert_owner_thread::<R>();
+        self.map.borrow().map(NonSend)
+    }
+
+    /// Borrows the non-send resource of type `R` mutably, failing if it's already borrowed.
+    ///
+    /// ## Panics
+    /// This will panic if this isn't the owner thread.
+    pub(crate) fn borrow_mut<R: 'static>(&self) -> Result<NonSendMut<'_, R>, ResourceError> {
+        self.assert_owner_thread::<R>();
+        self.map.borrow_mut().map(NonSendMut)
+    }
+}
+
+impl Drop for NonSendResources {
+    fn drop(&mut self) {
+        if self.map.values.is_empty() || thread::current().id() == self.owner {
+            return;
+        }
+
+        // Leak the values rather than dropping them on the wrong thread
+        std::mem::forget(std::mem::take(&mut self.map.values));
+        if !thread::panicking() {
+            panic!("Non-send resources were dropped on a thread other than the one that created the ECS");
+        }
+    }
+}
+
+/// A tracked immutable borrow of a value in a `ResourceMap`.
+struct Borrowed<'w, R> {
+    value: &'w R,
+    borrows: &'w AtomicIsize,
+}
+
+impl<R> Drop for Borrowed<'_, R> {
+    fn drop(&mut self) {
+        self.borrows.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// A tracked mutable borrow of a value in a `ResourceMap`.
+struct BorrowedMut<'w, R> {
+    value: &'w mut R,
+    borrows: &'w AtomicIsize,
+}
+
+impl<R> Drop for BorrowedMut<'_, R> {
+    fn drop(&mut self) {
+        self.borrows.store(0, Ordering::Release);
+    }
+}
+
+/// Implements `Deref` and `Debug` for a borrow type.
+macro_rules! impl_borrow {
+    ($name:ident: $bound:tt) => {
+        impl<R: $bound> Deref for $name<'_, R> {
+            type Target = R;
+
+            fn deref(&self) -> &Self::Target {
+                self.0.value
+            }
+        }
+
+        impl<R: $bound + Debug> Debug for $name<'_, R> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple(stringify!($name))
+                    .field(self.0.value)
+                    .finish()
+            }
+        }
+    };
+}
+
+/// Implements `Deref`, `DerefMut` and `Debug` for a mutable borrow type.
+macro_rules! impl_borrow_mut {
+    ($name:ident: $bound:tt) => {
+        impl_borrow!($name: $bound);
+
+        impl<R: $bound> DerefMut for $name<'_, R> {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                self.0.value
+            }
+        }
+    };
+}
+
+/// An immutable borrow of the resource of type `R`.
+///
+/// Any number of immutable borrows can exist at the same time, but not while the resource is
+/// borrowed mutably.
+pub struct Res<'w, R: Resource>(Borrowed<'w, R>);
+impl_borrow!(Res: Resource);
+
+/// A mutable borrow of the resource of type `R`.
+///
+/// The resource can't be borrowed again until this is dropped.
+pub struct ResMut<'w, R: Resource>(BorrowedMut<'w, R>);
+impl_borrow_mut!(ResMut: Resource);
+
+/// An immutable borrow of the non-send resource of type `R`.
+///
+/// Systems that take this as a parameter always run on the thread that created the ECS.
+pub struct NonSend<'w, R: 'static>(Borrowed<'w, R>);
+impl_borrow!(NonSend: 'static);
+
+/// A mutable borrow of the non-send resource of type `R`.
+///
+/// Systems that take this as a parameter always run on the thread that created the ECS.
+pub struct NonSendMut<'w, R: 'static>(BorrowedMut<'w, R>);
+impl_borrow_mut!(NonSendMut: 'static);
@@ -0,0 +1,86 @@
+use std::{any::TypeId, collections::HashSet};
+
+use crate::ComponentId;
+
+/// The components and resources a system reads and writes.
+///
+/// Systems with compatible accesses can be run in parallel by the multi-threaded executor.
+///
+/// This is `pub` (but can't be named outside the crate) since system parameters add their own
+/// accesses through `SystemParam::access`.
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    /// Components that are only read.
+    component_reads: HashSet<ComponentId>,
+
+    /// Components that are written (and possibly read).
+    component_writes: HashSet<ComponentId>,
+
+    /// Resources that are only read.
+    resource_reads: HashSet<TypeId>,
+
+    /// Resources that are written (and possibly read).
+    resource_writes: HashSet<TypeId>,
+}
+
+impl Access {
+    /// Adds read access to the specified component.
+    pub(crate) fn add_component_read(&mut self, component_id: ComponentId) {
+        self.component_reads.insert(component_id);
+    }
+
+    /// Adds write access to the specified component.
+    pub(crate) fn add_component_write(&mut self, component_id: ComponentId) {
+        self.component_writes.insert(component_id);
+    }
+
+    /// Checks if the specified component can be read.
+    pub(crate) fn has_component_read(&self, component_id: ComponentId) -> bool {
+        self.component_reads.contains(&component_id)
+            || self.component_writes.contains(&component_id)
+    }
+
+    /// Checks if the specified component can be written.
+    pub(crate) fn has_component_write(&self, component_id: ComponentId) -> bool {
+        self.component_writes.contains(&component_id)
+    }
+
+    /// Adds read access to the specified resource.
+    pub(crate) fn add_resource_read(&mut self, resource_id: TypeId) {
+        self.resource_reads.insert(resource_id);
+    }
+
+    /// Adds write access to the specified resource.
+    pub(crate) fn add_resource_write(&mut self, resource_id: TypeId) {
+        self.resource_writes.insert(resource_id);
+    }
+
+    /// Checks if the specified resource can be read.
+    pub(crate) fn has_resource_read(&self, resource_id: TypeId) -> bool {
+        self.resource_reads.contains(&resource_id) || self.resource_writes.contains(&resource_id)
+    }
+
+    /// Checks if the specified resource can be written.
+    pub(crate) fn has_resource_write(&self, resource_id: TypeId) -> bool {
+        self.resource_writes.contains(&resource_id)
+    }
+
+    /// Checks if a system with this access can run at the same time as one with `other`.
+    ///
+    /// Accesses are compatible unless one of them writes a component (or resource) the other
+    /// reads or writes.
+    pub(crate) fn is_compatible(&self, other: &Self) -> bool {
+        self.is_compatible_one_way(other) && other.is_compatible_one_way(self)
+    }
+
+    /// Checks that `other` doesn't read anything this access writes.
+    fn is_compatible_one_way(&self, other: &Self) -> bool {
+        self.component_writes
+            .iter()
+            .all(|id| !other.has_component_read(*id))
+            && self
+                .resource_writes
+                .iter()
+                .all(|id| !other.has_resource_read(*id))
+    }
+}
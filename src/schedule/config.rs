@@ -0,0 +1,381 @@
+use std::{
+    any::{Any, TypeId},
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{
+    change_detection::{Tick, MAX_CHANGE_AGE},
+    resource::Resource,
+    system::{BoxedSystem, IntoSystem},
+    world::{World, WorldCell},
+    Component, ComponentId, Context, EcsError, EcsResult, System,
+};
+
+use super::{
+    access::Access,
+    error_policy::{ErrorPolicy, PanicPolicy},
+    executor::RunSettings,
+    run_rate::{RunRate, Throttle},
+};
+
+/// A condition that decides whether a system should run.
+pub trait Condition: Send + 'static {
+    fn evaluate(&mut self, ctx: Context) -> bool;
+}
+
+impl<F> Condition for F
+where
+    F: Fn(Context) -> bool + Send + 'static,
+{
+    fn evaluate(&mut self, ctx: Context) -> bool {
+        self(ctx)
+    }
+}
+
+/// A system along with the configuration that controls how it's run.
+pub struct SystemConfig {
+    /// The system to run.
+    pub(crate) system: BoxedSystem,
+
+    /// The name of the system, used to order it relative to other systems.
+    pub(crate) name: String,
+
+    /// Conditions that must all be `true` for the system to run.
+    pub(crate) conditions: Vec<Box<dyn Condition>>,
+
+    /// The components the system accesses.
+    ///
+    /// Systems that haven't declared their access (`None`) are assumed to access the entire
+    /// world, and never run in parallel with other systems.
+    pub(crate) access: Option<Access>,
+
+    /// Names of systems that must run before this one.
+    pub(crate) after: Vec<String>,
+
+    /// Names of systems that must run after this one.
+    pub(crate) before: Vec<String>,
+
+    /// How errors returned by the system are handled (`None` uses the ECS's policy).
+    pub(crate) error_policy: Option<ErrorPolicy>,
+
+    /// Whether the system is run (systems are disabled after panicking with
+    /// `PanicPolicy::CatchAndDisable`).
+    pub(crate) enabled: bool,
+
+    /// How often the system is run (`None` runs it every time its schedule runs).
+    pub(crate) throttle: Option<Throttle>,
+
+    /// Whether `System::initialize` was called (and `System::shutdown` still has to be).
+    pub(crate) initialized: bool,
+
+    /// Whether the system must run on the thread that created the ECS (e.g. because it accesses
+    /// non-send resources).
+    pub(crate) main_thread: bool,
+
+    /// The tick of the system's last run, which change detection is relative to.
+    pub(crate) last_run: Tick,
+}
+
+impl SystemConfig {
+    /// Initializes the system if it wasn't already.
+    pub(crate) fn initialize(&mut self, world: &mut World) {
+        if !self.initialized {
+            self.initialized = true;
+            self.system.initialize(world);
+
+            // Everything that already exists is new to the system
+            self.last_run = world.change_tick().before(MAX_CHANGE_AGE);
+        }
+    }
+
+    /// Shuts the system down if it was initialized.
+    pub(crate) fn shutdown(&mut self, world: &mut World) {
+        if self.initialized {
+            self.initialized = false;
+            self.system.shutdown(world);
+        }
+    }
+
+    /// Checks if the system must run on the thread that created the ECS.
+    ///
+    /// Systems that haven't declared their access may access anything (including non-send
+    /// resources), so they're always run on that thread as well.
+    pub(crate) fn runs_on_main_thread(&self) -> bool {
+        self.main_thread || self.is_exclusive()
+    }
+
+    /// Checks if the system has exclusive access to the world (because it didn't declare its
+    /// access).
+    pub(crate) fn is_exclusive(&self) -> bool {
+        self.access.is_none()
+    }
+
+    /// Runs the system if it's due (according to its run rate) and all of its conditions are met.
+    pub(crate) fn run(&mut self, world: WorldCell) -> EcsResult<()> {
+        // SAFETY: The time is only modified between ticks
+        let time = unsafe { world.world() }.time();
+        if let Some(throttle) = &self.throttle {
+            if !throttle.is_due(time) {
+                return Ok(());
+            }
+        }
+
+        // SAFETY: The tick counter is atomic
+        let this_run = unsafe { world.world() }.increment_change_tick();
+        let mut ctx = Context::new(
+            world,
+            self.access.as_ref(),
+            &self.name,
+            self.last_run,
+            this_run,
+        );
+
+        for condition in &mut self.conditions {
+            if !condition.evaluate(ctx.reborrow()) {
+                return Ok(());
+            }
+        }
+
+        // The system only uses up its slot once it actually runs
+        if let Some(throttle) = &mut self.throttle {
+            throttle.record_run(time);
+        }
+
+        self.system.validate_params(&ctx)?;
+        let result = self.system.run((), ctx);
+        self.last_run = this_run;
+        result
+    }
+
+    /// Runs the system (if it's enabled), handling any error it returns with its error policy
+    /// (or the schedule's policy if it doesn't have one).
+    ///
+    /// Panics are caught and handled like errors unless the panic policy is
+    /// `PanicPolicy::Propagate`.
+    pub(crate) fn run_with_policy(
+        &mut self,
+        world: WorldCell,
+        settings: RunSettings,
+    ) -> EcsResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let error = match settings.panic_policy {
+            PanicPolicy::Propagate => match self.run(world) {
+                Ok(()) => return Ok(()),
+                Err(error) => self.wrap_error(error, settings.schedule),
+            },
+            PanicPolicy::Catch | PanicPolicy::CatchAndDisable => {
+                match panic::catch_unwind(AssertUnwindSafe(|| self.run(world))) {
+                    Ok(Ok(())) => return Ok(()),
+                    Ok(Err(error)) => self.wrap_error(error, settings.schedule),
+                    Err(payload) => {
+                        if settings.panic_policy == PanicPolicy::CatchAndDisable {
+                            self.enabled = false;
+                        }
+
+                        EcsError::SystemPanicked {
+                            system: self.name.clone(),
+                            message: panic_message(payload.as_ref()),
+                        }
+                    }
+                }
+            }
+        };
+
+        self.error_policy
+            .as_ref()
+            .unwrap_or(settings.error_policy)
+            .handle(error)
+    }
+
+    /// Applies the changes the system deferred (e.g. with `Commands`).
+    ///
+    /// Errors are handled by the system's error policy, like the errors the system returns.
+    pub(crate) fn apply_deferred(
+        &mut self,
+        world: &mut World,
+        settings: RunSettings,
+    ) -> EcsResult<()> {
+        match self.system.apply_deferred(world) {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                let error = self.wrap_error(error, settings.schedule);
+                self.error_policy
+                    .as_ref()
+                    .unwrap_or(settings.error_policy)
+                    .handle(error)
+            }
+        }
+    }
+
+    /// Wraps an error returned by the system so it names the system and its schedule.
+    fn wrap_error(&self, error: EcsError, schedule: &str) -> EcsError {
+        EcsError::SystemFailed {
+            system: self.name.clone(),
+            schedule: schedule.into(),
+            source: Box::new(error),
+        }
+    }
+
+    /// Gets the declared access of the system, creating one with the accesses of its parameters
+    /// if it has none.
+    fn access_mut(&mut self) -> &mut Access {
+        let system = &self.system;
+        self.access.get_or_insert_with(|| {
+            let mut access = Access::default();
+            system.param_access(&mut access);
+            access
+        })
+    }
+}
+
+/// Gets the message of a panic from its payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+
+    "Box<dyn Any>".into()
+}
+
+/// Types that can be converted into a configured system.
+///
+/// `Marker` tells the different kinds of systems apart, and is always inferred.
+pub trait IntoSystemConfig<Marker>: Sized {
+    /// Converts `self` into a system config.
+    fn into_config(self) -> SystemConfig;
+
+    /// Only runs the system if the condition returns `true`.
+    ///
+    /// Conditions are evaluated every time the system's schedule is run, in the order they were
+    /// added.
+    fn run_if<C: Condition>(self, condition: C) -> SystemConfig {
+        let mut config = self.into_config();
+        config.conditions.push(Box::new(condition));
+        config
+    }
+
+    /// Throttles the system to the specified run rate.
+    ///
+    /// A run only counts towards the rate if the system's conditions are met, so a system whose
+    /// conditions aren't met when it's due runs as soon as they are.
+    fn run_rate(self, rate: RunRate) -> SystemConfig {
+        let mut config = self.into_config();
+        config.throttle = Some(Throttle::new(rate));
+        config
+    }
+
+    /// Sets the name of the system (defaults to the system's type name).
+    fn named(self, name: impl Into<String>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.name = name.into();
+        config
+    }
+
+    /// Runs the system after all systems with the specified name (in the same schedule).
+    fn after(self, name: impl Into<String>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.after.push(name.into());
+        config
+    }
+
+    /// Runs the system before all systems with the specified name (in the same schedule).
+    fn before(self, name: impl Into<String>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.before.push(name.into());
+        config
+    }
+
+    /// Sets how errors returned by the system are handled, overriding the ECS's error policy.
+    fn on_error(self, error_policy: ErrorPolicy) -> SystemConfig {
+        let mut config = self.into_config();
+        config.error_policy = Some(error_policy);
+        config
+    }
+
+    /// Always runs the system on the thread that created the ECS.
+    ///
+    /// This is only needed for systems that access non-send resources through their `Context`;
+    /// systems that take `NonSend` or `NonSendMut` parameters already run on that thread.
+    fn on_main_thread(self) -> SystemConfig {
+        let mut config = self.into_config();
+        config.main_thread = true;
+        config
+    }
+
+    /// Declares that the system reads the resource of type `R`.
+    ///
+    /// Like component access, this lets the system run in parallel with systems it doesn't
+    /// conflict with, but only lets it access the resources it declared.
+    fn reads_resource<R: Resource>(self) -> SystemConfig {
+        let mut config = self.into_config();
+        config.access_mut().add_resource_read(TypeId::of::<R>());
+        config
+    }
+
+    /// Declares that the system writes (and possibly reads) the resource of type `R`.
+    fn writes_resource<R: Resource>(self) -> SystemConfig {
+        let mut config = self.into_config();
+        config.access_mut().add_resource_write(TypeId::of::<R>());
+        config
+    }
+
+    /// Declares that the system reads components of type `T`.
+    ///
+    /// Once a system declares any access, it may only query the components it declared (and
+    /// can't make structural changes), but it may run in parallel with systems it doesn't
+    /// conflict with.
+    fn reads<T: Component>(self) -> SystemConfig {
+        let mut config = self.into_config();
+        config
+            .access_mut()
+            .add_component_read(ComponentId::of::<T>());
+        config
+    }
+
+    /// Declares that the system reads and writes components of type `T`.
+    ///
+    /// See `IntoSystemConfig::reads` for the restrictions this places on the system.
+    fn writes<T: Component>(self) -> SystemConfig {
+        let mut config = self.into_config();
+        config
+            .access_mut()
+            .add_component_write(ComponentId::of::<T>());
+        config
+    }
+}
+
+impl<S, Marker> IntoSystemConfig<Marker> for S
+where
+    S: IntoSystem<(), EcsResult<()>, Marker>,
+{
+    fn into_config(self) -> SystemConfig {
+        let system = self.into_system();
+
+        SystemConfig {
+            name: system.name().into_owned(),
+            main_thread: !system.is_send(),
+            last_run: Tick::default(),
+            system: Box::new(system),
+            conditions: Vec::new(),
+            access: None,
+            after: Vec::new(),
+            before: Vec::new(),
+            error_policy: None,
+            enabled: true,
+            throttle: None,
+            initialized: false,
+        }
+    }
+}
+
+impl IntoSystemConfig<()> for SystemConfig {
+    fn into_config(self) -> SystemConfig {
+        self
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt::Debug;
+
+use crate::{world::World, EcsResult};
+
+use super::{config::SystemConfig, ScheduleError, ScheduleId};
+
+/// A change to a schedule that is queued while the ECS is running, and applied between ticks.
+pub(crate) enum ScheduleEdit {
+    AddSystem(ScheduleId, Box<SystemConfig>),
+    RemoveSystem(ScheduleId, String),
+    SetEnabled(ScheduleId, String, bool),
+}
+
+impl Debug for ScheduleEdit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AddSystem(id, system) => f
+                .debug_tuple("AddSystem")
+                .field(id)
+                .field(&system.name)
+                .finish(),
+            Self::RemoveSystem(id, name) => {
+                f.debug_tuple("RemoveSystem").field(id).field(name).finish()
+            }
+            Self::SetEnabled(id, name, enabled) => f
+                .debug_tuple("SetEnabled")
+                .field(id)
+                .field(name)
+                .field(enabled)
+                .finish(),
+        }
+    }
+}
+
+/// Applies all queued schedule edits, in the order they were queued.
+///
+/// Edits that refer to a missing schedule or system are skipped, and the first of those errors is
+/// returned once the other edits are applied.
+pub(crate) fn apply_edits(world: &mut World) -> EcsResult<()> {
+    let mut first_error = None;
+
+    for edit in world.schedules_mut().take_edits() {
+        let result = match edit {
+            ScheduleEdit::AddSystem(id, system) => {
+                world.schedules_mut().entry(id).add_system(*system);
+                Ok(())
+            }
+            ScheduleEdit::RemoveSystem(id, name) => {
+                remove_systems(world, &id, &name).map(|removed| {
+                    for mut system in removed {
+                        system.shutdown(world);
+                    }
+                })
+            }
+            ScheduleEdit::SetEnabled(id, name, enabled) => {
+                find_systems(world, &id, &name).map(|systems| {
+                    for system in systems {
+                        system.enabled = enabled;
+                    }
+                })
+            }
+        };
+
+        if let Err(error) = result {
+            first_error.get_or_insert(error);
+        }
+    }
+
+    match first_error {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// Removes the systems with the specified name from the schedule.
+fn remove_systems(
+    world: &mut World,
+    id: &ScheduleId,
+    name: &str,
+) -> Result<Vec<SystemConfig>, ScheduleError> {
+    let schedule = world
+        .schedules_mut()
+        .get_mut(id)
+        .ok_or_else(|| ScheduleError::ScheduleNotFound(id.name().into()))?;
+
+    let removed = schedule.remove_systems(name);
+    if removed.is_empty() {
+        return Err(ScheduleError::SystemNotFound {
+            schedule: id.name().into(),
+            system: name.into(),
+        });
+    }
+
+    Ok(removed)
+}
+
+/// Finds the systems with the specified name in the schedule.
+fn find_systems<'a>(
+    world: &'a mut World,
+    id: &ScheduleId,
+    name: &str,
+) -> Result<Vec<&'a mut SystemConfig>, ScheduleError> {
+    let schedule = world
+        .schedules_mut()
+        .get_mut(id)
+        .ok_or_else(|| ScheduleError::ScheduleNotFound(id.name().into()))?;
+
+    let systems = schedule.systems_named(name);
+    if systems.is_empty() {
+        return Err(ScheduleError::SystemNotFound {
+            schedule: id.name().into(),
+            system: name.into(),
+        });
+    }
+
+    Ok(systems)
+}
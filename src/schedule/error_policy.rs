@@ -0,0 +1,71 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{EcsError, EcsResult};
+
+/// A handler for errors returned by systems.
+///
+/// Returning `Ok(())` lets the schedule carry on, while returning an error stops it.
+pub type ErrorHandler = Arc<dyn Fn(EcsError) -> EcsResult<()> + Send + Sync>;
+
+/// What the scheduler does when a system returns an error.
+///
+/// The error is wrapped in `EcsError::SystemFailed`, which names the failing system and the
+/// schedule it ran in.
+#[derive(Clone, Default)]
+pub enum ErrorPolicy {
+    /// Stops the schedule and returns the error (the default).
+    ///
+    /// Systems that are already running are still allowed to finish.
+    #[default]
+    Abort,
+
+    /// Prints the error to `stderr` and keeps running the remaining systems.
+    Log,
+
+    /// Passes the error to a handler, which decides whether the schedule carries on.
+    Custom(ErrorHandler),
+}
+
+impl ErrorPolicy {
+    /// Creates a policy that passes errors to the specified handler.
+    pub fn custom(handler: impl Fn(EcsError) -> EcsResult<()> + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(handler))
+    }
+
+    /// Handles an error returned by a system.
+    pub(crate) fn handle(&self, error: EcsError) -> EcsResult<()> {
+        match self {
+            Self::Abort => Err(error),
+            Self::Log => {
+                eprintln!("{error}");
+                Ok(())
+            }
+            Self::Custom(handler) => handler(error),
+        }
+    }
+}
+
+/// What the scheduler does when a system panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// Lets the panic unwind out of `Ecs::update` (the default).
+    #[default]
+    Propagate,
+
+    /// Catches the panic and turns it into an `EcsError::SystemPanicked`, which is then handled
+    /// by the system's error policy.
+    Catch,
+
+    /// Like `Catch`, but also disables the system so it doesn't run again.
+    CatchAndDisable,
+}
+
+impl Debug for ErrorPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Abort => write!(f, "Abort"),
+            Self::Log => write!(f, "Log"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    collections::{BTreeSet, HashSet, VecDeque},
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex, OnceLock, PoisonError},
+    thread,
+};
+
+use crate::{world::WorldCell, EcsResult};
+
+use super::{
+    config::SystemConfig,
+    error_policy::{ErrorPolicy, PanicPolicy},
+    ScheduleError,
+};
+
+/// The strategy used to run the systems of a schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutorKind {
+    /// Runs systems one at a time on the calling thread, in a deterministic order.
+    #[default]
+    SingleThreaded,
+
+    /// Runs systems on a pool of threads, where systems whose declared accesses don't conflict
+    /// may run at the same time.
+    MultiThreaded,
+}
+
+/// Settings shared by all systems of a running schedule.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RunSettings<'a> {
+    /// The name of the schedule.
+    pub(crate) schedule: &'a str,
+
+    /// How errors are handled for systems that don't have their own error policy.
+    pub(crate) error_policy: &'a ErrorPolicy,
+
+    /// What happens when a system panics.
+    pub(crate) panic_policy: PanicPolicy,
+}
+
+/// The order that the systems of a schedule must run in.
+#[derive(Debug)]
+pub(crate) struct ScheduleGraph {
+    /// System indices in an order that satisfies all dependencies.
+    order: Vec<usize>,
+
+    /// Number of systems that must finish before each system can start.
+    num_dependencies: Vec<usize>,
+
+    /// Systems that can't start before each system has finished.
+    dependents: Vec<Vec<usize>>,
+}
+
+impl ScheduleGraph {
+    /// Builds the dependency graph for the specified systems.
+    ///
+    /// Systems are ordered by their explicit `before`/`after` constraints, falling back to the
+    /// order they were added in. Any two systems whose accesses conflict always run in that
+    /// order, never at the same time.
+    pub(crate) fn build(schedule: &str, systems: &[SystemConfig]) -> Result<Self, ScheduleError> {
+        let num_systems = systems.len();
+        let mut edges = vec![HashSet::new(); num_systems];
+
+        let systems_named = |name: &str| -> Result<Vec<usize>, ScheduleError> {
+            let indices = systems
+                .iter()
+                .enumerate()
+                .filter(|(_, system)| system.name == name)
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+
+            if indices.is_empty() {
+                return Err(ScheduleError::SystemNotFound {
+                    schedule: schedule.into(),
+                    system: name.into(),
+                });
+            }
+
+            Ok(indices)
+        };
+
+        // Add explicit ordering constraints
+        for (idx, system) in systems.iter().enumerate() {
+            for name in &system.after {
+                for dependency in systems_named(name)? {
+                    if dependency != idx {
+                        edges[dependency].insert(idx);
+                    }
+                }
+            }
+
+            for name in &system.before {
+                for dependent in systems_named(name)? {
+                    if dependent != idx {
+                        edges[idx].insert(dependent);
+                    }
+                }
+            }
+        }
+
+        // Sort topologically, preferring the order systems were added in
+        let mut in_degree = vec![0; num_systems];
+        for dependents in &edges {
+            for &dependent in dependents {
+                in_degree[dependent] += 1;
+            }
+        }
+
+        let mut ready = (0..num_systems)
+            .filter(|&idx| in_degree[idx] == 0)
+            .collect::<BTreeSet<_>>();
+        let mut order = Vec::with_capacity(num_systems);
+        while let Some(idx) = ready.pop_first() {
+            order.push(idx);
+
+            for &dependent in &edges[idx] {
+                in_degree[dependent] -= 1;
+                if in_degree[dependent] == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        if order.len() != num_systems {
+            return Err(ScheduleError::DependencyCycle(schedule.into()));
+        }
+
+        // Systems with conflicting accesses must run in order
+        for (position, &first) in order.iter().enumerate() {
+            for &second in &order[position + 1..] {
+                let compatible = match (&systems[first].access, &systems[second].access) {
+                    (Some(first), Some(second)) => first.is_compatible(second),
+                    _ => false,
+                };
+
+                if !compatible {
+                    edges[first].insert(second);
+                }
+            }
+        }
+
+        let mut num_dependencies = vec![0; num_systems];
+        let dependents = edges
+            .into_iter()
+            .map(|dependents| {
+                let mut dependents = dependents.into_iter().collect::<Vec<_>>();
+                dependents.sort();
+                for &dependent in &dependents {
+                    num_dependencies[dependent] += 1;
+                }
+                dependents
+            })
+            .collect();
+
+        Ok(Self {
+            order,
+            num_dependencies,
+            dependents,
+        })
+    }
+}
+
+/// Runs the systems one at a time, in the order given by the graph.
+pub(crate) fn run_single_threaded(
+    systems: &mut [SystemConfig],
+    graph: &ScheduleGraph,
+    world: WorldCell,
+    settings: RunSettings,
+) -> EcsResult<()> {
+    for (position, &idx) in graph.order.iter().enumerate() {
+        // Sync point: exclusive systems see the changes deferred by the systems before them
+        if systems[idx].is_exclusive() {
+            // SAFETY: No other system is running
+            let world = unsafe { world.world_mut() };
+            for &earlier in &graph.order[..position] {
+                systems[earlier].apply_deferred(world, settings)?;
+            }
+        }
+
+        systems[idx].run_with_policy(world, settings)?;
+    }
+
+    Ok(())
+}
+
+/// Spawns the reserved entities, then applies the changes deferred by the systems (e.g. with
+/// `Commands`) in the order given by the graph.
+///
+/// The changes of every system are applied even if some fail; the first error is returned.
+pub(crate) fn apply_deferred(
+    systems: &mut [SystemConfig],
+    graph: &ScheduleGraph,
+    world: WorldCell,
+    settings: RunSettings,
+) -> EcsResult<()> {
+    // SAFETY: Deferred changes are only applied once the systems of the schedule have finished
+    let world = unsafe { world.world_mut() };
+
+    let mut result = world.flush_entities();
+    for &idx in &graph.order {
+        let applied = systems[idx].apply_deferred(world, settings);
+        if result.is_ok() {
+            result = applied;
+        }
+    }
+    result
+}
+
+/// Message sent back to the executor once a worker has run a system.
+type Completion = (usize, SystemConfig, thread::Result<EcsResult<()>>);
+
+/// A job that is run by a worker thread of the `TaskPool`.
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// The worker threads that run the systems of multi-threaded schedules.
+///
+/// The threads are started the first time a multi-threaded schedule runs and are kept alive
+/// for the rest of the program, so running a schedule doesn't spawn any threads.
+struct TaskPool {
+    sender: mpsc::Sender<Task>,
+}
+
+impl TaskPool {
+    /// Gets the pool shared by all schedules, starting its threads if needed.
+    fn get() -> &'static Self {
+        static POOL: OnceLock<TaskPool> = OnceLock::new();
+
+        POOL.get_or_init(|| {
+            let num_threads = thread::available_parallelism()
+                .map(NonZeroUsize::get)
+                .unwrap_or(1);
+
+            let (sender, receiver) = mpsc::channel::<Task>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            for _ in 0..num_threads {
+                let receiver = Arc::clone(&receiver);
+
+                thread::spawn(move || loop {
+                    let task = receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .recv();
+                    let Ok(task) = task else {
+                        break;
+                    };
+
+                    task();
+                });
+            }
+
+            Self { sender }
+        })
+    }
+
+    /// Runs the task on one of the worker threads.
+    ///
+    /// ## Safety
+    /// The task may borrow data that doesn't live for `'static`, in which case the caller must
+    /// make sure the data outlives the task (i.e. wait for it to finish, even when unwinding).
+    unsafe fn spawn<'a>(&self, task: Box<dyn FnOnce() + Send + 'a>) {
+        // SAFETY: The caller makes sure the task finishes before its borrows end
+        let task = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'a>, Task>(task) };
+
+        self.sender.send(task).expect("Worker threads exited");
+    }
+}
+
+/// Waits for the systems that were sent to the `TaskPool` by a schedule run.
+///
+/// The systems borrow the world and the settings of the run, so the executor can't return
+/// (or unwind) before all of them have finished.
+struct PendingSystems {
+    receiver: mpsc::Receiver<Completion>,
+
+    /// The number of systems that were sent to the pool and haven't finished.
+    count: usize,
+}
+
+impl PendingSystems {
+    /// Waits for a system to finish.
+    fn wait(&mut self) -> Completion {
+        let completion = self.receiver.recv().expect("Worker threads exited early");
+        self.count -= 1;
+        completion
+    }
+}
+
+impl Drop for PendingSystems {
+    fn drop(&mut self) {
+        while self.count > 0 {
+            let _ = self.wait();
+        }
+    }
+}
+
+/// Runs the systems on the pool of worker threads, starting each system as soon as all of its
+/// dependencies have finished.
+///
+/// Systems that must run on the main thread (see `SystemConfig::runs_on_main_thread`) are run on
+/// the calling thread instead, in between waiting for the workers. This includes exclusive
+/// systems, so schedules they run can use the worker threads too.
+///
+/// If a system fails (and its error policy doesn't let the schedule carry on), no new systems
+/// are started, and the first error is returned once the systems that are already running have
+/// finished.
+pub(crate) fn run_multi_threaded(
+    systems: &mut Vec<SystemConfig>,
+    graph: &ScheduleGraph,
+    world: WorldCell,
+    settings: RunSettings,
+) -> EcsResult<()> {
+    let num_systems = systems.len();
+    if num_systems == 0 {
+        return Ok(());
+    }
+
+    let pool = TaskPool::get();
+    let mut slots = systems.drain(..).map(Some).collect::<Vec<_>>();
+    let mut remaining_dependencies = graph.num_dependencies.clone();
+
+    let (completion_sender, completion_receiver) = mpsc::channel::<Completion>();
+    let mut pending = PendingSystems {
+        receiver: completion_receiver,
+        count: 0,
+    };
+
+    let mut result = Ok(());
+    let mut panic = None;
+    let mut in_flight = 0;
+    let mut main_queue = VecDeque::new();
+
+    let start = |idx,
+                 mut system: SystemConfig,
+                 main_queue: &mut VecDeque<_>,
+                 pending: &mut PendingSystems| {
+        if system.runs_on_main_thread() {
+            main_queue.push_back((idx, system));
+            return;
+        }
+
+        let completion_sender = completion_sender.clone();
+        let task = Box::new(move || {
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(|| system.run_with_policy(world, settings)));
+            // The executor is always waiting for the system (see `PendingSystems`)
+            let _ = completion_sender.send((idx, system, result));
+        });
+
+        // SAFETY: `pending` waits for the task before the executor returns or unwinds
+        unsafe { pool.spawn(task) };
+        pending.count += 1;
+    };
+
+    for idx in 0..num_systems {
+        if remaining_dependencies[idx] == 0 {
+            let system = slots[idx].take().expect("System was already started");
+            start(idx, system, &mut main_queue, &mut pending);
+            in_flight += 1;
+        }
+    }
+
+    while in_flight > 0 {
+        // Only wait for the workers once there's nothing left to run on this thread
+        let (idx, system, system_result) = match main_queue.pop_front() {
+            Some((idx, mut system)) => {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    // Sync point: exclusive systems never run at the same time as other
+                    // systems, so all systems before them have finished
+                    if system.is_exclusive() {
+                        // SAFETY: No other system is running
+                        let world = unsafe { world.world_mut() };
+                        for &earlier in &graph.order {
+                            if let Some(earlier) = &mut slots[earlier] {
+                                earlier.apply_deferred(world, settings)?;
+                            }
+                        }
+                    }
+
+                    system.run_with_policy(world, settings)
+                }));
+                (idx, system, result)
+            }
+            None => pending.wait(),
+        };
+        slots[idx] = Some(system);
+        in_flight -= 1;
+
+        match system_result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+            Err(payload) => {
+                panic.get_or_insert(payload);
+            }
+        }
+
+        // Stop starting new systems once one has failed
+        if result.is_err() || panic.is_some() {
+            continue;
+        }
+
+        for &dependent in &graph.dependents[idx] {
+            remaining_dependencies[dependent] -= 1;
+            if remaining_dependencies[dependent] == 0 {
+                let system = slots[dependent].take().expect("System was already started");
+                start(dependent, system, &mut main_queue, &mut pending);
+                in_flight += 1;
+            }
+        }
+    }
+
+    *systems = slots
+        .into_iter()
+        .map(|system| system.expect("System wasn't returned by its worker"))
+        .collect();
+
+    if let Some(payload) = panic {
+        panic::resume_unwind(payload);
+    }
+
+    result
+}
@@ -0,0 +1,373 @@
+use std::{
+    any::TypeId,
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{
+    change_detection::Tick,
+    world::{World, WorldCell},
+    EcsResult,
+};
+
+pub(crate) mod access;
+pub(crate) mod config;
+pub(crate) mod edit;
+pub(crate) mod error_policy;
+pub(crate) mod executor;
+pub(crate) mod registry;
+pub(crate) mod run_rate;
+
+use self::{
+    config::SystemConfig,
+    edit::ScheduleEdit,
+    error_policy::{ErrorPolicy, PanicPolicy},
+    executor::{ExecutorKind, RunSettings, ScheduleGraph},
+    registry::SystemId,
+};
+
+/// Possible errors caused by schedules.
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("No schedule with the label {0} exists (or it is already running)")]
+    ScheduleNotFound(String),
+
+    #[error("No system named `{system}` exists in the schedule {schedule}")]
+    SystemNotFound { schedule: String, system: String },
+
+    #[error("The ordering constraints of the systems in the schedule {0} form a cycle")]
+    DependencyCycle(String),
+
+    #[error("No system with the id {0:?} was registered (or it is already running)")]
+    RegisteredSystemNotFound(SystemId),
+}
+
+/// A label used to identify a schedule.
+///
+/// Any hashable type can be used as a label; the type and its value together identify the
+/// schedule.
+pub trait ScheduleLabel: Debug + Hash + 'static {}
+
+/// Runs at the start of every tick, before all other schedules; event buffers are swapped here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct First;
+impl ScheduleLabel for First {}
+
+/// Runs once, before the first tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Startup;
+impl ScheduleLabel for Startup {}
+
+/// Runs every tick, before `Update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PreUpdate;
+impl ScheduleLabel for PreUpdate {}
+
+/// Runs zero or more times every tick (between `PreUpdate` and `Update`), once for every fixed
+/// timestep that has elapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedUpdate;
+impl ScheduleLabel for FixedUpdate {}
+
+/// Runs every tick; systems added with `Ecs::add_system` go here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Update;
+impl ScheduleLabel for Update {}
+
+/// Runs every tick, after `Update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PostUpdate;
+impl ScheduleLabel for PostUpdate {}
+
+/// Runs every tick, after all other schedules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Last;
+impl ScheduleLabel for Last {}
+
+/// Identifies a schedule by the type and value of its label.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ScheduleId {
+    type_id: TypeId,
+    value_hash: u64,
+    name: String,
+}
+
+impl ScheduleId {
+    /// Creates the schedule id for the specified label.
+    pub(crate) fn of<L: ScheduleLabel>(label: &L) -> Self {
+        let mut hasher = DefaultHasher::new();
+        label.hash(&mut hasher);
+
+        Self {
+            type_id: TypeId::of::<L>(),
+            value_hash: hasher.finish(),
+            name: format!("{:?}", label),
+        }
+    }
+
+    /// Returns the (debug) name of the label.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A collection of systems that are run together.
+pub(crate) struct Schedule {
+    /// The id of the schedule's label.
+    id: ScheduleId,
+
+    /// The systems in the schedule, in the order they were added.
+    systems: Vec<SystemConfig>,
+
+    /// The order the systems must run in.
+    ///
+    /// This is rebuilt the next time the schedule is run whenever systems are added.
+    graph: Option<ScheduleGraph>,
+}
+
+impl Schedule {
+    /// Creates a new, empty schedule.
+    pub(crate) fn new(id: ScheduleId) -> Self {
+        Self {
+            id,
+            systems: Vec::new(),
+            graph: None,
+        }
+    }
+
+    /// Returns the id of the schedule.
+    pub(crate) fn id(&self) -> &ScheduleId {
+        &self.id
+    }
+
+    /// Adds a system to the schedule.
+    pub(crate) fn add_system(&mut self, system: SystemConfig) {
+        self.systems.push(system);
+        self.graph = None;
+    }
+
+    /// Removes and returns the systems with the specified name.
+    pub(crate) fn remove_systems(&mut self, name: &str) -> Vec<SystemConfig> {
+        let (removed, kept) = std::mem::take(&mut self.systems)
+            .into_iter()
+            .partition(|system| system.name == name);
+        self.systems = kept;
+
+        if !removed.is_empty() {
+            self.graph = None;
+        }
+        removed
+    }
+
+    /// Gets mutable references to the systems with the specified name.
+    pub(crate) fn systems_named(&mut self, name: &str) -> Vec<&mut SystemConfig> {
+        self.systems
+            .iter_mut()
+            .filter(|system| system.name == name)
+            .collect()
+    }
+
+    /// Shuts down all initialized systems in the schedule, in the order they were added.
+    pub(crate) fn shutdown(&mut self, world: &mut World) {
+        for system in &mut self.systems {
+            system.shutdown(world);
+        }
+    }
+
+    /// Runs all systems in the schedule with the specified executor.
+    ///
+    /// Errors returned by systems are handled by their own error policy, or by `error_policy` if
+    /// they don't have one.
+    pub(crate) fn run(
+        &mut self,
+        world: WorldCell,
+        executor: ExecutorKind,
+        error_policy: &ErrorPolicy,
+        panic_policy: PanicPolicy,
+    ) -> EcsResult<()> {
+        if self.graph.is_none() {
+            self.graph = Some(ScheduleGraph::build(self.id.name(), &self.systems)?);
+
+            // SAFETY: Schedules are only run from outside of a schedule, or by exclusive systems
+            // (which never run at the same time as other systems)
+            let world = unsafe { world.world_mut() };
+            for system in &mut self.systems {
+                system.initialize(world);
+            }
+        }
+        let graph = self
+            .graph
+            .as_ref()
+            .expect("The schedule graph was just built");
+
+        let settings = RunSettings {
+            schedule: self.id.name(),
+            error_policy,
+            panic_policy,
+        };
+        let result = match executor {
+            ExecutorKind::SingleThreaded => {
+                executor::run_single_threaded(&mut self.systems, graph, world, settings)
+            }
+            ExecutorKind::MultiThreaded => {
+                executor::run_multi_threaded(&mut self.systems, graph, world, settings)
+            }
+        };
+
+        // Sync point: apply the changes deferred by all systems of the schedule
+        let applied = executor::apply_deferred(&mut self.systems, graph, world, settings);
+        result.and(applied)
+    }
+}
+
+impl Debug for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Schedule")
+            .field("id", &self.id)
+            .field("num_systems", &self.systems.len())
+            .finish()
+    }
+}
+
+/// Maps schedule labels to their schedules.
+#[derive(Debug, Default)]
+pub(crate) struct Schedules {
+    schedules: HashMap<ScheduleId, Schedule>,
+
+    /// The executor used to run all schedules.
+    executor: ExecutorKind,
+
+    /// How errors returned by systems are handled (unless a system overrides it).
+    error_policy: ErrorPolicy,
+
+    /// What happens when a system panics.
+    panic_policy: PanicPolicy,
+
+    /// Changes to schedules that are applied between ticks.
+    edits: Vec<ScheduleEdit>,
+}
+
+impl Schedules {
+    /// Adds a schedule, replacing any existing schedule with the same label.
+    pub(crate) fn insert(&mut self, schedule: Schedule) {
+        self.schedules.insert(schedule.id().clone(), schedule);
+    }
+
+    /// Removes and returns the schedule with the specified id.
+    pub(crate) fn remove(&mut self, id: &ScheduleId) -> Option<Schedule> {
+        self.schedules.remove(id)
+    }
+
+    /// Removes and returns all schedules, sorted by the names of their labels.
+    pub(crate) fn take_all(&mut self) -> Vec<Schedule> {
+        let mut schedules: Vec<_> = self
+            .schedules
+            .drain()
+            .map(|(_, schedule)| schedule)
+            .collect();
+        schedules.sort_by(|a, b| a.id.name().cmp(b.id.name()));
+        schedules
+    }
+
+    /// Gets a mutable reference to the schedule with the specified id.
+    pub(crate) fn get_mut(&mut self, id: &ScheduleId) -> Option<&mut Schedule> {
+        self.schedules.get_mut(id)
+    }
+
+    /// Queues a change to a schedule, to be applied between ticks.
+    pub(crate) fn queue_edit(&mut self, edit: ScheduleEdit) {
+        self.edits.push(edit);
+    }
+
+    /// Takes all queued changes, in the order they were queued.
+    pub(crate) fn take_edits(&mut self) -> Vec<ScheduleEdit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Checks if a schedule with the specified id exists.
+    pub(crate) fn contains(&self, id: &ScheduleId) -> bool {
+        self.schedules.contains_key(id)
+    }
+
+    /// Gets a mutable reference to the schedule with the specified id, creating an empty one if
+    /// it doesn't exist.
+    pub(crate) fn entry(&mut self, id: ScheduleId) -> &mut Schedule {
+        self.schedules
+            .entry(id.clone())
+            .or_insert_with(|| Schedule::new(id))
+    }
+
+    /// Returns the executor used to run schedules.
+    pub(crate) fn executor(&self) -> ExecutorKind {
+        self.executor
+    }
+
+    /// Sets the executor used to run schedules.
+    pub(crate) fn set_executor(&mut self, executor: ExecutorKind) {
+        self.executor = executor;
+    }
+
+    /// Returns the default policy for errors returned by systems.
+    pub(crate) fn error_policy(&self) -> &ErrorPolicy {
+        &self.error_policy
+    }
+
+    /// Sets the default policy for errors returned by systems.
+    pub(crate) fn set_error_policy(&mut self, error_policy: ErrorPolicy) {
+        self.error_policy = error_policy;
+    }
+
+    /// Returns what happens when a system panics.
+    pub(crate) fn panic_policy(&self) -> PanicPolicy {
+        self.panic_policy
+    }
+
+    /// Sets what happens when a system panics.
+    pub(crate) fn set_panic_policy(&mut self, panic_policy: PanicPolicy) {
+        self.panic_policy = panic_policy;
+    }
+
+    /// Clamps the last run ticks of all systems that are too old (see `Tick::check`).
+    pub(crate) fn check_change_ticks(&mut self, now: Tick) {
+        for schedule in self.schedules.values_mut() {
+            for system in &mut schedule.systems {
+                system.last_run.check(now);
+            }
+        }
+    }
+}
+
+/// Runs the schedule with the specified id to completion.
+///
+/// The schedule is taken out of the world while it runs, so its systems are free to access the
+/// world (and run other schedules). It is put back even if one of its systems panics.
+///
+/// Registered systems that were queued by the schedule's systems are run once it completes.
+pub(crate) fn run_schedule(world: WorldCell, id: &ScheduleId) -> EcsResult<()> {
+    // SAFETY: Schedules are only run from outside of a schedule, or by exclusive systems (which
+    // never run at the same time as other systems)
+    let (mut schedule, executor, error_policy, panic_policy) = {
+        let schedules = unsafe { world.world_mut() }.schedules_mut();
+        let schedule = schedules
+            .remove(id)
+            .ok_or_else(|| ScheduleError::ScheduleNotFound(id.name().into()))?;
+        (
+            schedule,
+            schedules.executor(),
+            schedules.error_policy().clone(),
+            schedules.panic_policy(),
+        )
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        schedule.run(world, executor, &error_policy, panic_policy)
+    }));
+    unsafe { world.world_mut() }
+        .schedules_mut()
+        .insert(schedule);
+
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))?;
+    registry::run_queued_systems(world)
+}
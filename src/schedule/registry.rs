@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    change_detection::Tick,
+    system::BoxedSystem,
+    world::{World, WorldCell},
+    Context, EcsResult,
+};
+
+use super::ScheduleError;
+
+/// Identifies a system that was registered to be run on demand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SystemId(usize);
+
+/// A registered (and initialized) system along with its name.
+struct RegisteredSystem {
+    system: BoxedSystem,
+    name: String,
+
+    /// The tick of the system's last run, which change detection is relative to.
+    last_run: Tick,
+}
+
+/// The systems that were registered to be run on demand (instead of as part of a schedule).
+#[derive(Default)]
+pub(crate) struct RegisteredSystems {
+    /// Maps ids to their systems (`None` while the system is running).
+    systems: HashMap<SystemId, Option<RegisteredSystem>>,
+
+    /// The id of the next system to be registered.
+    next_id: usize,
+
+    /// Systems that were queued to run at the end of the current schedule.
+    ///
+    /// The queue is behind a lock so systems running in parallel can add to it.
+    queued: Mutex<Vec<SystemId>>,
+}
+
+impl RegisteredSystems {
+    /// Registers a system and returns its id.
+    ///
+    /// The system's first run reports all changes since `last_run`.
+    pub(crate) fn register(&mut self, system: BoxedSystem, last_run: Tick) -> SystemId {
+        let id = SystemId(self.next_id);
+        self.next_id += 1;
+
+        let name = system.name().into_owned();
+        self.systems.insert(
+            id,
+            Some(RegisteredSystem {
+                system,
+                name,
+                last_run,
+            }),
+        );
+        id
+    }
+
+    /// Clamps the last run ticks of all systems that are too old (see `Tick::check`).
+    pub(crate) fn check_change_ticks(&mut self, now: Tick) {
+        for registered in self.systems.values_mut().flatten() {
+            registered.last_run.check(now);
+        }
+    }
+
+    /// Removes all registered systems, and shuts them down in the order they were registered.
+    ///
+    /// Systems that are currently running are skipped.
+    pub(crate) fn shutdown_all(world: &mut World) {
+        let mut systems: Vec<_> = world
+            .registered_systems_mut()
+            .systems
+            .drain()
+            .filter_map(|(id, registered)| Some((id, registered?)))
+            .collect();
+        systems.sort_by_key(|(id, _)| *id);
+
+        for (_, mut registered) in systems {
+            registered.system.shutdown(world);
+        }
+    }
+
+    /// Queues a system to run at the end of the current schedule.
+    pub(crate) fn queue(&self, id: SystemId) {
+        self.queued
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(id);
+    }
+
+    /// Takes all queued systems, in the order they were queued.
+    fn take_queued(&mut self) -> Vec<SystemId> {
+        std::mem::take(
+            self.queued
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+}
+
+impl std::fmt::Debug for RegisteredSystems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegisteredSystems")
+            .field("num_systems", &self.systems.len())
+            .finish()
+    }
+}
+
+/// Runs the registered system with the specified id, returning its result.
+///
+/// Like schedules, the system is taken out of the world while it runs (so it keeps its state
+/// between runs), and it is put back even if it panics.
+pub(crate) fn run_system(world: WorldCell, id: SystemId) -> EcsResult<()> {
+    // SAFETY: Registered systems are only run from outside of a schedule, or by exclusive systems
+    // (which never run at the same time as other systems)
+    let mut registered = unsafe { world.world_mut() }
+        .registered_systems_mut()
+        .systems
+        .get_mut(&id)
+        .and_then(Option::take)
+        .ok_or(ScheduleError::RegisteredSystemNotFound(id))?;
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let this_run = unsafe { world.world() }.increment_change_tick();
+        let ctx = Context::new(world, None, &registered.name, registered.last_run, this_run);
+        let result = registered
+            .system
+            .validate_params(&ctx)
+            .and_then(|()| registered.system.run((), ctx));
+        registered.last_run = this_run;
+
+        // The system has finished, so the changes it deferred can be applied right away
+        let applied = registered
+            .system
+            .apply_deferred(unsafe { world.world_mut() });
+        result.and(applied)
+    }));
+    unsafe { world.world_mut() }
+        .registered_systems_mut()
+        .systems
+        .insert(id, Some(registered));
+
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Runs all systems that were queued with `Context::run_system_deferred`, in order.
+///
+/// Systems queued while running the queue are run as well.
+pub(crate) fn run_queued_systems(world: WorldCell) -> EcsResult<()> {
+    loop {
+        // SAFETY: See `run_system`
+        let queued = unsafe { world.world_mut() }
+            .registered_systems_mut()
+            .take_queued();
+        if queued.is_empty() {
+            return Ok(());
+        }
+
+        for id in queued {
+            run_system(world, id)?;
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use crate::time::Time;
+
+/// How often a system is run, which can be used to throttle systems that don't need to run
+/// every tick (e.g. AI or pathfinding).
+///
+/// Throttled systems are run at most once per tick. Giving systems with the same rate different
+/// phases spreads them across ticks, instead of running all of them in the same tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunRate {
+    interval: Interval,
+
+    /// The offset of the runs, as a fraction of the interval in the range `[0, 1)`.
+    phase: f64,
+}
+
+/// The interval between runs of a throttled system.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Interval {
+    Ticks(u64),
+    Time(Duration),
+}
+
+impl RunRate {
+    /// Runs the system every `ticks` ticks (on ticks whose index is a multiple of `ticks`).
+    ///
+    /// If the system can't run on such a tick (e.g. because its conditions aren't met), it runs
+    /// on the next tick it can, and then on the next multiple after that.
+    ///
+    /// ## Panics
+    /// This will panic if `ticks` is zero.
+    pub fn every_ticks(ticks: u32) -> Self {
+        assert!(ticks > 0, "The run rate interval must be non-zero");
+
+        Self {
+            interval: Interval::Ticks(ticks.into()),
+            phase: 0.0,
+        }
+    }
+
+    /// Runs the system at most `hz` times per second of virtual time (the time passed to
+    /// `Ecs::update`).
+    ///
+    /// The system runs in the first tick at or after each multiple of the interval (`1 / hz`
+    /// seconds). Runs that are missed because ticks are longer than the interval are skipped, not
+    /// made up for in later ticks.
+    ///
+    /// ## Panics
+    /// This will panic if `hz` isn't positive and finite, or is so large that the interval rounds
+    /// to zero.
+    pub fn hz(hz: f64) -> Self {
+        assert!(
+            hz > 0.0 && hz.is_finite(),
+            "The run rate frequency must be positive and finite"
+        );
+        let interval = Duration::from_secs_f64(1.0 / hz);
+        assert!(
+            !interval.is_zero(),
+            "The run rate interval must be non-zero"
+        );
+
+        Self {
+            interval: Interval::Time(interval),
+            phase: 0.0,
+        }
+    }
+
+    /// Offsets the runs by the specified fraction of the interval.
+    ///
+    /// For example, two systems that run every 4 ticks with phases of `0.0` and `0.5` run on
+    /// ticks 0, 4, 8, ... and 2, 6, 10, ... respectively.
+    ///
+    /// ## Panics
+    /// This will panic if `phase` isn't in the range `[0, 1)`.
+    pub fn with_phase(mut self, phase: f64) -> Self {
+        assert!(
+            (0.0..1.0).contains(&phase),
+            "The run rate phase must be in the range [0, 1)"
+        );
+
+        self.phase = phase;
+        self
+    }
+}
+
+/// Keeps track of when a throttled system has to run next.
+#[derive(Debug, Clone)]
+pub(crate) struct Throttle {
+    rate: RunRate,
+
+    /// The tick the system has to run in next (for rates in ticks).
+    next_tick: u64,
+
+    /// The virtual time the system has to run at next (for rates in Hz).
+    next_run: Duration,
+}
+
+impl Throttle {
+    /// Creates a throttle for the specified run rate.
+    pub(crate) fn new(rate: RunRate) -> Self {
+        let (next_tick, next_run) = match rate.interval {
+            Interval::Ticks(interval) => ((interval as f64 * rate.phase) as u64, Duration::ZERO),
+            Interval::Time(interval) => (0, interval.mul_f64(rate.phase)),
+        };
+
+        Self {
+            rate,
+            next_tick,
+            next_run,
+        }
+    }
+
+    /// Checks if the system is due in the current tick.
+    ///
+    /// A system stays due until a run is recorded, so it isn't delayed by a whole interval if it
+    /// can't run on the tick it became due in (e.g. because its conditions weren't met).
+    pub(crate) fn is_due(&self, time: &Time) -> bool {
+        match self.rate.interval {
+            Interval::Ticks(_) => time.tick() >= self.next_tick,
+            Interval::Time(_) => time.elapsed() >= self.next_run,
+        }
+    }
+
+    /// Records that the system ran in the current tick, so it's due again at the first multiple
+    /// of the interval (offset by the phase) after the current tick or time.
+    pub(crate) fn record_run(&mut self, time: &Time) {
+        match self.rate.interval {
+            Interval::Ticks(interval) => {
+                let missed = time.tick().saturating_sub(self.next_tick) / interval;
+                self.next_tick += (missed + 1) * interval;
+            }
+            Interval::Time(interval) => {
+                let interval = interval.as_nanos();
+                let missed = time.elapsed().saturating_sub(self.next_run).as_nanos() / interval;
+                self.next_run += Duration::from_nanos(((missed + 1) * interval) as u64);
+            }
+        }
+    }
+}
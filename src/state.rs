@@ -0,0 +1,140 @@
+use std::{fmt::Debug, hash::Hash, marker::PhantomData, ops::Deref};
+
+use crate::{
+    schedule::{config::Condition, ScheduleId, ScheduleLabel},
+    Context, EcsResult, System,
+};
+
+/// Possible errors caused by states.
+#[derive(Debug, thiserror::Error)]
+pub enum StateError {
+    #[error("The state {0} was never added to the ECS")]
+    StateNotFound(&'static str),
+}
+
+/// A finite set of states that the game can be in (e.g. an enum of menu/playing/paused).
+pub trait States: Debug + Clone + PartialEq + Eq + Hash + Send + Sync + 'static {}
+
+/// The current value of the state `S`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct State<S: States>(pub(crate) S);
+
+impl<S: States> State<S> {
+    /// Gets the current state.
+    pub fn get(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S: States> Deref for State<S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The state that `S` will transition to the next time transitions are processed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NextState<S: States>(Option<S>);
+
+impl<S: States> NextState<S> {
+    /// Queues a transition to the specified state.
+    pub fn set(&mut self, state: S) {
+        self.0 = Some(state);
+    }
+
+    /// Gets the queued state (if any).
+    pub fn get(&self) -> Option<&S> {
+        self.0.as_ref()
+    }
+
+    /// Takes the queued state, leaving no transition queued.
+    pub(crate) fn take(&mut self) -> Option<S> {
+        self.0.take()
+    }
+}
+
+/// The current and next values of the state `S`.
+#[derive(Debug)]
+pub(crate) struct StateData<S: States> {
+    pub(crate) current: State<S>,
+    pub(crate) next: NextState<S>,
+}
+
+impl<S: States> StateData<S> {
+    /// Creates new state data for the specified initial state.
+    pub(crate) fn new(initial: S) -> Self {
+        Self {
+            current: State(initial),
+            next: NextState(None),
+        }
+    }
+}
+
+/// Runs every tick after `PreUpdate`, and applies any queued state transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateTransition;
+impl ScheduleLabel for StateTransition {}
+
+/// Runs when the state `S` is entered.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnEnter<S: States>(pub S);
+impl<S: States> ScheduleLabel for OnEnter<S> {}
+
+/// Runs when the state `S` is exited.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnExit<S: States>(pub S);
+impl<S: States> ScheduleLabel for OnExit<S> {}
+
+/// Creates a condition that is `true` while the state `S` is equal to `state`.
+pub fn in_state<S: States>(state: S) -> impl Condition {
+    move |ctx: Context| {
+        ctx.state::<S>()
+            .map(|current| *current == state)
+            .unwrap_or(false)
+    }
+}
+
+/// A system that applies queued transitions of the state `S`.
+///
+/// The first time it runs, the `OnEnter` schedule of the initial state is run.
+pub(crate) struct ApplyStateTransition<S: States> {
+    entered: bool,
+    _marker: PhantomData<S>,
+}
+
+impl<S: States> ApplyStateTransition<S> {
+    /// Creates a new system that applies transitions of the state `S`.
+    pub(crate) fn new() -> Self {
+        Self {
+            entered: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S: States> System for ApplyStateTransition<S> {
+    type In = ();
+    type Out = EcsResult<()>;
+
+    fn run(&mut self, _input: (), ctx: Context) -> EcsResult<()> {
+        if !self.entered {
+            self.entered = true;
+            let current = ctx.state::<S>()?.get().clone();
+            ctx.run_schedule_if_exists(&ScheduleId::of(&OnEnter(current)))?;
+        }
+
+        let Some(next) = ctx.take_next_state::<S>()? else {
+            return Ok(());
+        };
+        let current = ctx.state::<S>()?.get().clone();
+        if next == current {
+            return Ok(());
+        }
+
+        ctx.run_schedule_if_exists(&ScheduleId::of(&OnExit(current)))?;
+        ctx.replace_state(next.clone())?;
+        ctx.run_schedule_if_exists(&ScheduleId::of(&OnEnter(next)))
+    }
+}
@@ -4,12 +4,12 @@ use super::{archetype_table::ArchetypeTable, ArchetypeHash};
 
 /// A map of archetype hashes to their corresponding tables.
 #[derive(Debug)]
-pub(crate) struct ArchetypeMap(Box<HashMap<ArchetypeHash, Box<ArchetypeTable>>>);
+pub(crate) struct ArchetypeMap(HashMap<ArchetypeHash, Box<ArchetypeTable>>);
 
 impl ArchetypeMap {
     /// Creates new archetype map.
     pub(crate) fn new() -> Self {
-        Self(Box::new(HashMap::new()))
+        Self(HashMap::new())
     }
 
     /// Adds an archetype table to the map.
@@ -23,12 +23,32 @@ impl ArchetypeMap {
     }
 
     /// Gets a mutable reference to the archetype table with the specified hash.
-    pub(crate) fn get_archetype_table_mut<'a>(
-        &self,
+    pub(crate) fn get_archetype_table_mut(
+        &mut self,
         hash: ArchetypeHash,
-    ) -> Option<&'a mut ArchetypeTable> {
-        let this = unsafe { (self as *const ArchetypeMap).cast_mut().as_mut()? };
-        this.0.get_mut(&hash).map(|a| &mut **a)
+    ) -> Option<&mut ArchetypeTable> {
+        self.0.get_mut(&hash).map(|a| &mut **a)
+    }
+
+    /// Gets mutable references to two different archetype tables (e.g. to move an entity from
+    /// one to the other).
+    ///
+    /// ## Panics
+    /// This will panic if the hashes are the same.
+    pub(crate) fn get_two_archetype_tables_mut(
+        &mut self,
+        first: ArchetypeHash,
+        second: ArchetypeHash,
+    ) -> Option<(&mut ArchetypeTable, &mut ArchetypeTable)> {
+        match self.0.get_disjoint_mut([&first, &second]) {
+            [Some(first), Some(second)] => Some((&mut **first, &mut **second)),
+            _ => None,
+        }
+    }
+
+    /// Returns mutable references to all archetype tables.
+    pub(crate) fn tables_mut(&mut self) -> impl Iterator<Item = &mut ArchetypeTable> {
+        self.0.values_mut().map(|table| &mut **table)
     }
 
     /// Checks if an archetype table with the specified hash exists in the archetype map.
@@ -1,22 +1,24 @@
 use std::{collections::HashMap, hash::Hash};
 
-use crate::{Component, ComponentId, EcsResult};
+use crate::{
+    change_detection::{ComponentTicks, Tick},
+    hooks::ComponentHooks,
+    Component, ComponentId, EcsResult, EntityId,
+};
 
 use super::{
     erased_component_table::ErasedComponentTable, ArchetypeHash, StorageError,
     DEFAULT_ARCHETYPE_HASH,
 };
 
-/// Possible errors caused by storage types.
-
 /// A table that stores components for an archetype.
 #[derive(Debug)]
 pub(crate) struct ArchetypeTable {
     /// Hash of the archetype.
     hash: ArchetypeHash,
 
-    /// Number of entities with this archetype.
-    num_entities: usize,
+    /// The entities with this archetype, in the order of their rows.
+    entities: Vec<EntityId>,
 
     /// Map of component types to their corresponding (component) tables.
     ///
@@ -30,14 +32,14 @@ impl ArchetypeTable {
     pub(crate) fn new(hash: ArchetypeHash) -> Self {
         Self {
             hash,
-            num_entities: 0,
+            entities: vec![],
             component_tables: HashMap::new(),
         }
     }
 
     /// Returns the number of entities with this archetype.
     pub(crate) fn num_entities(&self) -> usize {
-        self.num_entities
+        self.entities.len()
     }
 
     /// Returns the hash of the archetype.
@@ -45,11 +47,11 @@ impl ArchetypeTable {
         self.hash
     }
 
-    /// Adds an entity to the archetype table.
+    /// Adds an entity to the archetype table (in the last row).
     ///
     /// The component will be set to `None`, and the caller is responsible for updating the actual
     /// value of the component for an entity using `set_component_value`.
-    pub(crate) fn add_entity(&mut self) -> EcsResult<()> {
+    pub(crate) fn add_entity(&mut self, entity: EntityId) -> EcsResult<()> {
         // Don't actually add anything to the default archetype table
         if self.hash != DEFAULT_ARCHETYPE_HASH {
             for component_table in self.component_tables.values_mut() {
@@ -57,7 +59,7 @@ impl ArchetypeTable {
             }
         }
 
-        self.num_entities += 1;
+        self.entities.push(entity);
 
         Ok(())
     }
@@ -71,35 +73,72 @@ impl ArchetypeTable {
     /// Updates the component value for the entity represented by `row`.
     ///
     /// The existing value is replaced with the given `component` value, and the old value is
-    /// returned.
+    /// returned. The value is marked as changed at `tick` (and as added, if there was no old
+    /// value).
     pub(crate) fn update_component_value<T: Component>(
         &mut self,
         row: usize,
         component: T,
+        tick: Tick,
     ) -> EcsResult<Option<T>> {
         let component_id = ComponentId::of::<T>();
 
+        let erased_table = self
+            .component_tables
+            .get_mut(&component_id)
+            .ok_or(StorageError::InvalidComponentTable(component_id))?;
         let component_table = unsafe {
-            self.component_tables
-                .get_mut(&component_id)
-                .ok_or_else(|| StorageError::InvalidComponentTable(component_id))?
+            erased_table
                 .as_component_table::<T>()
-                .ok_or_else(|| StorageError::InvalidComponentTable(component_id))?
+                .ok_or(StorageError::InvalidComponentTable(component_id))?
         };
         let replace_value = component_table.update_component_value(row, component);
+        erased_table.set_ticks(row, tick, replace_value.is_some());
 
         Ok(replace_value)
     }
 
+    /// Gets the change ticks of the component (with the specified id) for the entity represented
+    /// by `row`.
+    pub(crate) fn get_component_ticks(
+        &self,
+        component_id: ComponentId,
+        row: usize,
+    ) -> Option<&ComponentTicks> {
+        self.component_tables.get(&component_id)?.ticks(row)
+    }
+
+    /// Marks the component (with the specified id) as changed at `tick` for the entity
+    /// represented by `row`.
+    pub(crate) fn set_component_changed(
+        &mut self,
+        component_id: ComponentId,
+        row: usize,
+        tick: Tick,
+    ) {
+        if let Some(component_table) = self.component_tables.get_mut(&component_id) {
+            component_table.set_changed(row, tick);
+        }
+    }
+
+    /// Clamps the change ticks that are too old (see `Tick::check`).
+    pub(crate) fn check_change_ticks(&mut self, now: Tick) {
+        for component_table in self.component_tables.values_mut() {
+            component_table.check_change_ticks(now);
+        }
+    }
+
     /// Moves an entity from `self` to `other` archetype table.
     ///
     /// `src_row` and `dst_row` are the positions of the entity in the `self` and `other` archetype tables.
+    /// Components that `other` has no table for are dropped, and the last entity in `self` is
+    /// moved into `src_row`. Returns the entity that was moved (if it wasn't the moved entity).
     pub(crate) fn move_entity(
         &mut self,
         other: &mut Self,
         src_row: usize,
         dst_row: usize,
-    ) -> EcsResult<()> {
+    ) -> EcsResult<Option<EntityId>> {
         // Move component from each component table to `other` (if other has the specified
         // component table)
         for (component_id, old_component_table) in &mut self.component_tables {
@@ -107,12 +146,44 @@ impl ArchetypeTable {
                 unsafe {
                     old_component_table.move_entity(other_component_table, src_row, dst_row)?;
                 }
+            } else {
+                unsafe { old_component_table.remove_entity(src_row)? };
             }
         }
 
-        self.num_entities -= 1;
+        Ok(self.swap_remove_entity(src_row))
+    }
 
-        Ok(())
+    /// Removes an entity from the archetype table, dropping all of its components.
+    ///
+    /// The last entity is moved into `row`. Returns the entity that was moved (if it wasn't the
+    /// removed entity).
+    pub(crate) fn remove_entity(&mut self, row: usize) -> EcsResult<Option<EntityId>> {
+        for component_table in self.component_tables.values_mut() {
+            unsafe { component_table.remove_entity(row)? };
+        }
+
+        Ok(self.swap_remove_entity(row))
+    }
+
+    /// Removes the entity in `row` from the entities of the table, and returns the (last) entity
+    /// that took its place.
+    fn swap_remove_entity(&mut self, row: usize) -> Option<EntityId> {
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+
+    /// Returns the lifecycle hooks of the components stored in the archetype table.
+    pub(crate) fn component_hooks(&self) -> Vec<ComponentHooks> {
+        self.component_tables
+            .values()
+            .map(|component_table| component_table.hooks())
+            .collect()
+    }
+
+    /// Returns the ids of the components stored in the archetype table.
+    pub(crate) fn component_ids(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.component_tables.keys().copied()
     }
 
     /// Adds a component table to `self`.
@@ -173,7 +244,7 @@ impl ArchetypeTable {
         let component_table = self
             .component_tables
             .get_mut(&component_id)
-            .ok_or_else(|| StorageError::InvalidComponentTable(component_id))?;
+            .ok_or(StorageError::InvalidComponentTable(component_id))?;
 
         component_table.remove_component_value(row)
     }
@@ -184,12 +255,8 @@ impl ArchetypeTable {
             .component_tables
             .get(&ComponentId::of::<T>())
             .map(|table| unsafe {
-                let table = ((&**table as *const ErasedComponentTable)
-                    as *mut ErasedComponentTable)
-                    .as_mut()
-                    .expect("The pointer to the erased component table was NULL".into());
                 table
-                    .as_component_table::<T>()
+                    .as_component_table_ref::<T>()
                     .expect("Unable to cast erased component table to concrete type")
             })
             .ok_or_else(|| StorageError::InvalidComponentTable(ComponentId::of::<T>()))?;
@@ -206,10 +273,6 @@ impl ArchetypeTable {
             .component_tables
             .get_mut(&ComponentId::of::<T>())
             .map(|table| unsafe {
-                let table = ((&**table as *const ErasedComponentTable)
-                    as *mut ErasedComponentTable)
-                    .as_mut()
-                    .expect("The pointer to the erased component table was NULL".into());
                 table
                     .as_component_table::<T>()
                     .expect("Unable to cast erased component table to concrete type")
@@ -218,6 +281,36 @@ impl ArchetypeTable {
 
         Ok(component_table.get_mut(row))
     }
+
+    /// Gets immutable references to the component value (of component type `T`) for the
+    /// specified entity and its ticks, with unbounded lifetimes (for queries).
+    ///
+    /// ## Safety
+    /// The caller must make sure the value and its ticks aren't mutated while the references are
+    /// alive.
+    pub(crate) unsafe fn get_component_unchecked<'a, T: Component>(
+        &self,
+        row: usize,
+    ) -> Option<(&'a T, &'a ComponentTicks)> {
+        self.component_tables
+            .get(&ComponentId::of::<T>())?
+            .get_unchecked(row)
+    }
+
+    /// Gets mutable references to the component value (of component type `T`) for the specified
+    /// entity and its ticks through a shared reference to the table (for queries).
+    ///
+    /// ## Safety
+    /// The caller must make sure nothing else accesses the value or its ticks while the
+    /// references are alive.
+    pub(crate) unsafe fn get_component_unchecked_mut<'a, T: Component>(
+        &self,
+        row: usize,
+    ) -> Option<(&'a mut T, &'a mut ComponentTicks)> {
+        self.component_tables
+            .get(&ComponentId::of::<T>())?
+            .get_unchecked_mut(row)
+    }
 }
 
 impl PartialEq for ArchetypeTable {
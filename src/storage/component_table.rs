@@ -1,3 +1,5 @@
+use std::cell::UnsafeCell;
+
 use crate::Component;
 
 use super::ComponentStorage;
@@ -9,9 +11,16 @@ pub(crate) struct ComponentTable<T: Component> {
     num_entities: usize,
 
     /// The actual component data for each entity.
-    components: Vec<Option<T>>,
+    ///
+    /// The values are in cells so queries can mutate the components they declared write access
+    /// to through a shared reference to the world.
+    components: Vec<UnsafeCell<Option<T>>>,
 }
 
+// SAFETY: Values are only mutated through a shared reference by queries, and the scheduler never
+// runs a system that writes a component at the same time as any other system that accesses it
+unsafe impl<T: Component> Sync for ComponentTable<T> {}
+
 impl<T: Component> ComponentTable<T> {
     pub(crate) fn new() -> Self {
         Self {
@@ -20,27 +29,28 @@ impl<T: Component> ComponentTable<T> {
         }
     }
 
-    /// Gets the components in the table.
-    pub(crate) fn get_components(&mut self) -> &mut Vec<Option<T>> {
-        &mut self.components
+    /// Sets the component value for the specified entity.
+    pub(crate) fn set(&mut self, row: usize, component: Option<T>) {
+        *self.components[row].get_mut() = component;
     }
 
     /// Adds an entity to the table.
     pub(crate) fn add_entity(&mut self) {
-        self.components.push(None);
+        self.components.push(UnsafeCell::new(None));
         self.num_entities += 1;
     }
 
     /// Updates the component value for the specified entity and returns the old value.
     pub(crate) fn update_component_value(&mut self, row: usize, component: T) -> Option<T> {
-        self.components[row].replace(component)
+        self.components[row].get_mut().replace(component)
     }
 
     /// Removes and returns the component value for an entity from the table.
+    ///
+    /// The last entity in the table is moved into `row`.
     pub(crate) fn remove_entity(&mut self, row: usize) -> Option<T> {
-        self.components.remove(row).and_then(|r| {
+        self.components.swap_remove(row).into_inner().inspect(|_r| {
             self.num_entities -= 1;
-            Some(r)
         })
     }
 
@@ -49,20 +59,39 @@ impl<T: Component> ComponentTable<T> {
     /// ## Note
     /// The `num_entities` can go out of sync since the entire entity is not removed.
     pub(crate) fn remove_component_value(&mut self, row: usize) -> Option<T> {
-        self.components[row].take().and_then(|c| {
+        self.components[row].get_mut().take().inspect(|_c| {
             self.num_entities -= 1;
-            Some(c)
         })
     }
 
     /// Gets an immutable reference to the component value for the specified entity.
     pub(crate) fn get(&self, row: usize) -> Option<&T> {
-        self.components.get(row)?.as_ref()
+        // SAFETY: Values are only mutated through a shared reference while nothing else accesses
+        // them (see `get_unchecked_mut`)
+        unsafe { self.get_unchecked(row) }
     }
 
     /// Gets a mutable reference to the component value for the specified entity.
     pub(crate) fn get_mut(&mut self, row: usize) -> Option<&mut T> {
-        self.components.get_mut(row)?.as_mut()
+        self.components.get_mut(row)?.get_mut().as_mut()
+    }
+
+    /// Gets an immutable reference to the component value for the specified entity, with an
+    /// unbounded lifetime.
+    ///
+    /// ## Safety
+    /// The caller must make sure the value isn't mutated while the reference is alive.
+    pub(crate) unsafe fn get_unchecked<'a>(&self, row: usize) -> Option<&'a T> {
+        (*self.components.get(row)?.get()).as_ref()
+    }
+
+    /// Gets a mutable reference to the component value for the specified entity through a
+    /// shared reference to the table.
+    ///
+    /// ## Safety
+    /// The caller must make sure nothing else accesses the value while the reference is alive.
+    pub(crate) unsafe fn get_unchecked_mut<'a>(&self, row: usize) -> Option<&'a mut T> {
+        (*self.components.get(row)?.get()).as_mut()
     }
 }
 
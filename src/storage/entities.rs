@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use crate::EntityId;
+
+/// Hands out entity IDs, reusing the IDs of despawned entities.
+///
+/// IDs can be reserved through a shared reference (e.g. by systems running on other threads).
+/// Reserved entities don't exist until they're flushed, after which they must be spawned by the
+/// world.
+#[derive(Debug, Default)]
+pub(crate) struct Entities {
+    /// IDs of despawned entities that can be reused.
+    free: Vec<EntityId>,
+
+    /// Number of IDs at the start of `free` that haven't been reserved.
+    ///
+    /// Reserving an ID decrements the cursor. While it's positive, the reserved ID is taken from
+    /// `free`; once it's negative, its (absolute) value is the number of new IDs that were
+    /// reserved after `len`.
+    free_cursor: AtomicIsize,
+
+    /// Number of IDs that were handed out (this is also the first ID that was never used).
+    len: usize,
+}
+
+impl Entities {
+    /// Reserves an ID, which can be used once the reserved entities are flushed.
+    pub(crate) fn reserve(&self) -> EntityId {
+        let cursor = self.free_cursor.fetch_sub(1, Ordering::Relaxed);
+        if cursor > 0 {
+            self.free[cursor as usize - 1]
+        } else {
+            self.len + cursor.unsigned_abs()
+        }
+    }
+
+    /// Allocates an ID for an entity that is spawned right away.
+    ///
+    /// The reserved entities must have been flushed.
+    pub(crate) fn alloc(&mut self) -> EntityId {
+        debug_assert!(!self.needs_flush(), "Reserved entities weren't flushed");
+
+        match self.free.pop() {
+            Some(entity) => {
+                *self.free_cursor.get_mut() = self.free.len() as isize;
+                entity
+            }
+            None => {
+                self.len += 1;
+                self.len - 1
+            }
+        }
+    }
+
+    /// Frees the ID of a despawned entity, so it can be reused.
+    ///
+    /// The reserved entities must have been flushed.
+    pub(crate) fn free(&mut self, entity: EntityId) {
+        debug_assert!(!self.needs_flush(), "Reserved entities weren't flushed");
+
+        self.free.push(entity);
+        *self.free_cursor.get_mut() = self.free.len() as isize;
+    }
+
+    /// Checks if any IDs were reserved since the last flush.
+    pub(crate) fn needs_flush(&mut self) -> bool {
+        *self.free_cursor.get_mut() != self.free.len() as isize
+    }
+
+    /// Takes the IDs that were reserved since the last flush, so their entities can be spawned.
+    pub(crate) fn flush(&mut self) -> Vec<EntityId> {
+        let cursor = *self.free_cursor.get_mut();
+
+        // The reserved free IDs are the ones after the cursor
+        let mut reserved = self.free.split_off(cursor.max(0) as usize);
+        if cursor < 0 {
+            let num_new = cursor.unsigned_abs();
+            reserved.extend(self.len..self.len + num_new);
+            self.len += num_new;
+        }
+
+        *self.free_cursor.get_mut() = self.free.len() as isize;
+        reserved
+    }
+
+    /// Returns the number of IDs that were handed out (this is also the first ID that was never
+    /// used).
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
@@ -1,7 +1,27 @@
-use crate::{Component, ComponentId, EcsResult};
+use std::cell::UnsafeCell;
+
+use crate::{
+    change_detection::{ComponentTicks, Tick},
+    hooks::ComponentHooks,
+    Component, ComponentId, EcsResult,
+};
 
 use super::{component_table::ComponentTable, ComponentStorage, StorageError};
 
+/// Function that adds an entity to a type-erased component table.
+type AddEntityFn = Box<dyn FnMut(&mut ErasedComponentTable) -> EcsResult<()> + Send + Sync>;
+
+/// Function that moves an entity between two type-erased component tables.
+type MoveEntityFn = Box<
+    dyn FnMut(&mut ErasedComponentTable, usize, &mut ErasedComponentTable, usize) -> EcsResult<()>
+        + Send
+        + Sync,
+>;
+
+/// Function that removes (and drops) an entity from a type-erased component table.
+type RemoveEntityFn =
+    Box<dyn FnMut(&mut ErasedComponentTable, usize) -> EcsResult<()> + Send + Sync>;
+
 /// A type-erased component table (`ComponentTable<T>`).
 pub(crate) struct ErasedComponentTable {
     /// Total number of entities with this component.
@@ -10,22 +30,39 @@ pub(crate) struct ErasedComponentTable {
     /// A reference to the concrete `ComponentStorage` holding the component values.
     storage: Box<dyn ComponentStorage>,
 
+    /// The change ticks of the component value in each row.
+    ///
+    /// Like the values, the ticks are in cells so queries can mark the components they write as
+    /// changed through a shared reference to the world.
+    ticks: Vec<UnsafeCell<ComponentTicks>>,
+
+    /// The lifecycle hooks of the component type.
+    hooks: ComponentHooks,
+
     /// Function to add an entity to the underlying component table.
-    add_entity: Box<dyn FnMut(&mut Self) -> EcsResult<()>>,
+    add_entity: AddEntityFn,
 
     /// Function to move an entity from `self` to `other` archetype table.
-    move_entity: Box<dyn FnMut(&mut Self, usize, &mut Self, usize) -> EcsResult<()>>,
+    move_entity: MoveEntityFn,
+
+    /// Function to remove an entity from the underlying component table.
+    remove_entity: RemoveEntityFn,
 
     /// Function to create a new erased component table of the same underlying type as `self`
     /// where the component type is unknown.
-    clone_component_type: Box<dyn Fn() -> Self>,
+    clone_component_type: Box<dyn Fn() -> Self + Send + Sync>,
 }
 
+// SAFETY: See `ComponentTable`; the ticks of a value are only accessed along with the value
+unsafe impl Sync for ErasedComponentTable {}
+
 impl ErasedComponentTable {
     pub(crate) fn new<T: Component>() -> Self {
         Self {
             num_entities: 0,
             storage: Box::new(ComponentTable::<T>::new()),
+            ticks: Vec::new(),
+            hooks: ComponentHooks::of::<T>(),
             add_entity: Box::new(|this| unsafe {
                 this.as_component_table::<T>()
                     .ok_or_else(|| StorageError::FailedConcreteCast(ComponentId::of::<T>()))?
@@ -45,11 +82,23 @@ impl ErasedComponentTable {
                     .ok_or_else(|| StorageError::FailedConcreteCast(ComponentId::of::<T>()))?;
 
                 // Remove entity entry from old table and add it to the other component table
-                other_concrete.get_components()[dst_row] = this_concrete.remove_entity(src_row);
+                other_concrete.set(dst_row, this_concrete.remove_entity(src_row));
                 this.num_entities -= 1;
 
                 Ok(())
             }),
+            remove_entity: Box::new(|this, row| unsafe {
+                let removed = this
+                    .as_component_table::<T>()
+                    .ok_or_else(|| StorageError::FailedConcreteCast(ComponentId::of::<T>()))?
+                    .remove_entity(row);
+
+                if removed.is_some() {
+                    this.num_entities -= 1;
+                }
+
+                Ok(())
+            }),
             clone_component_type: Box::new(|| ErasedComponentTable::new::<T>()),
         }
     }
@@ -63,6 +112,13 @@ impl ErasedComponentTable {
         raw_table.as_mut()
     }
 
+    /// Casts type-erased component table to an immutable typed table.
+    pub(crate) unsafe fn as_component_table_ref<T: Component>(&self) -> Option<&ComponentTable<T>> {
+        let raw_storage = (&*self.storage) as *const dyn ComponentStorage;
+        let raw_table = raw_storage as *const ComponentTable<T>;
+        raw_table.as_ref()
+    }
+
     /// Adds an entity to the underlying component table.
     pub(crate) unsafe fn add_entity(&mut self) -> EcsResult<()> {
         let this = (self as *mut Self)
@@ -71,12 +127,16 @@ impl ErasedComponentTable {
                 "Unable to get valid pointer to self".into(),
             ))?;
 
-        (this.add_entity)(self)
+        (this.add_entity)(self)?;
+        self.ticks.push(UnsafeCell::default());
+
+        Ok(())
     }
 
     /// Moves an entity from `self` to `other`.
     ///
     /// `src_row` and `dst_row` are the positions of the entity in each of the archetype tables.
+    /// The last entity in `self` is moved into `src_row`.
     pub(crate) unsafe fn move_entity(
         &mut self,
         other: &mut Self,
@@ -89,7 +149,26 @@ impl ErasedComponentTable {
                 "Unable to get valid pointer to self".into(),
             ))?;
 
-        (this.move_entity)(self, src_row, other, dst_row)
+        (this.move_entity)(self, src_row, other, dst_row)?;
+        other.ticks[dst_row] = self.ticks.swap_remove(src_row);
+
+        Ok(())
+    }
+
+    /// Removes an entity from the underlying component table, dropping its component value.
+    ///
+    /// The last entity in the table is moved into `row`.
+    pub(crate) unsafe fn remove_entity(&mut self, row: usize) -> EcsResult<()> {
+        let this = (self as *mut Self)
+            .as_mut()
+            .ok_or(StorageError::InvalidCast(
+                "Unable to get valid pointer to self".into(),
+            ))?;
+
+        (this.remove_entity)(self, row)?;
+        self.ticks.swap_remove(row);
+
+        Ok(())
     }
 
     /// Creates a new erased component table pointing to `ComponentTable<T>` where `T` is
@@ -98,6 +177,72 @@ impl ErasedComponentTable {
         (self.clone_component_type)()
     }
 
+    /// Gets the lifecycle hooks of the component type.
+    pub(crate) fn hooks(&self) -> ComponentHooks {
+        self.hooks
+    }
+
+    /// Gets the change ticks of the component value in the specified row.
+    pub(crate) fn ticks(&self, row: usize) -> Option<&ComponentTicks> {
+        // SAFETY: Ticks are only mutated through a shared reference while nothing else accesses
+        // them (see `get_unchecked_mut`)
+        Some(unsafe { &*self.ticks.get(row)?.get() })
+    }
+
+    /// Gets immutable references to the component value (of type `T`) in the specified row and
+    /// its ticks, with unbounded lifetimes.
+    ///
+    /// ## Safety
+    /// The caller must make sure the value and its ticks aren't mutated while the references are
+    /// alive.
+    pub(crate) unsafe fn get_unchecked<'a, T: Component>(
+        &self,
+        row: usize,
+    ) -> Option<(&'a T, &'a ComponentTicks)> {
+        let value = self.as_component_table_ref::<T>()?.get_unchecked(row)?;
+        Some((value, &*self.ticks.get(row)?.get()))
+    }
+
+    /// Gets mutable references to the component value (of type `T`) in the specified row and its
+    /// ticks through a shared reference to the table.
+    ///
+    /// ## Safety
+    /// The caller must make sure nothing else accesses the value or its ticks while the
+    /// references are alive.
+    pub(crate) unsafe fn get_unchecked_mut<'a, T: Component>(
+        &self,
+        row: usize,
+    ) -> Option<(&'a mut T, &'a mut ComponentTicks)> {
+        let value = self.as_component_table_ref::<T>()?.get_unchecked_mut(row)?;
+        Some((value, &mut *self.ticks.get(row)?.get()))
+    }
+
+    /// Records that the component value in the specified row was set at `tick`.
+    ///
+    /// Values that replaced an existing value keep the tick they were added at.
+    pub(crate) fn set_ticks(&mut self, row: usize, tick: Tick, replaced: bool) {
+        let ticks = self.ticks[row].get_mut();
+        if replaced {
+            ticks.changed = tick;
+        } else {
+            *ticks = ComponentTicks::new(tick);
+        }
+    }
+
+    /// Marks the component value in the specified row as changed at `tick`.
+    pub(crate) fn set_changed(&mut self, row: usize, tick: Tick) {
+        if let Some(ticks) = self.ticks.get_mut(row) {
+            ticks.get_mut().changed = tick;
+        }
+    }
+
+    /// Clamps the ticks that are too old (see `Tick::check`).
+    pub(crate) fn check_change_ticks(&mut self, now: Tick) {
+        for ticks in &mut self.ticks {
+            ticks.get_mut().check(now);
+        }
+    }
+
     /// Removes the component value for the specified entity.
     ///
     /// ## Note
@@ -110,7 +255,7 @@ impl ErasedComponentTable {
 
         let concrete_storage = unsafe {
             self.as_component_table::<T>()
-                .ok_or_else(|| StorageError::InvalidComponentTable(component_id))?
+                .ok_or(StorageError::InvalidComponentTable(component_id))?
         };
 
         let removed = concrete_storage.remove_component_value(row);
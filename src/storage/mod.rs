@@ -2,11 +2,12 @@ use crate::ComponentId;
 
 pub(crate) mod archetype_map;
 pub(crate) mod archetype_table;
+pub(crate) mod entities;
 
 mod component_table;
 mod erased_component_table;
 
-trait ComponentStorage {}
+trait ComponentStorage: Send + Sync {}
 
 /// The hash of an archetype.
 pub(crate) type ArchetypeHash = u64;
@@ -31,7 +32,7 @@ pub enum StorageError {
 }
 
 /// The location of an entity in an archetype table.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub(crate) struct StorageLocation {
     /// Hash of the archtype.
     pub(crate) hash: ArchetypeHash,
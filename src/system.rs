@@ -0,0 +1,527 @@
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    resource::{NonSend, NonSendMut, ResourceError},
+    schedule::access::Access,
+    world::World,
+    Context, EcsResult, System,
+};
+
+/// A system as it's stored in a schedule.
+pub(crate) type BoxedSystem = Box<dyn System<In = (), Out = EcsResult<()>>>;
+
+impl<F, Out> System for F
+where
+    F: FnMut(Context) -> Out + Send + 'static,
+{
+    type In = ();
+    type Out = Out;
+
+    fn run(&mut self, _input: (), ctx: Context) -> Out {
+        self(ctx)
+    }
+}
+
+/// Marker for systems that take `&mut World` instead of a `Context`.
+pub struct ExclusiveSystemMarker;
+
+/// Marker for systems made from functions that take an input or system parameters.
+pub struct FunctionSystemMarker;
+
+/// Types that can be converted into a system.
+///
+/// `Marker` tells the different kinds of systems apart, and is always inferred.
+pub trait IntoSystem<In, Out, Marker>: Sized {
+    /// The system that `self` is converted into.
+    type System: System<In = In, Out = Out>;
+
+    /// Converts `self` into a system.
+    fn into_system(self) -> Self::System;
+
+    /// Creates a system that passes the output of `self` as the input of `other`.
+    ///
+    /// `other` must take an `In<Out>` as its first parameter.
+    fn pipe<B, BOut, BMarker>(self, other: B) -> PipeSystem<Self::System, B::System>
+    where
+        B: IntoSystem<Out, BOut, BMarker>,
+    {
+        PipeSystem {
+            first: self.into_system(),
+            second: other.into_system(),
+        }
+    }
+}
+
+impl<S: System> IntoSystem<S::In, S::Out, ()> for S {
+    type System = S;
+
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+impl<F> IntoSystem<(), EcsResult<()>, ExclusiveSystemMarker> for F
+where
+    F: FnMut(&mut World) -> EcsResult<()> + Send + 'static,
+{
+    type System = ExclusiveFunctionSystem<F>;
+
+    fn into_system(self) -> Self::System {
+        ExclusiveFunctionSystem { function: self }
+    }
+}
+
+impl<F, Marker> IntoSystem<F::In, F::Out, (FunctionSystemMarker, Marker)> for F
+where
+    F: SystemParamFunction<Marker>,
+    Marker: 'static,
+{
+    type System = FunctionSystem<F, Marker>;
+
+    fn into_system(self) -> Self::System {
+        FunctionSystem {
+            function: self,
+            state: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A system that takes `&mut World` (instead of a `Context`), giving it exclusive access to the
+/// world.
+///
+/// Exclusive systems can't declare their access, so the scheduler never runs them at the same
+/// time as any other system.
+pub struct ExclusiveFunctionSystem<F> {
+    function: F,
+}
+
+impl<F> System for ExclusiveFunctionSystem<F>
+where
+    F: FnMut(&mut World) -> EcsResult<()> + Send + 'static,
+{
+    type In = ();
+    type Out = EcsResult<()>;
+
+    fn run(&mut self, _input: (), mut ctx: Context) -> EcsResult<()> {
+        let last_run = ctx.last_run();
+        let world = ctx.world_mut();
+
+        // Queries made by the system report the changes since its last run
+        let last_change_tick = world.replace_last_change_tick(last_run);
+        let result = (self.function)(world);
+        world.replace_last_change_tick(last_change_tick);
+        result
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        std::any::type_name::<F>().into()
+    }
+}
+
+/// A system that passes the output of one system as the input of another.
+pub struct PipeSystem<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> System for PipeSystem<A, B>
+where
+    A: System,
+    B: System<In = A::Out>,
+{
+    type In = A::In;
+    type Out = B::Out;
+
+    fn run(&mut self, input: Self::In, mut ctx: Context) -> Self::Out {
+        let output = self.first.run(input, ctx.reborrow());
+        self.second.run(output, ctx)
+    }
+
+    fn param_access(&self, access: &mut Access) {
+        self.first.param_access(access);
+        self.second.param_access(access);
+    }
+
+    fn validate_params(&self, ctx: &Context) -> EcsResult<()> {
+        self.first.validate_params(ctx)?;
+        self.second.validate_params(ctx)
+    }
+
+    fn is_send(&self) -> bool {
+        self.first.is_send() && self.second.is_send()
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        format!("{} | {}", self.first.name(), self.second.name()).into()
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        self.first.initialize(world);
+        self.second.initialize(world);
+    }
+
+    fn shutdown(&mut self, world: &mut World) {
+        self.first.shutdown(world);
+        self.second.shutdown(world);
+    }
+
+    fn apply_deferred(&mut self, world: &mut World) -> EcsResult<()> {
+        let first = self.first.apply_deferred(world);
+        let second = self.second.apply_deferred(world);
+        first.and(second)
+    }
+}
+
+/// The input of a system, which is passed to it when it's run (e.g. by piping).
+///
+/// Systems that take an input must take it as their first parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct In<T>(pub T);
+
+impl<T> Deref for In<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for In<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// A parameter that systems can take after their `Context`.
+///
+/// Each system keeps its own `State` for every parameter, which persists between runs.
+pub trait SystemParam: Sized {
+    /// The state the system keeps for the parameter.
+    type State: Send + 'static;
+
+    /// The parameter type with the lifetimes of a specific run.
+    type Item<'w, 's>: SystemParam<State = Self::State>;
+
+    /// Creates the state for a new system.
+    fn init_state() -> Self::State;
+
+    /// Creates the parameter for a run of the system.
+    fn get_param<'w, 's>(state: &'s mut Self::State, ctx: &Context<'w>) -> Self::Item<'w, 's>;
+
+    /// Adds the components and resources the parameter accesses to the access of a system that
+    /// declared its access.
+    fn access(_access: &mut Access) {}
+
+    /// Checks that the parameter can be created for a run of the system (e.g. that the resource
+    /// it borrows exists).
+    fn validate(_ctx: &Context) -> EcsResult<()> {
+        Ok(())
+    }
+
+    /// Whether systems that take the parameter must run on the thread that created the ECS.
+    fn main_thread_only() -> bool {
+        false
+    }
+
+    /// Applies the changes that were deferred through the parameter (e.g. by `Commands`).
+    ///
+    /// This is called at the scheduler's sync points, with exclusive access to the world.
+    fn apply(_state: &mut Self::State, _world: &mut World) -> EcsResult<()> {
+        Ok(())
+    }
+}
+
+/// The parameter type of `P` with the lifetimes of a specific run.
+pub type SystemParamItem<'w, 's, P> = <P as SystemParam>::Item<'w, 's>;
+
+/// A value that is private to a system, and persists between its runs.
+///
+/// The value starts out as `T::default()`.
+#[derive(Debug)]
+pub struct Local<'s, T: Default + Send + 'static>(&'s mut T);
+
+impl<T: Default + Send + 'static> Deref for Local<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<T: Default + Send + 'static> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl<T: Default + Send + 'static> SystemParam for Local<'_, T> {
+    type State = T;
+    type Item<'w, 's> = Local<'s, T>;
+
+    fn init_state() -> Self::State {
+        T::default()
+    }
+
+    fn get_param<'w, 's>(state: &'s mut Self::State, _ctx: &Context<'w>) -> Self::Item<'w, 's> {
+        Local(state)
+    }
+}
+
+impl<R: 'static> SystemParam for NonSend<'_, R> {
+    type State = ();
+    type Item<'w, 's> = NonSend<'w, R>;
+
+    fn init_state() -> Self::State {}
+
+    fn validate(ctx: &Context) -> EcsResult<()> {
+        validate_non_send::<R>(ctx)
+    }
+
+    fn get_param<'w, 's>(_state: &'s mut Self::State, ctx: &Context<'w>) -> Self::Item<'w, 's> {
+        ctx.non_send_resource()
+            .unwrap_or_else(|error| panic!("System `{}` failed: {}", ctx.system_name(), error))
+    }
+
+    fn main_thread_only() -> bool {
+        true
+    }
+}
+
+impl<R: 'static> SystemParam for NonSendMut<'_, R> {
+    type State = ();
+    type Item<'w, 's> = NonSendMut<'w, R>;
+
+    fn init_state() -> Self::State {}
+
+    fn validate(ctx: &Context) -> EcsResult<()> {
+        validate_non_send::<R>(ctx)
+    }
+
+    fn get_param<'w, 's>(_state: &'s mut Self::State, ctx: &Context<'w>) -> Self::Item<'w, 's> {
+        ctx.non_send_resource_mut()
+            .unwrap_or_else(|error| panic!("System `{}` failed: {}", ctx.system_name(), error))
+    }
+
+    fn main_thread_only() -> bool {
+        true
+    }
+}
+
+/// Returns an error if the world doesn't have a non-send resource of type `R`.
+fn validate_non_send<R: 'static>(ctx: &Context) -> EcsResult<()> {
+    if !ctx.contains_non_send_resource::<R>() {
+        return Err(ResourceError::ResourceNotFound(std::any::type_name::<R>()).into());
+    }
+    Ok(())
+}
+
+/// A function that can be run as a system.
+///
+/// This is implemented for functions that take an optional `In<T>`, a `Context` and up to four
+/// system parameters (in that order), where `Marker` is the signature of the function.
+pub trait SystemParamFunction<Marker>: Send + 'static {
+    /// The input of the function (`()` if it doesn't take an `In<T>`).
+    type In;
+
+    /// The output of the function.
+    type Out;
+
+    /// The system parameters of the function.
+    type Param: SystemParam;
+
+    /// Runs the function with the specified input and parameters.
+    fn run(
+        &mut self,
+        input: Self::In,
+        ctx: Context,
+        params: SystemParamItem<Self::Param>,
+    ) -> Self::Out;
+}
+
+/// A system made from a function that takes an input or system parameters.
+pub struct FunctionSystem<F: SystemParamFunction<Marker>, Marker> {
+    function: F,
+
+    /// The state of the parameters (created when the system is initialized).
+    state: Option<<F::Param as SystemParam>::State>,
+
+    _marker: PhantomData<fn() -> Marker>,
+}
+
+impl<F, Marker> System for FunctionSystem<F, Marker>
+where
+    F: SystemParamFunction<Marker>,
+    Marker: 'static,
+{
+    type In = F::In;
+    type Out = F::Out;
+
+    fn run(&mut self, input: Self::In, ctx: Context) -> Self::Out {
+        let state = self.state.get_or_insert_with(F::Param::init_state);
+        let params = F::Param::get_param(state, &ctx);
+
+        self.function.run(input, ctx, params)
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        std::any::type_name::<F>().into()
+    }
+
+    fn initialize(&mut self, _world: &mut World) {
+        self.state.get_or_insert_with(F::Param::init_state);
+    }
+
+    fn param_access(&self, access: &mut Access) {
+        F::Param::access(access);
+    }
+
+    fn validate_params(&self, ctx: &Context) -> EcsResult<()> {
+        F::Param::validate(ctx)
+    }
+
+    fn is_send(&self) -> bool {
+        !F::Param::main_thread_only()
+    }
+
+    fn apply_deferred(&mut self, world: &mut World) -> EcsResult<()> {
+        match &mut self.state {
+            Some(state) => F::Param::apply(state, world),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Implements `SystemParam` for tuples of parameters, and `SystemParamFunction` for functions
+/// that take an input followed by them.
+macro_rules! impl_system_param_function {
+    ($($param:ident),*) => {
+        #[allow(non_snake_case, unused_variables, unused_mut, clippy::unused_unit)]
+        impl<$($param: SystemParam),*> SystemParam for ($($param,)*) {
+            type State = ($($param::State,)*);
+            type Item<'w, 's> = ($($param::Item<'w, 's>,)*);
+
+            fn init_state() -> Self::State {
+                ($($param::init_state(),)*)
+            }
+
+            fn get_param<'w, 's>(
+                state: &'s mut Self::State,
+                ctx: &Context<'w>,
+            ) -> Self::Item<'w, 's> {
+                let ($($param,)*) = state;
+                ($($param::get_param($param, ctx),)*)
+            }
+
+            fn access(access: &mut Access) {
+                $($param::access(access);)*
+            }
+
+            fn validate(ctx: &Context) -> EcsResult<()> {
+                $($param::validate(ctx)?;)*
+                Ok(())
+            }
+
+            fn main_thread_only() -> bool {
+                false $(|| $param::main_thread_only())*
+            }
+
+            fn apply(state: &mut Self::State, world: &mut World) -> EcsResult<()> {
+                let ($($param,)*) = state;
+                let mut result = Ok(());
+                $(
+                    let applied = $param::apply($param, world);
+                    if result.is_ok() {
+                        result = applied;
+                    }
+                )*
+                result
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<Input, Out, Func, $($param: SystemParam),*>
+            SystemParamFunction<fn(In<Input>, Context, $($param),*) -> Out> for Func
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func: FnMut(In<Input>, Context, $($param),*) -> Out
+                + FnMut(In<Input>, Context, $(SystemParamItem<$param>),*) -> Out,
+        {
+            type In = Input;
+            type Out = Out;
+            type Param = ($($param,)*);
+
+            fn run(
+                &mut self,
+                input: Input,
+                ctx: Context,
+                params: SystemParamItem<($($param,)*)>,
+            ) -> Out {
+                // Calling through a helper picks the signature with the run's lifetimes
+                fn call<Input, Out, $($param),*>(
+                    mut function: impl FnMut(In<Input>, Context, $($param),*) -> Out,
+                    input: In<Input>,
+                    ctx: Context,
+                    $($param: $param),*
+                ) -> Out {
+                    function(input, ctx, $($param),*)
+                }
+
+                let ($($param,)*) = params;
+                call(&mut *self, In(input), ctx, $($param),*)
+            }
+        }
+    };
+}
+
+/// Implements `SystemParamFunction` for functions that take system parameters (but no input).
+macro_rules! impl_system_param_function_without_input {
+    ($($param:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<Out, Func, $($param: SystemParam),*>
+            SystemParamFunction<fn(Context, $($param),*) -> Out> for Func
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func: FnMut(Context, $($param),*) -> Out
+                + FnMut(Context, $(SystemParamItem<$param>),*) -> Out,
+        {
+            type In = ();
+            type Out = Out;
+            type Param = ($($param,)*);
+
+            fn run(
+                &mut self,
+                _input: (),
+                ctx: Context,
+                params: SystemParamItem<($($param,)*)>,
+            ) -> Out {
+                // Calling through a helper picks the signature with the run's lifetimes
+                fn call<Out, $($param),*>(
+                    mut function: impl FnMut(Context, $($param),*) -> Out,
+                    ctx: Context,
+                    $($param: $param),*
+                ) -> Out {
+                    function(ctx, $($param),*)
+                }
+
+                let ($($param,)*) = params;
+                call(&mut *self, ctx, $($param),*)
+            }
+        }
+    };
+}
+
+impl_system_param_function!();
+impl_system_param_function!(P1);
+impl_system_param_function!(P1, P2);
+impl_system_param_function!(P1, P2, P3);
+impl_system_param_function!(P1, P2, P3, P4);
+
+impl_system_param_function_without_input!(P1);
+impl_system_param_function_without_input!(P1, P2);
+impl_system_param_function_without_input!(P1, P2, P3);
+impl_system_param_function_without_input!(P1, P2, P3, P4);
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+/// The default fixed timestep (60 Hz).
+const DEFAULT_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// The default maximum number of fixed steps run in a single update.
+const DEFAULT_MAX_STEPS: u32 = 5;
+
+/// Keeps track of the ticks of the ECS and the (virtual) time that has elapsed.
+///
+/// Virtual time is the sum of the durations passed to `Ecs::update`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Time {
+    /// The number of ticks that have been started.
+    ticks: u64,
+
+    /// The time passed to the current (or last) tick.
+    delta: Duration,
+
+    /// The total time passed to all ticks so far.
+    elapsed: Duration,
+}
+
+impl Time {
+    /// Returns the index of the current (or last) tick, starting at 0.
+    pub fn tick(&self) -> u64 {
+        self.ticks.saturating_sub(1)
+    }
+
+    /// Returns the time passed to the current (or last) tick.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Returns the total time passed to all ticks so far (including the current one).
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Starts a new tick, where `delta` is the time since the last tick.
+    pub(crate) fn advance(&mut self, delta: Duration) {
+        self.ticks += 1;
+        self.delta = delta;
+        self.elapsed += delta;
+    }
+}
+
+/// Keeps track of the time used to drive the `FixedUpdate` schedule.
+///
+/// Real elapsed time is added to an accumulator every update, and the `FixedUpdate` schedule is
+/// run once for every whole timestep in the accumulator.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTime {
+    /// The duration of a single fixed step.
+    timestep: Duration,
+
+    /// Elapsed time that hasn't been consumed by fixed steps yet.
+    accumulator: Duration,
+
+    /// The maximum number of fixed steps run in a single update.
+    max_steps: u32,
+
+    /// Total number of fixed steps run.
+    steps: u64,
+}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMESTEP)
+    }
+}
+
+impl FixedTime {
+    /// Creates a new fixed time with the specified timestep.
+    ///
+    /// ## Panics
+    /// This will panic if the timestep is zero.
+    pub fn new(timestep: Duration) -> Self {
+        assert!(!timestep.is_zero(), "The fixed timestep must be non-zero");
+
+        Self {
+            timestep,
+            accumulator: Duration::ZERO,
+            max_steps: DEFAULT_MAX_STEPS,
+            steps: 0,
+        }
+    }
+
+    /// Creates a new fixed time that steps `hz` times per second.
+    ///
+    /// ## Panics
+    /// This will panic if `hz` isn't positive and finite (or is so large that the timestep
+    /// rounds to zero).
+    pub fn from_hz(hz: f64) -> Self {
+        assert!(
+            hz > 0.0 && hz.is_finite(),
+            "The fixed timestep frequency must be positive and finite"
+        );
+
+        Self::new(Duration::from_secs_f64(1.0 / hz))
+    }
+
+    /// Returns the duration of a single fixed step.
+    pub fn delta(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Returns the duration of a single fixed step in seconds.
+    pub fn delta_secs(&self) -> f32 {
+        self.timestep.as_secs_f32()
+    }
+
+    /// Returns how far the accumulator is into the next fixed step, in the range `[0, 1)`.
+    ///
+    /// This can be used to interpolate between the previous and current fixed step states when
+    /// rendering.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.timestep.as_secs_f32()
+    }
+
+    /// Returns the maximum number of fixed steps run in a single update.
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// Returns the total number of fixed steps that have been run.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Sets the maximum number of fixed steps run in a single update.
+    ///
+    /// Any whole steps left over once the limit is reached are dropped, so a slow frame can't
+    /// cause an ever-growing backlog of steps.
+    pub(crate) fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    /// Adds real elapsed time to the accumulator.
+    pub(crate) fn accumulate(&mut self, elapsed: Duration) {
+        self.accumulator += elapsed;
+    }
+
+    /// Consumes a single timestep from the accumulator.
+    ///
+    /// Returns `false` if the accumulator doesn't contain a whole timestep.
+    pub(crate) fn expend(&mut self) -> bool {
+        if self.accumulator < self.timestep {
+            return false;
+        }
+
+        self.accumulator -= self.timestep;
+        self.steps += 1;
+        true
+    }
+
+    /// Drops any whole timesteps left in the accumulator, keeping only the partial step.
+    pub(crate) fn discard_overflow(&mut self) {
+        let timestep = self.timestep.as_nanos();
+        let remainder = self.accumulator.as_nanos() % timestep;
+        self.accumulator = Duration::from_nanos(remainder as u64);
+    }
+}
@@ -1,18 +1,44 @@
 use std::{
-    cell::RefCell,
+    any::{Any, TypeId},
     collections::{hash_map::DefaultHasher, HashMap},
     hash::{Hash, Hasher},
-    rc::Rc,
+    marker::PhantomData,
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex, PoisonError,
+    },
 };
 
 use crate::{
+    change_detection::{Tick, CHECK_TICK_THRESHOLD, MAX_CHANGE_AGE},
+    command::CommandQueue,
+    context::EntityBuilder,
+    event::Event,
+    hooks::{ComponentHook, ComponentHooks, DeferredWorld},
+    observer::{self, Observer, Observers, OnAdd, OnInsert, OnRemove},
+    query::Query,
+    query_params::{QueryFilter, QueryParam},
+    removal_detection::RemovedComponentEvents,
+    resource::{NonSendResources, Resource, Resources},
+    schedule::{
+        self,
+        config::IntoSystemConfig,
+        edit::ScheduleEdit,
+        registry::{self, RegisteredSystems, SystemId},
+        ScheduleId, ScheduleLabel, Schedules,
+    },
+    state::{StateData, States},
     storage::{
-        archetype_map::ArchetypeMap, archetype_table::ArchetypeTable, ArchetypeHash,
-        StorageLocation, DEFAULT_ARCHETYPE_HASH,
+        archetype_map::ArchetypeMap, archetype_table::ArchetypeTable, entities::Entities,
+        ArchetypeHash, StorageLocation, DEFAULT_ARCHETYPE_HASH,
     },
-    Component, ComponentId, EcsResult, EntityId,
+    system::IntoSystem,
+    time::{FixedTime, Time},
+    Component, ComponentId, EcsResult, EntityId, System,
 };
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum WorldError {
     #[error("The default archetype table does not exist")]
@@ -23,9 +49,12 @@ pub enum WorldError {
 
     #[error("Archetype table with a hash of {0} not found in the archetype map")]
     InvalidArchetypeHash(ArchetypeHash),
+
+    #[error("Entity {0} does not exist (or was despawned)")]
+    EntityNotFound(EntityId),
 }
 
-pub trait EcsHasher: Hasher {
+pub trait EcsHasher: Hasher + Clone {
     fn new() -> Self;
 
     fn reset(&mut self);
@@ -46,24 +75,74 @@ type ComponentHash = ArchetypeHash;
 // TODO: Add component_id_map that maps component id to hashes of all archetypes that have that component
 // component_id_map = HashMap<ComponentId, Vec<ArchetypeHash>>
 //
-/// Contains the entities and components of the ECS.
+/// Contains the entities, components and resources of the ECS.
+///
+/// Exclusive systems (systems that take `&mut World`) can use the world directly, which is
+/// useful for operations that restructure the world in bulk (e.g. loading a level).
 #[derive(Debug)]
-pub(crate) struct World<H: EcsHasher = DefaultHasher> {
-    /// Total number of entities in the ECS.
-    num_entities: usize,
+pub struct World<H: EcsHasher = DefaultHasher> {
+    /// Hands out the IDs of entities (including reserved ones).
+    entities: Entities,
 
     /// Maps archetype hashes to their corresponding tables.
     archetype_map: ArchetypeMap,
 
-    /// Maps entities to their positions in an archetype table.
-    entity_map: Vec<StorageLocation>,
+    /// Maps entities to their positions in an archetype table (`None` if they were despawned).
+    entity_map: Vec<Option<StorageLocation>>,
 
     /// Maps components/groups of components to hashes of all archetype that have that
     /// component/subgroup.
     associated_archetype_map: HashMap<ComponentHash, Vec<ArchetypeHash>>,
 
+    /// The global resources of the world.
+    resources: Resources,
+
+    /// The resources of the world that can only be accessed on the thread that created it.
+    non_send_resources: NonSendResources,
+
     /// The hasher used to calculate archetype hashes.
-    hasher: Rc<RefCell<H>>,
+    ///
+    /// This is cloned (and reset) for every hash, so the world can be hashed from multiple threads.
+    hasher: H,
+
+    /// The schedules (and their systems) that can be run on the world.
+    schedules: Schedules,
+
+    /// The systems that can be run on demand.
+    registered_systems: RegisteredSystems,
+
+    /// The observers that run when events are triggered (e.g. when components are inserted).
+    observers: Observers,
+
+    /// The entities that had a component removed (or were despawned), per component type.
+    removed_components: RemovedComponentEvents,
+
+    /// Commands recorded by component hooks, which are applied after the structural change that
+    /// ran the hooks.
+    queued_commands: CommandQueue,
+
+    /// The tick counter used for change detection, which is incremented every time a system
+    /// runs.
+    change_tick: AtomicU32,
+
+    /// The tick that queries made outside of systems report changes since.
+    ///
+    /// Exclusive systems set this to their last run while they run.
+    last_change_tick: Tick,
+
+    /// The tick at which old ticks were last clamped (see `Tick::check`).
+    last_check_tick: Tick,
+
+    /// The ticks and virtual time of the ECS.
+    time: Time,
+
+    /// The time used to drive the `FixedUpdate` schedule.
+    fixed_time: FixedTime,
+
+    /// Maps state types to their (type-erased) `StateData`.
+    ///
+    /// States are behind a lock so they can be read and queued by systems running in parallel.
+    states: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
 }
 
 impl<H: EcsHasher> World<H> {
@@ -75,55 +154,147 @@ impl<H: EcsHasher> World<H> {
         archetype_map.add_archetype_table(DEFAULT_ARCHETYPE_HASH, default_archetype_table);
 
         Self {
-            num_entities: 0,
+            entities: Entities::default(),
             archetype_map,
             entity_map: vec![],
             associated_archetype_map: HashMap::new(),
-            hasher: Rc::new(RefCell::new(hasher)),
+            resources: Resources::default(),
+            non_send_resources: NonSendResources::default(),
+            hasher,
+            schedules: Schedules::default(),
+            registered_systems: RegisteredSystems::default(),
+            observers: Observers::default(),
+            removed_components: RemovedComponentEvents::default(),
+            queued_commands: CommandQueue::default(),
+            change_tick: AtomicU32::new(1),
+            last_change_tick: Tick::default(),
+            last_check_tick: Tick::default(),
+            time: Time::default(),
+            fixed_time: FixedTime::default(),
+            states: Mutex::new(HashMap::new()),
         }
     }
 
     /// Adds an entity to the world.
+    ///
+    /// The IDs of despawned entities are reused.
     pub(crate) fn spawn_entity(&mut self) -> EcsResult<EntityId> {
-        let entity = self.num_entities;
-        self.num_entities += 1;
+        self.flush_entities()?;
+
+        let entity = self.entities.alloc();
+        self.add_to_default_archetype(entity)?;
+        Ok(entity)
+    }
 
-        // Add the entity to the default archetype table
+    /// Reserves the ID of an entity, which is spawned (without any components) at the next
+    /// flush.
+    ///
+    /// This only needs `&self`, so IDs can be reserved from any thread (e.g. while systems are
+    /// running). The world is flushed whenever entities are spawned or despawned, when commands
+    /// are applied, and at the end of every schedule.
+    pub fn reserve_entity(&self) -> EntityId {
+        self.entities.reserve()
+    }
+
+    /// Spawns the entities that were reserved since the last flush.
+    pub fn flush_entities(&mut self) -> EcsResult<()> {
+        if !self.entities.needs_flush() {
+            return Ok(());
+        }
+
+        for entity in self.entities.flush() {
+            self.add_to_default_archetype(entity)?;
+        }
+        Ok(())
+    }
+
+    /// Adds an entity (without any components) to the default archetype.
+    fn add_to_default_archetype(&mut self, entity: EntityId) -> EcsResult<()> {
         let default_archetype_table = self
             .archetype_map
             .get_archetype_table_mut(DEFAULT_ARCHETYPE_HASH)
-            .ok_or_else(|| WorldError::InvalidDefaultArchetypeTable)?;
-        default_archetype_table.add_entity()?;
+            .ok_or(WorldError::InvalidDefaultArchetypeTable)?;
+        default_archetype_table.add_entity(entity)?;
 
-        // Add entity to entity map
-        self.entity_map.push(StorageLocation {
+        if entity >= self.entity_map.len() {
+            self.entity_map.resize(entity + 1, None);
+        }
+        self.entity_map[entity] = Some(StorageLocation {
             hash: DEFAULT_ARCHETYPE_HASH,
             row: default_archetype_table.num_entities() - 1,
         });
 
-        Ok(entity)
+        Ok(())
+    }
+
+    /// Returns the number of entity IDs that were handed out (every entity's ID is below this).
+    pub(crate) fn num_spawned_entities(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Gets the location of the specified entity in the archetype tables.
+    pub(crate) fn location(&self, entity: EntityId) -> Result<StorageLocation, WorldError> {
+        self.entity_map
+            .get(entity)
+            .copied()
+            .flatten()
+            .ok_or(WorldError::EntityNotFound(entity))
+    }
+
+    /// Updates the location of the entity that was moved into `removed` when another entity
+    /// was removed from its archetype table (see `ArchetypeTable::remove_entity`).
+    fn fill_removed_row(&mut self, removed: StorageLocation, moved: Option<EntityId>) {
+        if let Some(moved) = moved {
+            self.entity_map[moved] = Some(removed);
+        }
     }
 
     /// Gets an immutable reference to the archetype table associated with the specified entity.
     fn archetype_table_by_entity(&self, entity: EntityId) -> Option<&ArchetypeTable> {
-        let ent_archetype_hash = self.entity_map[entity].hash;
+        let ent_archetype_hash = self.location(entity).ok()?.hash;
         self.archetype_map.get_archetype_table(ent_archetype_hash)
     }
 
     /// Gets a mutable reference to the archetype table associated with the specified entity.
-    fn archetype_table_by_entity_mut(&self, entity: EntityId) -> Option<&mut ArchetypeTable> {
-        let ent_archetype_hash = self.entity_map[entity].hash;
+    fn archetype_table_by_entity_mut(&mut self, entity: EntityId) -> Option<&mut ArchetypeTable> {
+        let ent_archetype_hash = self.location(entity).ok()?.hash;
         self.archetype_map
             .get_archetype_table_mut(ent_archetype_hash)
     }
 
-    /// Adds a component to the specified entity.
-    pub(crate) fn add_component_to_entity<T: Component>(
+    /// Checks if the specified entity has a component of type `T`.
+    fn has_component<T: Component>(&self, entity: EntityId) -> EcsResult<bool> {
+        let archetype_table = self
+            .archetype_table_by_entity(entity)
+            .ok_or(WorldError::EntityNotFound(entity))?;
+
+        Ok(archetype_table.contains_component(ComponentId::of::<T>()))
+    }
+
+    /// Adds an archetype table to the world, and associates it with each of its components.
+    fn add_archetype_table(&mut self, archetype_table: ArchetypeTable) {
+        let archetype_hash = archetype_table.get_hash();
+        let component_ids = archetype_table.component_ids().collect::<Vec<_>>();
+
+        self.archetype_map
+            .add_archetype_table(archetype_hash, archetype_table);
+
+        for component_id in component_ids {
+            let component_hash = self.get_component_hash(&[component_id]);
+            self.add_associated_archetype(component_hash, archetype_hash);
+        }
+    }
+
+    /// Stores a component value for the specified entity, moving the entity to a new archetype
+    /// table if it didn't have a component of type `T`.
+    fn insert_component_value<T: Component>(
         &mut self,
         entity: EntityId,
         component: T,
     ) -> EcsResult<()> {
         let component_id = ComponentId::of::<T>();
+        let location = self.location(entity)?;
+        let tick = self.change_tick();
 
         // Calculate new hash:
         //
@@ -133,17 +304,15 @@ impl<H: EcsHasher> World<H> {
         let (old_hash, new_hash) = {
             let ent_archetype_table = self
                 .archetype_table_by_entity(entity)
-                .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
-            let ent_archetype_hash = self.entity_map[entity].hash;
+                .ok_or(WorldError::InvalidEntityArchetype(entity))?;
+            let ent_archetype_hash = location.hash;
 
             if ent_archetype_table.contains_component(component_id) {
                 (ent_archetype_hash, ent_archetype_hash)
             } else {
-                self.hasher.borrow_mut().reset();
-                component_id.hash(&mut *self.hasher.borrow_mut());
                 (
                     ent_archetype_hash,
-                    ent_archetype_hash ^ self.hasher.borrow().finish(),
+                    ent_archetype_hash ^ self.hash_component_id(component_id),
                 )
             }
         };
@@ -154,10 +323,14 @@ impl<H: EcsHasher> World<H> {
             let existing_archetype_table = self
                 .archetype_map
                 .get_archetype_table_mut(old_hash)
-                .ok_or_else(|| WorldError::InvalidArchetypeHash(old_hash))?;
+                .ok_or(WorldError::InvalidArchetypeHash(old_hash))?;
 
-            let entity_row_idx = self.entity_map[entity].row;
-            existing_archetype_table.update_component_value::<T>(entity_row_idx, component)?;
+            let entity_row_idx = location.row;
+            existing_archetype_table.update_component_value::<T>(
+                entity_row_idx,
+                component,
+                tick,
+            )?;
 
             return Ok(());
         }
@@ -166,36 +339,35 @@ impl<H: EcsHasher> World<H> {
         // the new archetype
         if self.archetype_map.table_exists(new_hash) {
             // Move entity to the new archetype table
-            let (new_archetype_table, dst_row) = {
+            let (new_archetype_table, dst_row, moved) = {
                 // Get the entity's current archetype table and the new archetype table
-                let ent_archetype_table = self
-                    .archetype_table_by_entity_mut(entity)
-                    .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
-                let new_archetype_table = self
+                let (ent_archetype_table, new_archetype_table) = self
                     .archetype_map
-                    .get_archetype_table_mut(new_hash)
-                    .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
+                    .get_two_archetype_tables_mut(old_hash, new_hash)
+                    .ok_or(WorldError::InvalidEntityArchetype(entity))?;
 
                 // Get the entity's location (row index) in each of the archetype tables
-                let src_row = self.entity_map[entity].row;
+                let src_row = location.row;
                 let dst_row = new_archetype_table.num_entities();
 
                 // Add new entity to the new_archetype_table and move all component values for the
                 // entity over from the entity's current archetype table
-                new_archetype_table.add_entity()?;
-                ent_archetype_table.move_entity(new_archetype_table, src_row, dst_row)?;
+                new_archetype_table.add_entity(entity)?;
+                let moved =
+                    ent_archetype_table.move_entity(new_archetype_table, src_row, dst_row)?;
 
-                (new_archetype_table, dst_row)
+                (new_archetype_table, dst_row, moved)
             };
 
             // Update component value and add new_archetype_table to the world
-            new_archetype_table.update_component_value(dst_row, component)?;
+            new_archetype_table.update_component_value(dst_row, component, tick)?;
 
             // Update entity map
-            self.entity_map[entity] = StorageLocation {
+            self.fill_removed_row(location, moved);
+            self.entity_map[entity] = Some(StorageLocation {
                 hash: new_hash,
                 row: dst_row,
-            };
+            });
 
             return Ok(());
         }
@@ -208,103 +380,102 @@ impl<H: EcsHasher> World<H> {
             // Create new component tables for all of the entity's existing components
             let ent_archetype_table = self
                 .archetype_table_by_entity(entity)
-                .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
+                .ok_or(WorldError::InvalidEntityArchetype(entity))?;
             new_archetype_table.new_component_tables_from(ent_archetype_table)?;
 
             // Create new component table for the new component type
             new_archetype_table.add_new_component_table::<T>();
 
             // Move entity to the new archetype table
-            {
+            let moved = {
                 // Get the entity's current archetype table
                 let ent_archetype_table = self
                     .archetype_table_by_entity_mut(entity)
-                    .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
+                    .ok_or(WorldError::InvalidEntityArchetype(entity))?;
 
                 // Get the entity's location (row index) in each of the archetype tables
-                let src_row = self.entity_map[entity].row;
+                let src_row = location.row;
                 let dst_row = 0;
 
                 // Add new entity to the new_archetype_table and move all component values for the
                 // entity over from the entity's current archetype table
-                new_archetype_table.add_entity()?;
-                ent_archetype_table.move_entity(&mut new_archetype_table, src_row, dst_row)?;
-            }
+                new_archetype_table.add_entity(entity)?;
+                ent_archetype_table.move_entity(&mut new_archetype_table, src_row, dst_row)?
+            };
 
             // Add the component to the new component table and add new archetype table to the
             // world
-            new_archetype_table.update_component_value(0, component)?;
-            self.archetype_map
-                .add_archetype_table(new_hash, new_archetype_table);
+            new_archetype_table.update_component_value(0, component, tick)?;
+            self.add_archetype_table(new_archetype_table);
 
             // Update entity map
-            self.entity_map[entity] = StorageLocation {
+            self.fill_removed_row(location, moved);
+            self.entity_map[entity] = Some(StorageLocation {
                 hash: new_hash,
                 row: 0,
-            };
-
-            // Update associated archetypes
-            let component_hash = self.get_component_hash(&[component_id]);
-            self.add_associated_archetype(component_hash, new_hash);
+            });
         }
 
         Ok(())
     }
 
-    pub(crate) fn get_component_hash(&mut self, component_ids: &[ComponentId]) -> ComponentHash {
+    /// Calculates the hash of a single component type.
+    fn hash_component_id(&self, component_id: ComponentId) -> ComponentHash {
+        let mut hasher = self.hasher.clone();
+        hasher.reset();
+        component_id.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub(crate) fn get_component_hash(&self, component_ids: &[ComponentId]) -> ComponentHash {
         let mut hash = DEFAULT_ARCHETYPE_HASH;
 
         for component_id in component_ids {
-            self.hasher.borrow_mut().reset();
-            component_id.hash(&mut *self.hasher.borrow_mut());
-            hash ^= self.hasher.borrow().finish();
+            hash ^= self.hash_component_id(*component_id);
         }
 
         hash
     }
 
-    /// Removes the component of type `T` from the specified entity.
-    pub(crate) fn remove_component_from_entity<T: Component>(
-        &mut self,
-        entity: EntityId,
-    ) -> EcsResult<Option<T>> {
+    /// Takes the component of type `T` out of the specified entity, moving the entity to a new
+    /// archetype table.
+    fn take_component_value<T: Component>(&mut self, entity: EntityId) -> EcsResult<Option<T>> {
         let component_id = ComponentId::of::<T>();
+        let location = self.location(entity)?;
 
         let ent_archetype_table = self
-            .archetype_table_by_entity_mut(entity)
-            .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
+            .archetype_table_by_entity(entity)
+            .ok_or(WorldError::InvalidEntityArchetype(entity))?;
 
         // If entity's archetype table has component table for `T`, then remove the component and
         // update the entity's archetype
         if ent_archetype_table.contains_component(component_id) {
             // XOR the entity's hash with the hash of the component type to get the new hash for the entity
-            let new_archetype_hash = {
-                self.hasher.borrow_mut().reset();
-                component_id.hash(&mut *self.hasher.borrow_mut());
-                self.entity_map[entity].hash ^ self.hasher.borrow().finish()
-            };
+            let new_archetype_hash = location.hash ^ self.hash_component_id(component_id);
 
             // If new archetype exists move entity to it
             if self.archetype_map.table_exists(new_archetype_hash) {
-                let new_archetype_table = self
+                let (ent_archetype_table, new_archetype_table) = self
                     .archetype_map
-                    .get_archetype_table_mut(new_archetype_hash)
-                    .ok_or_else(|| WorldError::InvalidArchetypeHash(new_archetype_hash))?;
+                    .get_two_archetype_tables_mut(location.hash, new_archetype_hash)
+                    .ok_or(WorldError::InvalidArchetypeHash(new_archetype_hash))?;
 
-                let src_row = self.entity_map[entity].row;
+                let src_row = location.row;
                 let dst_row = new_archetype_table.num_entities();
 
                 // Remove the component value from the entity's archetype table
                 let removed_component = ent_archetype_table.remove_component_value::<T>(src_row)?;
 
-                new_archetype_table.add_entity()?;
-                ent_archetype_table.move_entity(new_archetype_table, src_row, dst_row)?;
+                new_archetype_table.add_entity(entity)?;
+                let moved =
+                    ent_archetype_table.move_entity(new_archetype_table, src_row, dst_row)?;
 
                 // Update entity map
-                self.entity_map[entity] = StorageLocation {
+                self.fill_removed_row(location, moved);
+                self.entity_map[entity] = Some(StorageLocation {
                     hash: new_archetype_hash,
                     row: dst_row,
-                };
+                });
 
                 return Ok(removed_component);
             }
@@ -317,28 +488,29 @@ impl<H: EcsHasher> World<H> {
                 // the one being removed)
                 let ent_archetype_table = self
                     .archetype_table_by_entity_mut(entity)
-                    .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
+                    .ok_or(WorldError::InvalidEntityArchetype(entity))?;
                 new_archetype_table
                     .new_component_tables_with(ent_archetype_table, |id| *id != component_id)?;
 
-                let src_row = self.entity_map[entity].row;
+                let src_row = location.row;
                 let dst_row = 0;
 
                 // Remove the component value from the entity's archetype table
                 let removed_component = ent_archetype_table.remove_component_value::<T>(src_row)?;
 
-                new_archetype_table.add_entity()?;
-                ent_archetype_table.move_entity(&mut new_archetype_table, src_row, dst_row)?;
+                new_archetype_table.add_entity(entity)?;
+                let moved =
+                    ent_archetype_table.move_entity(&mut new_archetype_table, src_row, dst_row)?;
 
                 // Add new archetype table to the world
-                self.archetype_map
-                    .add_archetype_table(new_archetype_hash, new_archetype_table);
+                self.add_archetype_table(new_archetype_table);
 
                 // Update entity map
-                self.entity_map[entity] = StorageLocation {
+                self.fill_removed_row(location, moved);
+                self.entity_map[entity] = Some(StorageLocation {
                     hash: new_archetype_hash,
                     row: dst_row,
-                };
+                });
 
                 return Ok(removed_component);
             }
@@ -352,21 +524,84 @@ impl<H: EcsHasher> World<H> {
     pub(crate) fn get_component<T: Component>(&self, entity: EntityId) -> EcsResult<Option<&T>> {
         let archetype_table = self
             .archetype_table_by_entity(entity)
-            .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
+            .ok_or(WorldError::InvalidEntityArchetype(entity))?;
 
-        archetype_table.get_component::<T>(self.entity_map[entity].row)
+        archetype_table.get_component::<T>(self.location(entity)?.row)
     }
 
     /// Gets a mutable reference to the component value (of type `T`) for the specified entity.
+    ///
+    /// The component is marked as changed.
     pub(crate) fn get_component_mut<T: Component>(
         &mut self,
         entity: EntityId,
     ) -> EcsResult<Option<&mut T>> {
+        let tick = self.change_tick();
+        let row = self.location(entity)?.row;
         let archetype_table = self
             .archetype_table_by_entity_mut(entity)
-            .ok_or_else(|| WorldError::InvalidEntityArchetype(entity))?;
+            .ok_or(WorldError::InvalidEntityArchetype(entity))?;
+
+        archetype_table.set_component_changed(ComponentId::of::<T>(), row, tick);
+        archetype_table.get_component_mut::<T>(row)
+    }
 
-        archetype_table.get_component_mut::<T>(self.entity_map[entity].row)
+    /// Gets the logs of removed components.
+    pub(crate) fn removed_components(&self) -> &RemovedComponentEvents {
+        &self.removed_components
+    }
+
+    /// Drops the removals of components that every system reading them has read.
+    pub(crate) fn update_removed_components(&mut self) {
+        self.removed_components.update();
+    }
+
+    /// Gets the current value of the tick counter, which changes are recorded at.
+    pub(crate) fn change_tick(&self) -> Tick {
+        Tick::new(self.change_tick.load(Ordering::Relaxed))
+    }
+
+    /// Increments the tick counter, returning the tick before the increment.
+    ///
+    /// Every system run gets its own tick this way, so it can be called from any thread.
+    pub(crate) fn increment_change_tick(&self) -> Tick {
+        Tick::new(self.change_tick.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Gets the tick that queries made outside of systems report changes since.
+    pub(crate) fn last_change_tick(&self) -> Tick {
+        self.last_change_tick
+    }
+
+    /// Sets the tick that queries made outside of systems report changes since, returning the
+    /// previous one.
+    pub(crate) fn replace_last_change_tick(&mut self, tick: Tick) -> Tick {
+        std::mem::replace(&mut self.last_change_tick, tick)
+    }
+
+    /// Ends a tick of the ECS for change detection.
+    ///
+    /// Queries made outside of systems only report the changes made after this. Every
+    /// `CHECK_TICK_THRESHOLD` ticks, all ticks that are too old are clamped so they stay correct
+    /// when the tick counter wraps around.
+    pub(crate) fn end_change_detection_tick(&mut self) {
+        self.last_change_tick = self.increment_change_tick();
+
+        let now = self.change_tick();
+        if now.get().wrapping_sub(self.last_check_tick.get()) >= CHECK_TICK_THRESHOLD {
+            self.check_change_ticks(now);
+            self.last_check_tick = now;
+        }
+    }
+
+    /// Clamps all ticks (of components and systems) that are too old (see `Tick::check`).
+    fn check_change_ticks(&mut self, now: Tick) {
+        for archetype_table in self.archetype_map.tables_mut() {
+            archetype_table.check_change_ticks(now);
+        }
+        self.schedules.check_change_ticks(now);
+        self.registered_systems.check_change_ticks(now);
+        self.last_change_tick.check(now);
     }
 
     /// Gets a vector of hashes to the associated archetypes for the specified
@@ -389,28 +624,557 @@ impl<H: EcsHasher> World<H> {
         component_hash: ComponentHash,
         archetype_hash: ArchetypeHash,
     ) {
-        if let Some(associated_archetypes) = self.associated_archetype_map.get_mut(&component_hash)
-        {
-            if *associated_archetypes.last().unwrap() != archetype_hash {
-                associated_archetypes.push(archetype_hash);
-                return;
-            }
+        let associated_archetypes = self
+            .associated_archetype_map
+            .entry(component_hash)
+            .or_default();
+
+        if !associated_archetypes.contains(&archetype_hash) {
+            associated_archetypes.push(archetype_hash);
         }
+    }
+
+    /// Gets the archetype tables of all entities that have every one of the specified
+    /// components.
+    ///
+    /// The tables are sorted by their hashes, so queries visit them in a consistent order.
+    pub(crate) fn matching_archetype_tables(
+        &self,
+        component_ids: &[ComponentId],
+    ) -> Vec<&ArchetypeTable> {
+        let Some(first) = component_ids.first() else {
+            return vec![];
+        };
+
+        // Only the archetypes associated with one of the components need to be checked
+        let mut archetype_hashes = self
+            .get_associated_archetypes(self.get_component_hash(&[*first]))
+            .unwrap_or_default();
+        archetype_hashes.sort();
+
+        archetype_hashes
+            .into_iter()
+            .filter_map(|hash| self.archetype_map.get_archetype_table(hash))
+            .filter(|table| {
+                component_ids
+                    .iter()
+                    .all(|component_id| table.contains_component(*component_id))
+            })
+            .collect()
+    }
+
+    /// Gets an immutable reference to the world's schedules.
+    pub(crate) fn schedules(&self) -> &Schedules {
+        &self.schedules
+    }
+
+    /// Gets a mutable reference to the world's schedules.
+    pub(crate) fn schedules_mut(&mut self) -> &mut Schedules {
+        &mut self.schedules
+    }
+
+    /// Gets an immutable reference to the systems that can be run on demand.
+    pub(crate) fn registered_systems(&self) -> &RegisteredSystems {
+        &self.registered_systems
+    }
+
+    /// Gets a mutable reference to the systems that can be run on demand.
+    pub(crate) fn registered_systems_mut(&mut self) -> &mut RegisteredSystems {
+        &mut self.registered_systems
+    }
+
+    /// Gets an immutable reference to the world's observers.
+    pub(crate) fn observers(&self) -> &Observers {
+        &self.observers
+    }
+
+    /// Gets a mutable reference to the world's observers.
+    pub(crate) fn observers_mut(&mut self) -> &mut Observers {
+        &mut self.observers
+    }
 
-        let associated_archetypes = vec![archetype_hash];
-        self.associated_archetype_map
-            .insert(component_hash, associated_archetypes);
+    /// Gets an immutable reference to the world's resources.
+    pub(crate) fn resources(&self) -> &Resources {
+        &self.resources
     }
 
-    pub(crate) fn get_entity_archetype_hash(&self, entity: EntityId) -> ArchetypeHash {
-        self.entity_map[entity].hash
+    /// Gets an immutable reference to the world's non-send resources.
+    pub(crate) fn non_send_resources(&self) -> &NonSendResources {
+        &self.non_send_resources
     }
 
-    pub(crate) fn get_archetype_table_mut<'a>(
+    /// Gets an immutable reference to the ticks and virtual time.
+    pub(crate) fn time(&self) -> &Time {
+        &self.time
+    }
+
+    /// Gets a mutable reference to the ticks and virtual time.
+    pub(crate) fn time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    /// Gets an immutable reference to the fixed timestep state.
+    pub(crate) fn fixed_time(&self) -> &FixedTime {
+        &self.fixed_time
+    }
+
+    /// Gets a mutable reference to the fixed timestep state.
+    pub(crate) fn fixed_time_mut(&mut self) -> &mut FixedTime {
+        &mut self.fixed_time
+    }
+
+    /// Adds the state `S` to the world with the specified initial value.
+    ///
+    /// Returns `false` (and leaves the existing state untouched) if the state was already added.
+    pub(crate) fn insert_state<S: States>(&mut self, initial: S) -> bool {
+        let states = self
+            .states
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let state_id = TypeId::of::<S>();
+        if states.contains_key(&state_id) {
+            return false;
+        }
+
+        states.insert(state_id, Box::new(StateData::new(initial)));
+        true
+    }
+
+    /// Calls `f` with the current and next values of the state `S`.
+    ///
+    /// Returns `None` if the state was never added.
+    pub(crate) fn with_state_data<S: States, R>(
         &self,
-        hash: ArchetypeHash,
-    ) -> Option<&'a mut ArchetypeTable> {
-        self.archetype_map.get_archetype_table_mut(hash)
+        f: impl FnOnce(&mut StateData<S>) -> R,
+    ) -> Option<R> {
+        let mut states = self.states.lock().unwrap_or_else(PoisonError::into_inner);
+        states.get_mut(&TypeId::of::<S>())?.downcast_mut().map(f)
+    }
+}
+
+impl World {
+    /// Removes an entity (and all of its components) from the world.
+    ///
+    /// The `on_replace` and `on_remove` hooks of all of the entity's components run first. Then
+    /// `OnRemove` is queued for the components, and their removals are logged.
+    pub(crate) fn despawn_entity(&mut self, entity: EntityId) -> EcsResult<()> {
+        self.flush_entities()?;
+
+        // The hooks run while the entity still has all of its components
+        let hooks = self
+            .archetype_table_by_entity(entity)
+            .ok_or(WorldError::EntityNotFound(entity))?
+            .component_hooks();
+        let replaced = self.run_hooks(hooks.iter().map(|hooks| hooks.on_replace), entity);
+        let removed = self.run_hooks(hooks.iter().map(|hooks| hooks.on_remove), entity);
+
+        // Spawn the entities that the hooks reserved, so the ID can be freed
+        self.flush_entities()?;
+        let location = self.location(entity)?;
+
+        let archetype_table = self
+            .archetype_map
+            .get_archetype_table_mut(location.hash)
+            .ok_or(WorldError::InvalidArchetypeHash(location.hash))?;
+        let component_ids: Vec<_> = archetype_table.component_ids().collect();
+        let moved = archetype_table.remove_entity(location.row)?;
+        self.entity_map[entity] = None;
+        self.fill_removed_row(location, moved);
+
+        for &component_id in &component_ids {
+            self.removed_components.send(component_id, entity);
+        }
+
+        self.observers
+            .queue_component_event(OnRemove, entity, component_ids);
+        self.observers.entity_despawned(entity);
+        self.entities.free(entity);
+        replaced.and(removed)
+    }
+
+    /// Adds a component to the specified entity.
+    ///
+    /// The component's `on_replace` hook runs before an existing value is replaced, and its
+    /// `on_add` (if the entity didn't have a component of type `T`) and `on_insert` hooks run
+    /// after the value is stored. `OnAdd` and `OnInsert` are queued the same way.
+    pub(crate) fn add_component_to_entity<T: Component>(
+        &mut self,
+        entity: EntityId,
+        component: T,
+    ) -> EcsResult<()> {
+        let hooks = ComponentHooks::of::<T>();
+        let added = !self.has_component::<T>(entity)?;
+        let replaced = if added {
+            Ok(())
+        } else {
+            self.run_hooks([hooks.on_replace], entity)
+        };
+
+        self.insert_component_value(entity, component)?;
+
+        let component_id = ComponentId::of::<T>();
+        if added {
+            self.observers
+                .queue_component_event(OnAdd, entity, vec![component_id]);
+        }
+        self.observers
+            .queue_component_event(OnInsert, entity, vec![component_id]);
+
+        let inserted = if added {
+            self.run_hooks([hooks.on_add, hooks.on_insert], entity)
+        } else {
+            self.run_hooks([hooks.on_insert], entity)
+        };
+        replaced.and(inserted)
+    }
+
+    /// Removes the component of type `T` from the specified entity.
+    ///
+    /// If the entity had one, the component's `on_replace` and `on_remove` hooks run before it's
+    /// removed. Then `OnRemove` is queued for the component, and the removal is logged.
+    pub(crate) fn remove_component_from_entity<T: Component>(
+        &mut self,
+        entity: EntityId,
+    ) -> EcsResult<Option<T>> {
+        let hooks = ComponentHooks::of::<T>();
+        let hooked = if self.has_component::<T>(entity)? {
+            self.run_hooks([hooks.on_replace, hooks.on_remove], entity)
+        } else {
+            Ok(())
+        };
+
+        let removed = self.take_component_value::<T>(entity)?;
+        if removed.is_some() {
+            self.removed_components.send(ComponentId::of::<T>(), entity);
+            self.observers
+                .queue_component_event(OnRemove, entity, vec![ComponentId::of::<T>()]);
+        }
+
+        hooked.map(|()| removed)
+    }
+
+    /// Runs component hooks for the entity.
+    ///
+    /// Every hook runs even if one of them fails; the first error is returned.
+    fn run_hooks(
+        &mut self,
+        hooks: impl IntoIterator<Item = ComponentHook>,
+        entity: EntityId,
+    ) -> EcsResult<()> {
+        let mut result = Ok(());
+        for hook in hooks {
+            let ran = hook(DeferredWorld::new(self), entity);
+            if result.is_ok() {
+                result = ran;
+            }
+        }
+
+        result
+    }
+
+    /// Queues commands recorded by component hooks, which are applied once the current
+    /// structural change (and its observers) have finished.
+    pub(crate) fn queue_commands(&mut self, queue: &mut CommandQueue) {
+        self.queued_commands.append(queue);
+    }
+
+    /// Takes the commands that were queued by component hooks.
+    pub(crate) fn take_queued_commands(&mut self) -> CommandQueue {
+        std::mem::take(&mut self.queued_commands)
+    }
+
+    /// Creates an `EntityBuilder` which is used to spawn an entity.
+    pub fn spawn(&mut self) -> EcsResult<EntityBuilder<'_>> {
+        let entity = self.spawn_entity()?;
+
+        Ok(EntityBuilder::new(WorldCell::new(self), entity))
+    }
+
+    /// Removes an entity and all of its components from the world.
+    ///
+    /// `OnRemove` observers of the entity's components (and the commands queued by their hooks)
+    /// are run before this returns.
+    pub fn despawn(&mut self, entity: EntityId) -> EcsResult<()> {
+        // Observers still run if a component hook failed
+        let despawned = self.despawn_entity(entity);
+        let flushed = observer::flush(self);
+        despawned.and(flushed)
+    }
+
+    /// Checks if the entity exists (it was spawned and hasn't been despawned).
+    pub fn contains(&self, entity: EntityId) -> bool {
+        self.location(entity).is_ok()
+    }
+
+    /// Adds a component to the specified entity, replacing any existing component of type `T`.
+    ///
+    /// `OnAdd` and `OnInsert` observers of the component are run before this returns.
+    pub fn insert<T: Component>(&mut self, entity: EntityId, component: T) -> EcsResult<()> {
+        let inserted = self.add_component_to_entity(entity, component);
+        let flushed = observer::flush(self);
+        inserted.and(flushed)
+    }
+
+    /// Removes the component of type `T` from the specified entity and returns it.
+    ///
+    /// Returns `None` if the entity didn't have a component of type `T`. `OnRemove` observers of
+    /// the component are run before this returns.
+    pub fn remove<T: Component>(&mut self, entity: EntityId) -> EcsResult<Option<T>> {
+        let removed = self.remove_component_from_entity(entity);
+        let flushed = observer::flush(self);
+        removed.and_then(|removed| flushed.map(|()| removed))
+    }
+
+    /// Gets an immutable reference to the component of type `T` for the specified entity.
+    pub fn get<T: Component>(&self, entity: EntityId) -> EcsResult<Option<&T>> {
+        if !self.has_component::<T>(entity)? {
+            return Ok(None);
+        }
+
+        self.get_component(entity)
+    }
+
+    /// Gets a mutable reference to the component of type `T` for the specified entity.
+    pub fn get_mut<T: Component>(&mut self, entity: EntityId) -> EcsResult<Option<&mut T>> {
+        if !self.has_component::<T>(entity)? {
+            return Ok(None);
+        }
+
+        self.get_component_mut(entity)
+    }
+
+    /// Adds an observer that runs whenever the event `E` is triggered for a component of type
+    /// `C` (or for any component if `C` is `()`).
+    ///
+    /// Observers run in the order they were added.
+    pub fn add_observer<E: Event, C: 'static>(&mut self, observer: impl Observer<E, C>) {
+        self.observers.add(None, observer);
+    }
+
+    /// Adds an observer that only runs when the event `E` is triggered for the specified entity
+    /// (or propagates to it), for a component of type `C` (or for any component if `C` is `()`).
+    ///
+    /// The observer is removed when the entity is despawned.
+    pub fn add_entity_observer<E: Event, C: 'static>(
+        &mut self,
+        entity: EntityId,
+        observer: impl Observer<E, C>,
+    ) -> EcsResult<()> {
+        self.location(entity)?;
+        self.observers.add(Some(entity), observer);
+        Ok(())
+    }
+
+    /// Triggers the event for the specified entity, immediately running the global observers of
+    /// the event and the ones attached to the entity (in the order they were added).
+    ///
+    /// If the trigger propagates (see `Event::PROPAGATE`), the observers of the entity's
+    /// `Parent` run next, and so on up the chain. When called from an observer, the trigger is
+    /// handled once the current observers have finished.
+    pub fn trigger<E: Event>(&mut self, event: E, target: EntityId) -> EcsResult<()> {
+        self.location(target)?;
+        self.observers.queue_event(event, target);
+        observer::flush(self)
+    }
+
+    /// Queries all entities that have the requested components.
+    ///
+    /// Outside of systems, `Ref` and the `Added`/`Changed` filters report the changes made since
+    /// the end of the last tick of the ECS.
+    pub fn query<'a, Params: QueryParam<'a>>(&'a mut self) -> Query<'a, Params> {
+        self.query_filtered()
+    }
+
+    /// Queries all entities that have the requested components and match the filter.
+    pub fn query_filtered<'a, Params: QueryParam<'a>, F: QueryFilter>(
+        &'a mut self,
+    ) -> Query<'a, Params, F> {
+        let (last_run, this_run) = (self.last_change_tick, self.change_tick());
+        Query::new(WorldCell::new(self), last_run, this_run)
+    }
+
+    /// Inserts a resource into the world, replacing (and returning) any existing value.
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> Option<R> {
+        self.resources.insert(resource)
+    }
+
+    /// Inserts the default value of the resource `R`, unless the world already has one.
+    pub fn init_resource<R: Resource + Default>(&mut self) {
+        if !self.resources.contains::<R>() {
+            self.resources.insert(R::default());
+        }
+    }
+
+    /// Removes the resource of type `R` from the world and returns it.
+    pub fn remove_resource<R: Resource>(&mut self) -> Option<R> {
+        self.resources.remove()
+    }
+
+    /// Checks if the world has a resource of type `R`.
+    pub fn contains_resource<R: Resource>(&self) -> bool {
+        self.resources.contains::<R>()
+    }
+
+    /// Gets an immutable reference to the resource of type `R`.
+    pub fn resource<R: Resource>(&self) -> Option<&R> {
+        self.resources.get()
+    }
+
+    /// Gets a mutable reference to the resource of type `R`.
+    pub fn resource_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut()
+    }
+
+    /// Inserts a non-send resource into the world, replacing (and returning) any existing value.
+    ///
+    /// ## Panics
+    /// This (like all other accesses to non-send resources) will panic if it isn't called on the
+    /// thread that created the world.
+    pub fn insert_non_send_resource<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.non_send_resources.insert(resource)
+    }
+
+    /// Removes the non-send resource of type `R` from the world and returns it.
+    pub fn remove_non_send_resource<R: 'static>(&mut self) -> Option<R> {
+        self.non_send_resources.remove()
+    }
+
+    /// Checks if the world has a non-send resource of type `R`.
+    pub fn contains_non_send_resource<R: 'static>(&self) -> bool {
+        self.non_send_resources.contains::<R>()
+    }
+
+    /// Gets an immutable reference to the non-send resource of type `R`.
+    pub fn non_send_resource<R: 'static>(&self) -> Option<&R> {
+        self.non_send_resources.get()
+    }
+
+    /// Gets a mutable reference to the non-send resource of type `R`.
+    pub fn non_send_resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.non_send_resources.get_mut()
+    }
+
+    /// Runs the schedule with the specified label to completion.
+    pub fn run_schedule<L: ScheduleLabel>(&mut self, label: L) -> EcsResult<()> {
+        schedule::run_schedule(WorldCell::new(self), &ScheduleId::of(&label))
+    }
+
+    /// Adds a system to the schedule with the specified label.
+    ///
+    /// Since the schedule may be running, the system is added between ticks (the schedule is
+    /// created if it doesn't exist yet).
+    pub fn add_system_to<L: ScheduleLabel, M>(
+        &mut self,
+        label: L,
+        system: impl IntoSystemConfig<M>,
+    ) {
+        self.schedules.queue_edit(ScheduleEdit::AddSystem(
+            ScheduleId::of(&label),
+            Box::new(system.into_config()),
+        ));
+    }
+
+    /// Removes all systems with the specified name from the schedule with the specified label,
+    /// shutting them down.
+    ///
+    /// Since the schedule may be running, the systems are removed between ticks; if there are no
+    /// such systems, `Ecs::update` returns `ScheduleError::SystemNotFound`. Ordering constraints
+    /// that refer to the removed systems have to be removed as well.
+    pub fn remove_system<L: ScheduleLabel>(&mut self, label: L, name: &str) {
+        self.schedules.queue_edit(ScheduleEdit::RemoveSystem(
+            ScheduleId::of(&label),
+            name.into(),
+        ));
+    }
+
+    /// Enables all systems with the specified name in the schedule with the specified label.
+    ///
+    /// The change is applied between ticks (like `World::remove_system`). This also re-enables
+    /// systems that were disabled by `PanicPolicy::CatchAndDisable`.
+    pub fn enable_system<L: ScheduleLabel>(&mut self, label: L, name: &str) {
+        self.schedules.queue_edit(ScheduleEdit::SetEnabled(
+            ScheduleId::of(&label),
+            name.into(),
+            true,
+        ));
+    }
+
+    /// Disables all systems with the specified name in the schedule with the specified label, so
+    /// they are skipped until they are enabled again.
+    ///
+    /// The change is applied between ticks (like `World::remove_system`).
+    pub fn disable_system<L: ScheduleLabel>(&mut self, label: L, name: &str) {
+        self.schedules.queue_edit(ScheduleEdit::SetEnabled(
+            ScheduleId::of(&label),
+            name.into(),
+            false,
+        ));
+    }
+
+    /// Registers a system that is only run on demand (with `World::run_system` or
+    /// `Context::run_system`), and returns its id.
+    pub fn register_system<M>(
+        &mut self,
+        system: impl IntoSystem<(), EcsResult<()>, M>,
+    ) -> SystemId {
+        let mut system = system.into_system();
+        system.initialize(self);
+
+        // Everything that already exists is new to the system
+        let last_run = self.change_tick().before(MAX_CHANGE_AGE);
+        self.registered_systems.register(Box::new(system), last_run)
+    }
+
+    /// Runs the registered system with the specified id, returning its result.
+    ///
+    /// The system keeps its state (e.g. `Local` parameters) between runs.
+    pub fn run_system(&mut self, id: SystemId) -> EcsResult<()> {
+        registry::run_system(WorldCell::new(self), id)
+    }
+}
+
+/// A pointer to the world that is shared by the systems of a running schedule.
+///
+/// The scheduler makes sure that systems running at the same time only access disjoint parts of
+/// the world, which is what allows the pointer to be shared between threads.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WorldCell<'w> {
+    world: NonNull<World>,
+    _marker: PhantomData<&'w mut World>,
+}
+
+// SAFETY: The scheduler only runs systems with compatible accesses at the same time. Systems
+// that declared their access only get shared references to the world: queries only get mutable
+// references to the components they write (through the `UnsafeCell`s of the component tables),
+// and resources are borrowed through tracked cells. Components and resources are `Send + Sync`.
+unsafe impl Send for WorldCell<'_> {}
+unsafe impl Sync for WorldCell<'_> {}
+
+impl<'w> WorldCell<'w> {
+    /// Creates a new world cell with exclusive access to the world.
+    pub(crate) fn new(world: &'w mut World) -> Self {
+        Self {
+            world: NonNull::from(world),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Gets an immutable reference to the world.
+    ///
+    /// ## Safety
+    /// The caller must make sure nothing is mutating the parts of the world that are read.
+    pub(crate) unsafe fn world(self) -> &'w World {
+        self.world.as_ref()
+    }
+
+    /// Gets a mutable reference to the world.
+    ///
+    /// ## Safety
+    /// The caller must make sure nothing else is accessing the parts of the world that are
+    /// mutated.
+    pub(crate) unsafe fn world_mut(self) -> &'w mut World {
+        &mut *self.world.as_ptr()
     }
 }
 
@@ -433,10 +1197,10 @@ mod tests {
 
         let entity = world.spawn_entity()?;
         assert_eq!(entity, 0);
-        assert_eq!(world.num_entities, 1);
+        assert_eq!(world.num_spawned_entities(), 1);
         assert_eq!(world.entity_map.len(), 1);
-        assert_eq!(world.entity_map[0].hash, DEFAULT_ARCHETYPE_HASH);
-        assert_eq!(world.entity_map[0].row, 0);
+        assert_eq!(world.location(0)?.hash, DEFAULT_ARCHETYPE_HASH);
+        assert_eq!(world.location(0)?.row, 0);
 
         Ok(())
     }
@@ -461,11 +1225,11 @@ mod tests {
         world.add_component_to_entity(e3, Health(10))?;
         world.add_component_to_entity(e3, Health(40))?;
 
-        assert_eq!(world.num_entities, 4);
-        assert_eq!(world.entity_map[e0].row, 0);
-        assert_eq!(world.entity_map[e1].row, 0);
-        assert_eq!(world.entity_map[e2].row, 0);
-        assert_eq!(world.entity_map[e3].row, 1);
+        assert_eq!(world.num_spawned_entities(), 4);
+        assert_eq!(world.location(e0)?.row, 0);
+        assert_eq!(world.location(e1)?.row, 0);
+        assert_eq!(world.location(e2)?.row, 0);
+        assert_eq!(world.location(e3)?.row, 1);
 
         Ok(())
     }
@@ -478,12 +1242,12 @@ mod tests {
             let entity = world.spawn_entity()?;
             world.add_component_to_entity(entity, Health(20))?;
             world.add_component_to_entity(entity, Age(20))?;
-            let old_hash = world.entity_map[entity].hash;
+            let old_hash = world.location(entity)?.hash;
 
             let removed = world
                 .remove_component_from_entity::<Health>(entity)?
                 .unwrap();
-            let new_hash = world.entity_map[entity].hash;
+            let new_hash = world.location(entity)?.hash;
 
             assert_eq!(removed.0, 20);
             assert_ne!(old_hash, new_hash);
@@ -494,10 +1258,10 @@ mod tests {
             world.add_component_to_entity(entity, Health(30))?;
             world.add_component_to_entity(entity, Age(30))?;
             world.add_component_to_entity(entity, Name("E1"))?;
-            let old_hash = world.entity_map[entity].hash;
+            let old_hash = world.location(entity)?.hash;
 
             let removed = world.remove_component_from_entity::<Name>(entity)?.unwrap();
-            let new_hash = world.entity_map[entity].hash;
+            let new_hash = world.location(entity)?.hash;
 
             assert_eq!(removed.0, "E1");
             assert_ne!(old_hash, new_hash);
@@ -507,16 +1271,16 @@ mod tests {
             let entity = world.spawn_entity()?;
             world.add_component_to_entity(entity, Age(40))?;
             world.add_component_to_entity(entity, Name("E2"))?;
-            let old_hash = world.entity_map[entity].hash;
+            let old_hash = world.location(entity)?.hash;
 
             let removed = world.remove_component_from_entity::<Name>(entity)?.unwrap();
-            let new_hash = world.entity_map[entity].hash;
+            let new_hash = world.location(entity)?.hash;
 
             assert_eq!(removed.0, "E2");
             assert_ne!(old_hash, new_hash);
         }
 
-        assert_eq!(world.num_entities, 3);
+        assert_eq!(world.num_spawned_entities(), 3);
 
         Ok(())
     }
@@ -540,4 +1304,107 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn can_despawn_entities() -> EcsResult<()> {
+        let mut world = World::new(DefaultHasher::new());
+
+        let e0 = world.spawn_entity()?;
+        world.add_component_to_entity(e0, Health(10))?;
+        let e1 = world.spawn_entity()?;
+        world.add_component_to_entity(e1, Health(20))?;
+        let e2 = world.spawn_entity()?;
+        world.add_component_to_entity(e2, Health(30))?;
+
+        world.despawn_entity(e0)?;
+
+        // The last entity is moved into the despawned entity's row
+        assert!(world.location(e0).is_err());
+        assert_eq!(world.location(e1)?.row, 1);
+        assert_eq!(world.location(e2)?.row, 0);
+        assert_eq!(world.get_component::<Health>(e1)?.unwrap().0, 20);
+        assert_eq!(world.get_component::<Health>(e2)?.unwrap().0, 30);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reserved_entities_reuse_freed_ids() -> EcsResult<()> {
+        let mut world = World::new(DefaultHasher::new());
+
+        for _ in 0..3 {
+            world.spawn_entity()?;
+        }
+        world.despawn_entity(1)?;
+
+        assert_eq!(world.reserve_entity(), 1);
+        assert_eq!(world.reserve_entity(), 3);
+        assert!(world.location(1).is_err());
+        assert!(world.location(3).is_err());
+
+        world.flush_entities()?;
+        assert_eq!(world.location(1)?.row, 2);
+        assert_eq!(world.location(3)?.row, 3);
+        assert_eq!(world.num_spawned_entities(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn change_detection_survives_tick_wraparound() -> EcsResult<()> {
+        use crate::change_detection::Changed;
+
+        let mut world = World::new(DefaultHasher::new());
+        *world.change_tick.get_mut() = u32::MAX - 1;
+
+        let entity = world.spawn_entity()?;
+        world.add_component_to_entity(entity, Health(10))?;
+        assert_eq!(
+            world
+                .query_filtered::<&Health, Changed<Health>>()
+                .num_entities(),
+            1
+        );
+
+        // The counter wraps around, but the old change still isn't reported
+        for _ in 0..3 {
+            world.end_change_detection_tick();
+        }
+        assert_eq!(world.change_tick().get(), 1);
+        assert_eq!(
+            world
+                .query_filtered::<&Health, Changed<Health>>()
+                .num_entities(),
+            0
+        );
+
+        world.add_component_to_entity(entity, Health(20))?;
+        assert_eq!(
+            world
+                .query_filtered::<&Health, Changed<Health>>()
+                .num_entities(),
+            1
+        );
+        world.end_change_detection_tick();
+
+        // Ticks that got too old are clamped, so they never seem newer after another wrap
+        *world.change_tick.get_mut() = 2 + MAX_CHANGE_AGE + CHECK_TICK_THRESHOLD;
+        world.end_change_detection_tick();
+        let now = world.change_tick();
+        let row = world.location(entity)?.row;
+        let ticks = world
+            .archetype_table_by_entity(entity)
+            .and_then(|table| table.get_component_ticks(ComponentId::of::<Health>(), row))
+            .copied()
+            .unwrap();
+        assert_eq!(ticks.changed, now.before(MAX_CHANGE_AGE));
+        assert_eq!(
+            world
+                .query_filtered::<&Health, Changed<Health>>()
+                .num_entities(),
+            0
+        );
+
+        Ok(())
+    }
 }
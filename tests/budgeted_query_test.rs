@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug)]
+struct Visits(u32);
+impl Component for Visits {}
+
+#[derive(Debug)]
+struct Marker;
+impl Component for Marker {}
+
+fn spawn_entities(world: &mut World, count: usize) -> EcsResult<Vec<usize>> {
+    (0..count)
+        .map(|_| Ok(world.spawn()?.with(Visits(0))?.build()))
+        .collect()
+}
+
+fn visits(world: &mut World, entity: usize) -> u32 {
+    world.get::<Visits>(entity).unwrap().unwrap().0
+}
+
+#[test]
+fn passes_are_spread_across_ticks() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Startup, |world: &mut World| {
+        spawn_entities(world, 10).map(|_| ())
+    })
+    .add_system(|mut ctx: Context, mut cursor: Local<QueryCursor>| {
+        let completed =
+            ctx.query::<&mut Visits>()
+                .for_each_budgeted(&mut cursor, 4, |mut visits| visits.0 += 1);
+        assert_eq!(completed, ctx.time().tick() % 3 == 2);
+        Ok(())
+    });
+
+    for tick in 0..6 {
+        ecs.run()?;
+
+        // Every entity is visited once per pass (i.e. once every 3 ticks)
+        let total: u32 = (0..10).map(|entity| visits(ecs.world_mut(), entity)).sum();
+        assert_eq!(total, [4, 8, 10, 14, 18, 20][tick]);
+    }
+    Ok(())
+}
+
+#[test]
+fn paused_passes_handle_changes_to_the_world() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let world = ecs.world_mut();
+    let entities = spawn_entities(world, 6)?;
+    let mut cursor = QueryCursor::new();
+
+    let done = world
+        .query::<&mut Visits>()
+        .for_each_budgeted(&mut cursor, 3, |mut visits| visits.0 += 1);
+    assert!(!done);
+
+    // Move a visited and an unvisited entity to another archetype, despawn an unvisited entity
+    // and spawn a new one
+    world.insert(entities[1], Marker)?;
+    world.insert(entities[5], Marker)?;
+    world.despawn(entities[4])?;
+    let spawned = spawn_entities(world, 1)?[0];
+
+    let done = world
+        .query::<&mut Visits>()
+        .for_each_budgeted(&mut cursor, 100, |mut visits| visits.0 += 1);
+    assert!(done);
+    assert_eq!(cursor.passes(), 1);
+
+    for entity in [0, 1, 2, 3, 5] {
+        assert_eq!(visits(world, entities[entity]), 1, "entity {entity}");
+    }
+    assert_eq!(visits(world, spawned), 1);
+    Ok(())
+}
+
+#[test]
+fn despawning_visited_entities_does_not_skip_others() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let world = ecs.world_mut();
+    let entities = spawn_entities(world, 6)?;
+    let mut cursor = QueryCursor::new();
+    let mut calls = 0;
+
+    world
+        .query::<&mut Visits>()
+        .for_each_budgeted(&mut cursor, 3, |mut visits| {
+            visits.0 += 1;
+            calls += 1;
+        });
+
+    // The last entity of the table is moved into the row of the despawned one, which the pass
+    // already visited
+    world.despawn(entities[1])?;
+
+    let done = world
+        .query::<&mut Visits>()
+        .for_each_budgeted(&mut cursor, 100, |mut visits| {
+            visits.0 += 1;
+            calls += 1;
+        });
+    assert!(done);
+
+    for entity in [0, 2, 3, 4, 5] {
+        assert_eq!(visits(world, entities[entity]), 1, "entity {entity}");
+    }
+    // The despawned entity was only visited before it was despawned
+    assert_eq!(calls, 6);
+    Ok(())
+}
+
+#[test]
+fn time_budgets_always_make_progress() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let world = ecs.world_mut();
+    spawn_entities(world, 3)?;
+    let mut cursor = QueryCursor::new();
+
+    let mut calls = 0;
+    loop {
+        calls += 1;
+        if world.query::<&mut Visits>().for_each_budgeted(
+            &mut cursor,
+            Duration::ZERO,
+            |mut visits| visits.0 += 1,
+        ) {
+            break;
+        }
+    }
+
+    // One entity per call
+    assert_eq!(calls, 3);
+    assert_eq!(cursor.passes(), 1);
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use fonehum::*;
+
+#[derive(Debug, PartialEq)]
+struct Position(u32);
+impl Component for Position {}
+
+/// The number of entities a system reported in each run.
+#[derive(Debug, Default)]
+struct Reported(Vec<usize>);
+impl Resource for Reported {}
+
+fn report_changed(mut ctx: Context) -> EcsResult<()> {
+    let changed = ctx
+        .query_filtered::<&Position, Changed<Position>>()
+        .num_entities();
+    ctx.resource_mut::<Reported>()?.0.push(changed);
+    Ok(())
+}
+
+#[test]
+fn changed_filter_only_reports_mutations_since_the_last_run() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(|mut ctx: Context, mut runs: Local<u32>| {
+        *runs += 1;
+        if *runs == 2 {
+            for mut position in ctx.query::<&mut Position>() {
+                match position.0 {
+                    1 => position.0 = 10,
+                    2 => position.bypass_change_detection().0 = 20,
+                    // Reading through `Mut` doesn't mark the component as changed
+                    _ => assert_eq!(position.0, 3),
+                }
+            }
+        }
+        Ok(())
+    })
+    .add_system(report_changed);
+
+    let world = ecs.world_mut();
+    world.insert_resource(Reported::default());
+    for i in 1..=3 {
+        world.spawn()?.with(Position(i))?;
+    }
+
+    for _ in 0..3 {
+        ecs.run()?;
+    }
+
+    // Added components count as changed
+    assert_eq!(ecs.world_mut().resource::<Reported>().unwrap().0, [3, 1, 0]);
+    Ok(())
+}
+
+#[test]
+fn changes_made_after_a_system_ran_are_seen_by_its_next_run() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(report_changed)
+        .add_system(|mut ctx: Context, mut runs: Local<u32>| {
+            *runs += 1;
+            if *runs == 1 {
+                ctx.query::<&mut Position>().single().0 += 1;
+            }
+            Ok(())
+        });
+
+    let world = ecs.world_mut();
+    world.insert_resource(Reported::default());
+    world.spawn()?.with(Position(0))?;
+
+    for _ in 0..3 {
+        ecs.run()?;
+    }
+
+    assert_eq!(ecs.world_mut().resource::<Reported>().unwrap().0, [1, 1, 0]);
+    Ok(())
+}
+
+#[test]
+fn added_components_are_reported_once() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(|mut ctx: Context| {
+        let added = ctx
+            .query_filtered::<&Position, Added<Position>>()
+            .num_entities();
+        ctx.resource_mut::<Reported>()?.0.push(added);
+        Ok(())
+    });
+
+    let world = ecs.world_mut();
+    world.insert_resource(Reported::default());
+    let entity = world.spawn()?.with(Position(0))?.build();
+    ecs.run()?;
+    ecs.run()?;
+
+    // Replacing a component changes it, but doesn't add it again
+    let world = ecs.world_mut();
+    world.spawn()?.with(Position(1))?;
+    world.insert(entity, Position(2))?;
+    ecs.run()?;
+
+    assert_eq!(ecs.world_mut().resource::<Reported>().unwrap().0, [1, 0, 1]);
+    Ok(())
+}
+
+#[test]
+fn refs_tell_whether_components_were_added_or_changed() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(|mut ctx: Context| {
+        // Reported as `<added><changed>`
+        let position = ctx.query::<Ref<Position>>().single();
+        let flags = usize::from(position.is_added()) * 10 + usize::from(position.is_changed());
+        ctx.resource_mut::<Reported>()?.0.push(flags);
+        Ok(())
+    });
+
+    let world = ecs.world_mut();
+    world.insert_resource(Reported::default());
+    let entity = world.spawn()?.with(Position(0))?.build();
+    ecs.run()?;
+    ecs.run()?;
+
+    ecs.world_mut().get_mut::<Position>(entity)?.unwrap().0 = 1;
+    ecs.run()?;
+
+    assert_eq!(
+        ecs.world_mut().resource::<Reported>().unwrap().0,
+        [11, 0, 1]
+    );
+
+    // Outside of systems, changes are reported since the end of the last tick
+    let world = ecs.world_mut();
+    assert_eq!(
+        world
+            .query_filtered::<&Position, Changed<Position>>()
+            .num_entities(),
+        0
+    );
+    world.get_mut::<Position>(entity)?.unwrap().0 = 2;
+    assert!(world.query::<Ref<Position>>().single().is_changed());
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use fonehum::*;
+
+#[derive(Debug)]
+struct Spawner(u32);
+impl Component for Spawner {}
+
+#[derive(Debug, PartialEq)]
+struct Minion(u32);
+impl Component for Minion {}
+
+#[derive(Debug, Default)]
+struct Counter(u32);
+impl Resource for Counter {}
+
+fn spawn_minions(mut ctx: Context, mut commands: Commands) -> EcsResult<()> {
+    for spawner in ctx.query::<&Spawner>() {
+        commands.spawn().with(Minion(spawner.0));
+    }
+    Ok(())
+}
+
+#[test]
+fn systems_can_spawn_entities_while_iterating_a_query() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded)
+        .add_system(spawn_minions.reads::<Spawner>())
+        .add_system(|world: &mut World| {
+            // Exclusive systems see the changes of the systems before them
+            assert_eq!(world.query::<&Minion>().num_entities(), 2);
+            Ok(())
+        });
+
+    let world = ecs.world_mut();
+    world.spawn()?.with(Spawner(1))?;
+    world.spawn()?.with(Spawner(2))?;
+    ecs.run()?;
+
+    let world = ecs.world_mut();
+    assert_eq!(world.get::<Minion>(2)?, Some(&Minion(1)));
+    assert_eq!(world.get::<Minion>(3)?, Some(&Minion(2)));
+    Ok(())
+}
+
+#[test]
+fn reserved_entities_can_be_referenced_before_they_are_spawned() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(|_: Context, mut commands: Commands, mut ran: Local<bool>| {
+        if !*ran {
+            *ran = true;
+            let parent = commands.spawn().build();
+            commands.spawn().with(Parent(parent)).with(Minion(0));
+            commands.reserve_entity();
+        }
+        Ok(())
+    });
+
+    ecs.world_mut().spawn()?;
+    ecs.run()?;
+
+    let world = ecs.world_mut();
+    assert_eq!(world.get::<Parent>(2)?, Some(&Parent(1)));
+    assert!(world.contains(1) && world.contains(3));
+
+    // Entities spawned later get the next IDs
+    assert_eq!(world.spawn()?.build(), 4);
+    Ok(())
+}
+
+#[test]
+fn commands_are_applied_in_the_order_they_were_recorded() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(|_: Context, mut commands: Commands| {
+        commands.insert_resource(Counter(1));
+        commands.add(|world: &mut World| {
+            world.resource_mut::<Counter>().unwrap().0 *= 10;
+            Ok(())
+        });
+        commands.insert(0, Minion(1));
+        commands.remove::<Spawner>(0);
+        Ok(())
+    });
+
+    let entity = ecs.world_mut().spawn()?.with(Spawner(0))?.build();
+    ecs.run()?;
+
+    let world = ecs.world_mut();
+    assert_eq!(world.resource::<Counter>().unwrap().0, 10);
+    assert_eq!(world.get::<Minion>(entity)?, Some(&Minion(1)));
+    assert!(world.get::<Spawner>(entity)?.is_none());
+    Ok(())
+}
+
+#[test]
+fn command_errors_are_returned_after_the_remaining_commands_are_applied() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let id = ecs
+        .world_mut()
+        .register_system(|_: Context, mut commands: Commands| {
+            commands.despawn(7);
+            commands.insert_resource(Counter(3));
+            Ok(())
+        });
+
+    assert!(matches!(
+        ecs.world_mut().run_system(id),
+        Err(EcsError::WorldError(WorldError::EntityNotFound(7)))
+    ));
+    assert_eq!(ecs.world().resource::<Counter>().unwrap().0, 3);
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use fonehum::*;
+
+/// A spatial index entry is kept for every collider, with the collider's radius.
+#[derive(Debug, Default)]
+struct SpatialIndex(HashMap<usize, u32>);
+impl Resource for SpatialIndex {}
+
+#[derive(Debug, Default)]
+struct Log(Vec<String>);
+impl Resource for Log {}
+
+fn log(world: &mut DeferredWorld, entry: String) {
+    world.resource_mut::<Log>().unwrap().0.push(entry);
+}
+
+#[derive(Debug)]
+struct Collider(u32);
+
+impl Component for Collider {
+    fn on_add(mut world: DeferredWorld, entity: usize) -> EcsResult<()> {
+        log(&mut world, format!("add {entity}"));
+        Ok(())
+    }
+
+    fn on_insert(mut world: DeferredWorld, entity: usize) -> EcsResult<()> {
+        let radius = world.get::<Collider>(entity)?.unwrap().0;
+        log(&mut world, format!("insert {entity}"));
+        world
+            .resource_mut::<SpatialIndex>()
+            .unwrap()
+            .0
+            .insert(entity, radius);
+        Ok(())
+    }
+
+    fn on_replace(mut world: DeferredWorld, entity: usize) -> EcsResult<()> {
+        let radius = world.get::<Collider>(entity)?.unwrap().0;
+        log(&mut world, format!("replace {entity} ({radius})"));
+        Ok(())
+    }
+
+    fn on_remove(mut world: DeferredWorld, entity: usize) -> EcsResult<()> {
+        log(&mut world, format!("remove {entity}"));
+        world
+            .resource_mut::<SpatialIndex>()
+            .unwrap()
+            .0
+            .remove(&entity);
+        Ok(())
+    }
+}
+
+fn spatial_index(ecs: &Ecs) -> Vec<(usize, u32)> {
+    let mut entries: Vec<_> = ecs
+        .world()
+        .resource::<SpatialIndex>()
+        .unwrap()
+        .0
+        .iter()
+        .map(|(&entity, &radius)| (entity, radius))
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn hooks_run_on_every_change_to_the_component() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<SpatialIndex>().init_resource::<Log>();
+
+    let world = ecs.world_mut();
+    let e0 = world.spawn()?.with(Collider(1))?.build();
+    let e1 = world.spawn()?.with(Collider(2))?.build();
+    world.insert(e0, Collider(3))?;
+    assert_eq!(spatial_index(&ecs), [(0, 3), (1, 2)]);
+
+    let world = ecs.world_mut();
+    world.remove::<Collider>(e0)?;
+    world.despawn(e1)?;
+    assert_eq!(spatial_index(&ecs), []);
+
+    assert_eq!(
+        ecs.world().resource::<Log>().unwrap().0,
+        [
+            "add 0",
+            "insert 0",
+            "add 1",
+            "insert 1",
+            "replace 0 (1)",
+            "insert 0",
+            "replace 0 (3)",
+            "remove 0",
+            "replace 1 (2)",
+            "remove 1",
+        ]
+    );
+    Ok(())
+}
+
+/// Spawns a marker entity for every sensor, through the commands of its hook.
+#[derive(Debug)]
+struct Sensor;
+
+#[derive(Debug)]
+struct SensorMarker(usize);
+impl Component for SensorMarker {}
+
+impl Component for Sensor {
+    fn on_add(mut world: DeferredWorld, entity: usize) -> EcsResult<()> {
+        world.commands().spawn().with(SensorMarker(entity));
+        Ok(())
+    }
+}
+
+#[test]
+fn hooks_make_structural_changes_through_commands() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>().add_observer(
+        |trigger: Trigger<OnAdd, Sensor>, world: &mut World| {
+            // Observers run before the commands of the hooks are applied
+            let markers = world.query::<&SensorMarker>().num_entities();
+            world
+                .resource_mut::<Log>()
+                .unwrap()
+                .0
+                .push(format!("observed {} ({markers} markers)", trigger.entity()));
+            Ok(())
+        },
+    );
+
+    let world = ecs.world_mut();
+    let sensor = world.spawn()?.with(Sensor)?.build();
+    assert_eq!(world.query::<&SensorMarker>().single().0, sensor);
+    assert_eq!(
+        ecs.world().resource::<Log>().unwrap().0,
+        ["observed 0 (0 markers)"]
+    );
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Fragile;
+
+impl Component for Fragile {
+    fn on_remove(_world: DeferredWorld, entity: usize) -> EcsResult<()> {
+        Err(WorldError::EntityNotFound(entity).into())
+    }
+}
+
+#[test]
+fn hook_errors_are_returned_after_the_change_is_made() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>().add_observer(
+        |trigger: Trigger<OnRemove, Fragile>, world: &mut World| {
+            let entry = format!("observed {}", trigger.entity());
+            world.resource_mut::<Log>().unwrap().0.push(entry);
+            Ok(())
+        },
+    );
+
+    let world = ecs.world_mut();
+    let entity = world.spawn()?.with(Fragile)?.build();
+
+    // The entity is despawned (and observed) even though the hook failed
+    assert!(world.despawn(entity).is_err());
+    assert!(!world.contains(entity));
+    assert_eq!(ecs.world().resource::<Log>().unwrap().0, ["observed 0"]);
+    Ok(())
+}
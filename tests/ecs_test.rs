@@ -8,6 +8,7 @@ impl Component for Health {}
 struct Age(usize);
 impl Component for Age {}
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Tst(usize);
 impl Component for Tst {}
@@ -38,14 +39,14 @@ fn query_system1(mut ctx: Context) -> EcsResult<()> {
 
     let health_query3: Query<(&mut Health,)> = ctx.query();
     assert_eq!(health_query3.num_entities(), 2);
-    for health in health_query3 {
+    for mut health in health_query3 {
         assert_eq!(health.0, 30);
         health.0 = 40;
     }
 
     let health_query4: Query<&mut Health> = ctx.query();
     assert_eq!(health_query4.num_entities(), 2);
-    for health in health_query4 {
+    for mut health in health_query4 {
         assert_eq!(health.0, 40);
         health.0 = 50;
     }
@@ -59,12 +60,12 @@ fn query_system2(mut ctx: Context) -> EcsResult<()> {
     assert_eq!(health1.0, 50);
     assert_eq!(age1.0, 100);
 
-    let (health2, age2) = ctx.query::<(&mut Health, &Age)>().single();
+    let (mut health2, age2) = ctx.query::<(&mut Health, &Age)>().single();
     assert_eq!(health2.0, 50);
     health2.0 = 40;
     assert_eq!(age2.0, 100);
 
-    let (health3, age3) = ctx.query::<(&Health, &mut Age)>().single();
+    let (health3, mut age3) = ctx.query::<(&Health, &mut Age)>().single();
     assert_eq!(health3.0, 40);
     assert_eq!(age3.0, 100);
     age3.0 = 45;
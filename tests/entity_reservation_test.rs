@@ -0,0 +1,67 @@
+use std::sync::{Arc, Mutex};
+
+use fonehum::*;
+
+#[derive(Debug)]
+struct Marker;
+impl Component for Marker {}
+
+#[test]
+fn despawned_ids_are_reused() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let world = ecs.world_mut();
+    let first = world.spawn()?.build();
+    let second = world.spawn()?.build();
+    world.despawn(first)?;
+
+    let reserved = world.reserve_entity();
+    assert_eq!(reserved, first);
+    assert!(!world.contains(reserved));
+
+    // Spawning flushes the reserved entities first, so they keep their IDs
+    let spawned = world.spawn()?.with(Marker)?.build();
+    assert!(world.contains(reserved));
+    assert!(world.get::<Marker>(reserved)?.is_none());
+    assert_eq!(spawned, second + 1);
+    Ok(())
+}
+
+#[test]
+fn systems_running_in_parallel_reserve_distinct_ids() -> EcsResult<()> {
+    let reserved = Arc::new(Mutex::new(Vec::new()));
+
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded);
+    for _ in 0..4 {
+        let reserved = reserved.clone();
+        ecs.add_system(
+            (move |ctx: Context| {
+                let ids = (0..50).map(|_| ctx.reserve_entity()).collect::<Vec<_>>();
+                reserved.lock().unwrap().extend(ids);
+                Ok(())
+            })
+            .reads::<Marker>(),
+        );
+    }
+
+    // Free some IDs so the reservations reuse them
+    let world = ecs.world_mut();
+    for _ in 0..20 {
+        world.spawn()?;
+    }
+    for entity in (0..20).step_by(2) {
+        world.despawn(entity)?;
+    }
+    ecs.run()?;
+
+    let mut reserved = reserved.lock().unwrap().clone();
+    reserved.sort();
+    reserved.dedup();
+    assert_eq!(reserved.len(), 200);
+
+    // The reserved entities are spawned at the end of the schedule
+    let world = ecs.world_mut();
+    assert!(reserved.iter().all(|&entity| world.contains(entity)));
+    assert_eq!(world.spawn()?.build(), 210);
+    Ok(())
+}
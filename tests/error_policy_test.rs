@@ -0,0 +1,110 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use fonehum::*;
+
+#[derive(Debug, Default)]
+struct Ran(Vec<&'static str>);
+impl Resource for Ran {}
+
+fn fail(_: Context) -> EcsResult<()> {
+    Err(WorldError::EntityNotFound(7).into())
+}
+
+fn record(entry: &'static str) -> impl FnMut(&mut World) -> EcsResult<()> {
+    move |world: &mut World| {
+        world.resource_mut::<Ran>().unwrap().0.push(entry);
+        Ok(())
+    }
+}
+
+fn ecs_with_failing_system() -> Ecs {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Ran::default())
+        .add_system(record("before"))
+        .add_system(fail)
+        .add_system(record("after"));
+    ecs
+}
+
+fn ran(ecs: &Ecs) -> &[&'static str] {
+    &ecs.world().resource::<Ran>().unwrap().0
+}
+
+#[test]
+fn errors_abort_by_default_and_name_the_system() {
+    let mut ecs = ecs_with_failing_system();
+
+    let Err(EcsError::SystemFailed {
+        system,
+        schedule,
+        source,
+    }) = ecs.update(Duration::ZERO)
+    else {
+        panic!("Expected the system to fail");
+    };
+
+    assert!(system.ends_with("fail"));
+    assert_eq!(schedule, "Update");
+    assert!(matches!(
+        *source,
+        EcsError::WorldError(WorldError::EntityNotFound(7))
+    ));
+    assert_eq!(ran(&ecs), ["before"]);
+}
+
+#[test]
+fn logged_errors_let_the_schedule_continue() -> EcsResult<()> {
+    let mut ecs = ecs_with_failing_system();
+    ecs.with_error_policy(ErrorPolicy::Log);
+
+    ecs.update(Duration::ZERO)?;
+    ecs.update(Duration::ZERO)?;
+
+    assert_eq!(ran(&ecs), ["before", "after", "before", "after"]);
+    Ok(())
+}
+
+#[test]
+fn custom_handlers_decide_whether_to_continue() {
+    let handled = Arc::new(AtomicUsize::new(0));
+
+    let mut ecs = ecs_with_failing_system();
+    ecs.with_error_policy(ErrorPolicy::custom({
+        let handled = handled.clone();
+        move |error| {
+            // Give up after the second failure
+            if handled.fetch_add(1, Ordering::SeqCst) == 1 {
+                return Err(error);
+            }
+            Ok(())
+        }
+    }));
+
+    assert!(ecs.update(Duration::ZERO).is_ok());
+    assert!(ecs.update(Duration::ZERO).is_err());
+
+    assert_eq!(handled.load(Ordering::SeqCst), 2);
+    assert_eq!(ran(&ecs), ["before", "after", "before"]);
+}
+
+#[test]
+fn systems_can_override_the_error_policy() -> EcsResult<()> {
+    for executor in [ExecutorKind::SingleThreaded, ExecutorKind::MultiThreaded] {
+        let mut ecs = Ecs::new();
+        ecs.with_executor(executor)
+            .insert_resource(Ran::default())
+            .add_system(fail.on_error(ErrorPolicy::Log))
+            .add_system(record("after"));
+
+        ecs.update(Duration::ZERO)?;
+        assert_eq!(ran(&ecs), ["after"]);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use fonehum::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hit(u32);
+impl Event for Hit {}
+
+#[derive(Debug)]
+struct Position;
+impl Component for Position {}
+
+#[derive(Debug, Default)]
+struct Log(Vec<(&'static str, u64, u32)>);
+impl Resource for Log {}
+
+/// Sends a single hit the first time it runs.
+fn send_hit(_: Context, mut writer: EventWriter<Hit>, mut sent: Local<bool>) -> EcsResult<()> {
+    if !*sent {
+        *sent = true;
+        writer.send_batch([Hit(1), Hit(2)]);
+    }
+    Ok(())
+}
+
+fn reader(name: &'static str) -> impl FnMut(Context, EventReader<Hit>) -> EcsResult<()> + Send {
+    move |ctx, mut reader| {
+        let tick = ctx.time().tick();
+        let mut log = ctx.resource_mut::<Log>()?;
+        for hit in reader.read() {
+            log.0.push((name, tick, hit.0));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn readers_see_events_once_whether_they_run_before_or_after_the_writer() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_event::<Hit>()
+        .init_resource::<Log>()
+        .add_system(reader("early").before("writer"))
+        .add_system(send_hit.named("writer"))
+        .add_system(reader("late").after("writer"));
+
+    for _ in 0..4 {
+        ecs.run()?;
+    }
+
+    assert_eq!(
+        ecs.world().resource::<Log>().unwrap().0,
+        [
+            ("late", 0, 1),
+            ("late", 0, 2),
+            ("early", 1, 1),
+            ("early", 1, 2)
+        ]
+    );
+    assert!(ecs.world().resource::<Events<Hit>>().unwrap().is_empty());
+    Ok(())
+}
+
+#[test]
+fn each_reader_has_its_own_cursor() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    // Adding the event twice doesn't swap its buffers twice per tick
+    ecs.add_event::<Hit>()
+        .add_event::<Hit>()
+        .add_system(|_: Context, reader: EventReader<Hit>| {
+            assert_eq!(reader.len(), 3);
+            Ok(())
+        })
+        .add_system(|_: Context, mut reader: EventReader<Hit>| {
+            assert_eq!(reader.read().count(), 3);
+            assert!(reader.is_empty());
+            Ok(())
+        });
+
+    ecs.world_mut()
+        .resource_mut::<Events<Hit>>()
+        .unwrap()
+        .send_batch([Hit(1), Hit(2), Hit(3)]);
+    ecs.run()?;
+    Ok(())
+}
+
+#[test]
+fn events_can_be_drained_and_cleared() {
+    let mut events = Events::default();
+    events.send(Hit(1));
+    events.update();
+    events.send(Hit(2));
+    assert_eq!(events.len(), 2);
+
+    assert_eq!(events.drain().collect::<Vec<_>>(), [Hit(1), Hit(2)]);
+    assert!(events.is_empty());
+
+    events.send(Hit(3));
+    events.update();
+    events.update();
+    assert!(events.is_empty());
+
+    events.send(Hit(4));
+    events.clear();
+    assert!(events.is_empty());
+}
+
+#[test]
+fn event_parameters_declare_their_own_access() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_event::<Hit>()
+        .with_executor(ExecutorKind::MultiThreaded)
+        .add_system(send_hit.reads::<Position>().named("writer"))
+        .add_system(
+            (|_: Context, mut reader: EventReader<Hit>| {
+                assert_eq!(reader.read().count(), 2);
+                Ok(())
+            })
+            .reads::<Position>()
+            .after("writer"),
+        );
+
+    ecs.run()
+}
+
+#[test]
+fn missing_events_are_an_error() {
+    let mut ecs = Ecs::new();
+    ecs.add_system(send_hit);
+
+    let Err(EcsError::SystemFailed { source, .. }) = ecs.run() else {
+        panic!("the system should fail");
+    };
+    assert!(matches!(
+        *source,
+        EcsError::ResourceError(ResourceError::ResourceNotFound(name)) if name.contains("Events")
+    ));
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use fonehum::*;
+
+#[derive(Debug)]
+struct Position(i32);
+impl Component for Position {}
+
+#[derive(Debug)]
+struct Velocity(i32);
+impl Component for Velocity {}
+
+#[derive(Debug)]
+struct Log(Vec<&'static str>);
+impl Component for Log {}
+
+fn spawn_entities(mut ctx: Context) -> EcsResult<()> {
+    ctx.spawn()?.with(Position(0))?.build();
+    ctx.spawn()?.with(Velocity(1))?.build();
+    ctx.spawn()?.with(Log(Vec::new()))?.build();
+    Ok(())
+}
+
+/// Creates a system that waits (for a while) until `count` systems have started.
+fn rendezvous(
+    started: Arc<AtomicUsize>,
+    met: Arc<AtomicUsize>,
+    count: usize,
+) -> impl System<In = (), Out = EcsResult<()>> {
+    move |_: Context| {
+        started.fetch_add(1, Ordering::SeqCst);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if started.load(Ordering::SeqCst) == count {
+                met.fetch_add(1, Ordering::SeqCst);
+                break;
+            }
+            std::thread::yield_now();
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn systems_with_disjoint_access_run_in_parallel() -> EcsResult<()> {
+    // The executor only uses as many threads as there are cores
+    if std::thread::available_parallelism().map_or(1, |n| n.get()) < 2 {
+        return Ok(());
+    }
+
+    let started = Arc::new(AtomicUsize::new(0));
+    let met = Arc::new(AtomicUsize::new(0));
+
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded)
+        .add_system(rendezvous(started.clone(), met.clone(), 2).writes::<Position>())
+        .add_system(rendezvous(started.clone(), met.clone(), 2).writes::<Velocity>());
+
+    ecs.update(Duration::ZERO)?;
+
+    assert_eq!(met.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[test]
+fn conflicting_systems_run_in_order() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded)
+        .add_system_to(Startup, spawn_entities);
+
+    for entry in ["first", "second", "third"] {
+        ecs.add_system(
+            (move |mut ctx: Context| {
+                ctx.query::<&mut Log>().single().0.push(entry);
+                Ok(())
+            })
+            .writes::<Log>(),
+        );
+    }
+
+    ecs.add_system_to(Last, |mut ctx: Context| {
+        assert_eq!(
+            ctx.query::<&Log>().single().0,
+            vec!["first", "second", "third"]
+        );
+        Ok(())
+    });
+
+    ecs.update(Duration::ZERO)
+}
+
+#[test]
+fn explicit_ordering_is_respected() -> EcsResult<()> {
+    for executor in [ExecutorKind::SingleThreaded, ExecutorKind::MultiThreaded] {
+        let mut ecs = Ecs::new();
+        ecs.with_executor(executor)
+            .add_system_to(Startup, spawn_entities)
+            .add_system(
+                (|mut ctx: Context| {
+                    ctx.query::<&mut Log>().single().0.push("integrate");
+                    Ok(())
+                })
+                .named("integrate")
+                .after("accelerate")
+                .writes::<Log>(),
+            )
+            .add_system(
+                (|mut ctx: Context| {
+                    ctx.query::<&mut Log>().single().0.push("accelerate");
+                    Ok(())
+                })
+                .named("accelerate")
+                .writes::<Log>(),
+            )
+            .add_system(
+                (|mut ctx: Context| {
+                    ctx.query::<&mut Log>().single().0.push("input");
+                    Ok(())
+                })
+                .before("accelerate")
+                .writes::<Log>(),
+            )
+            .add_system_to(Last, |mut ctx: Context| {
+                assert_eq!(
+                    ctx.query::<&Log>().single().0,
+                    vec!["input", "accelerate", "integrate"]
+                );
+                Ok(())
+            });
+
+        ecs.update(Duration::ZERO)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn multi_threaded_results_match_single_threaded() -> EcsResult<()> {
+    for executor in [ExecutorKind::SingleThreaded, ExecutorKind::MultiThreaded] {
+        let mut ecs = Ecs::new();
+        ecs.with_executor(executor)
+            .add_system_to(Startup, spawn_entities)
+            .add_system(
+                (|mut ctx: Context| {
+                    ctx.query::<&mut Velocity>().single().0 += 1;
+                    Ok(())
+                })
+                .named("accelerate")
+                .writes::<Velocity>(),
+            )
+            .add_system(
+                (|mut ctx: Context| {
+                    let velocity = ctx.query::<&Velocity>().single().0;
+                    ctx.query::<&mut Position>().single().0 += velocity;
+                    Ok(())
+                })
+                .after("accelerate")
+                .reads::<Velocity>()
+                .writes::<Position>(),
+            );
+
+        for _ in 0..3 {
+            ecs.update(Duration::ZERO)?;
+        }
+
+        ecs.add_system_to(Last, |mut ctx: Context| {
+            assert_eq!(ctx.query::<&Position>().single().0, 2 + 3 + 4 + 5);
+            Ok(())
+        });
+        ecs.update(Duration::ZERO)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn dependency_cycle_is_an_error() {
+    let mut ecs = Ecs::new();
+    ecs.add_system((|_: Context| Ok(())).named("a").after("b"))
+        .add_system((|_: Context| Ok(())).named("b").after("a"));
+
+    assert!(matches!(
+        ecs.update(Duration::ZERO),
+        Err(EcsError::ScheduleError(ScheduleError::DependencyCycle(_)))
+    ));
+}
+
+#[test]
+#[should_panic(expected = "declared its access")]
+fn systems_with_declared_access_cannot_spawn() {
+    let mut ecs = Ecs::new();
+    ecs.add_system(
+        (|mut ctx: Context| {
+            ctx.spawn()?.with(Position(0))?.build();
+            Ok(())
+        })
+        .writes::<Position>(),
+    );
+
+    let _ = ecs.update(Duration::ZERO);
+}
+
+#[test]
+fn worker_threads_are_reused_between_runs() -> EcsResult<()> {
+    let threads = Arc::new(Mutex::new(HashSet::new()));
+
+    let mut ecs = Ecs::new();
+    let recorded = threads.clone();
+    ecs.with_executor(ExecutorKind::MultiThreaded).add_system(
+        (move |_: Context| {
+            recorded.lock().unwrap().insert(std::thread::current().id());
+            Ok(())
+        })
+        .reads::<Position>(),
+    );
+
+    for _ in 0..10 {
+        ecs.update(Duration::ZERO)?;
+    }
+
+    let num_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    assert!(threads.lock().unwrap().len() <= num_threads);
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug)]
+struct Steps(u32);
+impl Component for Steps {}
+
+/// The number of fixed steps and the interpolation alpha recorded at the end of each frame.
+#[derive(Debug)]
+struct Frames(Vec<(u32, f32)>);
+impl Component for Frames {}
+
+fn setup(mut ctx: Context) -> EcsResult<()> {
+    ctx.spawn()?.with(Steps(0))?.with(Frames(vec![]))?.build();
+    Ok(())
+}
+
+fn fixed_step(mut ctx: Context) -> EcsResult<()> {
+    assert_eq!(ctx.fixed_time().delta(), Duration::from_millis(10));
+    ctx.query::<&mut Steps>().single().0 += 1;
+    Ok(())
+}
+
+fn record_frame(mut ctx: Context) -> EcsResult<()> {
+    let alpha = ctx.fixed_time().alpha();
+    let (mut steps, mut frames) = ctx.query::<(&mut Steps, &mut Frames)>().single();
+    frames.0.push((steps.0, alpha));
+    steps.0 = 0;
+    Ok(())
+}
+
+fn fixed_ecs() -> Ecs {
+    let mut ecs = Ecs::new();
+    ecs.with_fixed_timestep(Duration::from_millis(10))
+        .add_system_to(Startup, setup)
+        .add_system_to(FixedUpdate, fixed_step)
+        .add_system_to(Last, record_frame);
+    ecs
+}
+
+fn assert_frames(mut ecs: Ecs, expected: &'static [(u32, f32)]) -> EcsResult<()> {
+    ecs.add_system_to(Last, move |mut ctx: Context| {
+        let frames = ctx.query::<&Frames>().single();
+        assert_eq!(frames.0.len(), expected.len() + 1);
+        for (&(steps, alpha), &(expected_steps, expected_alpha)) in frames.0.iter().zip(expected) {
+            assert_eq!(steps, expected_steps);
+            assert!(
+                (alpha - expected_alpha).abs() < 1e-4,
+                "{alpha} != {expected_alpha}"
+            );
+        }
+        Ok(())
+    })
+    .run()
+}
+
+#[test]
+fn fixed_update_runs_once_per_elapsed_timestep() -> EcsResult<()> {
+    let mut ecs = fixed_ecs();
+
+    ecs.update(Duration::from_millis(25))?;
+    ecs.update(Duration::from_millis(5))?;
+    ecs.update(Duration::from_millis(4))?;
+    ecs.update(Duration::from_millis(16))?;
+
+    assert_frames(ecs, &[(2, 0.5), (1, 0.0), (0, 0.4), (2, 0.0)])
+}
+
+#[test]
+fn fixed_update_catch_up_is_capped() -> EcsResult<()> {
+    let mut ecs = fixed_ecs();
+    ecs.with_max_fixed_steps(3);
+
+    ecs.update(Duration::from_millis(105))?;
+    ecs.update(Duration::from_millis(5))?;
+
+    assert_frames(ecs, &[(3, 0.5), (1, 0.0)])
+}
+
+#[test]
+#[should_panic(expected = "must be positive and finite")]
+fn fixed_time_rejects_an_infinite_frequency() {
+    FixedTime::from_hz(f64::INFINITY);
+}
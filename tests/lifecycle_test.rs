@@ -0,0 +1,123 @@
+use std::sync::{Arc, Mutex};
+
+use fonehum::*;
+
+#[derive(Debug, Default)]
+struct Device(bool);
+impl Resource for Device {}
+
+type Events = Arc<Mutex<Vec<String>>>;
+
+/// A system that records its lifecycle, and opens a (fake) device while it's initialized.
+struct Recorder {
+    name: &'static str,
+    events: Events,
+}
+
+impl Recorder {
+    fn new(name: &'static str, events: &Events) -> Self {
+        Self {
+            name,
+            events: events.clone(),
+        }
+    }
+
+    fn record(&self, event: &str) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", event, self.name));
+    }
+}
+
+impl System for Recorder {
+    type In = ();
+    type Out = EcsResult<()>;
+
+    fn run(&mut self, _input: (), _ctx: Context) -> EcsResult<()> {
+        self.record("run");
+        Ok(())
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        world.insert_resource(Device(true));
+        self.record("initialize");
+    }
+
+    fn shutdown(&mut self, world: &mut World) {
+        world.resource_mut::<Device>().unwrap().0 = false;
+        self.record("shutdown");
+    }
+}
+
+fn events(events: &Events) -> Vec<String> {
+    events.lock().unwrap().clone()
+}
+
+#[test]
+fn systems_are_initialized_once_and_shut_down_on_drop() -> EcsResult<()> {
+    let log = Events::default();
+    let mut ecs = Ecs::new();
+    ecs.add_system(Recorder::new("a", &log));
+
+    assert!(events(&log).is_empty());
+    ecs.run()?;
+    assert!(ecs.world().resource::<Device>().unwrap().0);
+
+    // Systems added later are initialized when the schedule is rebuilt
+    ecs.add_system(Recorder::new("b", &log).named("b"));
+    ecs.run()?;
+    drop(ecs);
+
+    assert_eq!(
+        events(&log),
+        [
+            "initialize a",
+            "run a",
+            "initialize b",
+            "run a",
+            "run b",
+            "shutdown a",
+            "shutdown b"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn systems_that_never_ran_are_not_shut_down() {
+    let log = Events::default();
+    let mut ecs = Ecs::new();
+    ecs.add_system(Recorder::new("a", &log));
+    drop(ecs);
+
+    assert!(events(&log).is_empty());
+}
+
+#[test]
+fn registered_and_piped_systems_have_lifecycles() -> EcsResult<()> {
+    let log = Events::default();
+    let mut ecs = Ecs::new();
+    let id = ecs.register_system(Recorder::new("registered", &log));
+    assert!(ecs.world().resource::<Device>().unwrap().0);
+
+    ecs.add_system(
+        Recorder::new("first", &log).pipe(|In(result): In<EcsResult<()>>, _: Context| result),
+    );
+    ecs.run_system(id)?;
+    ecs.run()?;
+    drop(ecs);
+
+    assert_eq!(
+        events(&log),
+        [
+            "initialize registered",
+            "run registered",
+            "initialize first",
+            "run first",
+            "shutdown first",
+            "shutdown registered"
+        ]
+    );
+    Ok(())
+}
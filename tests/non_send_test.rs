@@ -0,0 +1,95 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    thread::{self, ThreadId},
+};
+
+use fonehum::*;
+
+#[derive(Debug)]
+struct Position;
+impl Component for Position {}
+
+/// A resource that can't be sent to other threads (like a window handle).
+#[derive(Debug, Default)]
+struct Window {
+    handle: Rc<Cell<u32>>,
+    threads: Vec<ThreadId>,
+}
+
+fn draw(_: Context, mut window: NonSendMut<Window>) -> EcsResult<()> {
+    window.handle.set(window.handle.get() + 1);
+    window.threads.push(thread::current().id());
+    Ok(())
+}
+
+fn present(ctx: Context) -> EcsResult<()> {
+    let mut window = ctx.non_send_resource_mut::<Window>()?;
+    window.threads.push(thread::current().id());
+    Ok(())
+}
+
+#[test]
+fn non_send_systems_run_on_the_main_thread() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded)
+        .init_non_send_resource::<Window>()
+        .add_system(draw.reads::<Position>().named("draw"))
+        .add_system((|_: Context| Ok(())).reads::<Position>().named("parallel"))
+        .add_system(present.on_main_thread().reads::<Position>().after("draw"));
+
+    for _ in 0..10 {
+        ecs.run()?;
+    }
+
+    let window = ecs.world().non_send_resource::<Window>().unwrap();
+    assert_eq!(window.handle.get(), 10);
+    assert_eq!(window.threads.len(), 20);
+    assert!(window
+        .threads
+        .iter()
+        .all(|&id| id == thread::current().id()));
+    Ok(())
+}
+
+#[test]
+fn accessing_non_send_resources_from_another_thread_panics() {
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded)
+        .with_panic_policy(PanicPolicy::Catch)
+        .init_non_send_resource::<Window>()
+        // Declaring access without `on_main_thread` lets the system run on a worker thread
+        .add_system(present.reads::<Position>());
+
+    let Err(EcsError::SystemPanicked { message, .. }) = ecs.run() else {
+        panic!("the system should panic");
+    };
+    assert!(message.contains("accessed from a thread other than the one that created the ECS"));
+}
+
+#[test]
+fn missing_non_send_resources_are_an_error() {
+    let mut ecs = Ecs::new();
+    ecs.add_system(present);
+
+    let Err(EcsError::SystemFailed { source, .. }) = ecs.run() else {
+        panic!("the system should fail");
+    };
+    assert!(matches!(
+        *source,
+        EcsError::ResourceError(ResourceError::ResourceNotFound(name)) if name.ends_with("Window")
+    ));
+}
+
+#[test]
+fn missing_non_send_parameters_are_an_error() {
+    let mut ecs = Ecs::new();
+    ecs.add_system(draw.on_error(ErrorPolicy::Log))
+        .add_system(present);
+
+    // The error is handled by the system's policy instead of panicking
+    let Err(EcsError::SystemFailed { system, .. }) = ecs.run() else {
+        panic!("the second system should fail");
+    };
+    assert!(system.contains("present"));
+}
@@ -0,0 +1,150 @@
+use fonehum::*;
+
+#[derive(Debug)]
+struct Dead;
+impl Component for Dead {}
+
+#[derive(Debug)]
+struct Loot(usize);
+impl Component for Loot {}
+
+#[derive(Debug)]
+struct Health;
+impl Component for Health {}
+
+#[derive(Debug, Default)]
+struct Log(Vec<String>);
+impl Resource for Log {}
+
+fn log(world: &mut World, entry: String) {
+    world.resource_mut::<Log>().unwrap().0.push(entry);
+}
+
+fn entries(ecs: &Ecs) -> &[String] {
+    &ecs.world().resource::<Log>().unwrap().0
+}
+
+#[test]
+fn observers_run_when_components_are_inserted() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>()
+        .add_observer(|trigger: Trigger<OnInsert, Dead>, world: &mut World| {
+            log(world, format!("sound {}", trigger.entity()));
+            Ok(())
+        })
+        .add_observer(|trigger: Trigger<OnAdd, Dead>, world: &mut World| {
+            world.spawn()?.with(Loot(trigger.entity()))?;
+            Ok(())
+        })
+        .add_system(|world: &mut World| {
+            let entity = world.spawn()?.with(Health)?.build();
+            world.insert(entity, Dead)
+        });
+
+    ecs.run()?;
+    assert_eq!(entries(&ecs), ["sound 0"]);
+
+    // Replacing the component only triggers `OnInsert`
+    ecs.world_mut().insert(0, Dead)?;
+    assert_eq!(entries(&ecs), ["sound 0", "sound 0"]);
+    assert_eq!(ecs.world_mut().query::<&Loot>().single().0, 0);
+    Ok(())
+}
+
+#[test]
+fn observers_run_when_components_are_removed_or_despawned() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>()
+        .add_observer(|trigger: Trigger<OnRemove, Health>, world: &mut World| {
+            log(world, format!("health {}", trigger.entity()));
+            Ok(())
+        })
+        .add_observer(|trigger: Trigger<OnRemove>, world: &mut World| {
+            log(world, format!("any {}", trigger.entity()));
+            Ok(())
+        });
+
+    let world = ecs.world_mut();
+    let first = world.spawn()?.with(Health)?.build();
+    let second = world.spawn()?.with(Health)?.with(Dead)?.build();
+    world.remove::<Health>(first)?;
+    world.remove::<Health>(first)?;
+    world.despawn(second)?;
+
+    // The despawn only runs each observer once, even though it removed two components
+    assert_eq!(entries(&ecs), ["health 0", "any 0", "health 1", "any 1"]);
+    Ok(())
+}
+
+#[test]
+fn changes_made_by_observers_are_observed_after_the_current_trigger() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>()
+        .add_observer(|trigger: Trigger<OnInsert, Dead>, world: &mut World| {
+            log(world, "first dead".into());
+            world.insert(trigger.entity(), Loot(0))?;
+            world.remove::<Health>(trigger.entity())?;
+            Ok(())
+        })
+        .add_observer(|_: Trigger<OnInsert, Loot>, world: &mut World| {
+            log(world, "loot".into());
+            Ok(())
+        })
+        .add_observer(|_: Trigger<OnRemove, Health>, world: &mut World| {
+            log(world, "health".into());
+            Ok(())
+        })
+        .add_observer(|_: Trigger<OnInsert, Dead>, world: &mut World| {
+            log(world, "second dead".into());
+            Ok(())
+        });
+
+    let world = ecs.world_mut();
+    let entity = world.spawn()?.with(Health)?.build();
+    world.insert(entity, Dead)?;
+
+    assert_eq!(
+        entries(&ecs),
+        ["first dead", "second dead", "loot", "health"]
+    );
+    Ok(())
+}
+
+#[test]
+fn observer_errors_are_returned() {
+    let mut ecs = Ecs::new();
+    ecs.add_observer(|trigger: Trigger<OnAdd, Dead>, _: &mut World| {
+        Err(WorldError::EntityNotFound(trigger.entity()).into())
+    });
+
+    let world = ecs.world_mut();
+    let entity = world.spawn().unwrap().build();
+    assert!(matches!(
+        world.insert(entity, Dead),
+        Err(EcsError::WorldError(WorldError::EntityNotFound(0)))
+    ));
+    assert!(world.get::<Dead>(entity).unwrap().is_some());
+}
+
+#[test]
+fn entity_observers_are_removed_when_the_entity_despawns_itself() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>();
+
+    let world = ecs.world_mut();
+    let entity = world.spawn()?.build();
+    world.add_entity_observer(
+        entity,
+        |trigger: Trigger<OnAdd, Dead>, world: &mut World| {
+            log(world, format!("dead {}", trigger.entity()));
+            world.despawn(trigger.entity())
+        },
+    )?;
+    world.insert(entity, Dead)?;
+
+    // The ID is reused, but the observer went away with the despawned entity
+    let reused = world.spawn()?.with(Dead)?.build();
+    assert_eq!(reused, entity);
+    assert_eq!(entries(&ecs), ["dead 0"]);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use fonehum::*;
+
+#[derive(Debug)]
+struct Respawns(u32);
+impl Component for Respawns {}
+
+fn setup(mut ctx: Context) -> EcsResult<()> {
+    ctx.spawn()?.with(Respawns(0))?.build();
+    Ok(())
+}
+
+fn respawn(mut ctx: Context, mut runs: Local<u32>) -> EcsResult<()> {
+    *runs += 1;
+    ctx.query::<&mut Respawns>().single().0 = *runs;
+    Ok(())
+}
+
+fn respawns(ecs: &mut Ecs) -> u32 {
+    ecs.world_mut().query::<&Respawns>().single().0
+}
+
+#[test]
+fn registered_systems_only_run_on_demand_and_keep_their_state() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Startup, setup);
+    let id = ecs.register_system(respawn);
+
+    ecs.run()?;
+    ecs.run()?;
+    assert_eq!(respawns(&mut ecs), 0);
+
+    ecs.run_system(id)?;
+    ecs.run_system(id)?;
+    assert_eq!(respawns(&mut ecs), 2);
+    Ok(())
+}
+
+#[test]
+fn systems_can_run_registered_systems() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let id = ecs.register_system(respawn);
+    ecs.add_system_to(Startup, setup)
+        .add_system(move |mut ctx: Context| ctx.run_system(id))
+        .add_system(move |ctx: Context| {
+            ctx.run_system_deferred(id);
+            Ok(())
+        });
+
+    ecs.run()?;
+    assert_eq!(respawns(&mut ecs), 2);
+    ecs.run()?;
+    assert_eq!(respawns(&mut ecs), 4);
+    Ok(())
+}
+
+#[test]
+fn errors_are_returned_to_the_caller() {
+    let mut ecs = Ecs::new();
+    let id = ecs.register_system(|_: Context| Err(WorldError::EntityNotFound(3).into()));
+    let runner = ecs.register_system(move |mut ctx: Context| ctx.run_system(id));
+
+    assert!(matches!(
+        ecs.run_system(runner),
+        Err(EcsError::WorldError(WorldError::EntityNotFound(3)))
+    ));
+
+    ecs.add_system(move |ctx: Context| {
+        ctx.run_system_deferred(id);
+        Ok(())
+    });
+    assert!(matches!(
+        ecs.run(),
+        Err(EcsError::WorldError(WorldError::EntityNotFound(3)))
+    ));
+}
+
+#[test]
+fn unknown_ids_are_an_error() {
+    let mut ecs = Ecs::new();
+    let id = ecs.register_system(|_: Context| Ok(()));
+
+    // Ids are only valid for the ECS the system was registered with
+    let mut other = Ecs::new();
+    assert!(matches!(
+        other.run_system(id),
+        Err(EcsError::ScheduleError(
+            ScheduleError::RegisteredSystemNotFound(_)
+        ))
+    ));
+}
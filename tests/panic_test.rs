@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug, Default)]
+struct Ticks(u32);
+impl Resource for Ticks {}
+
+#[derive(Debug)]
+struct Health(u32);
+impl Component for Health {}
+
+fn tick(world: &mut World) -> EcsResult<()> {
+    world.resource_mut::<Ticks>().unwrap().0 += 1;
+    Ok(())
+}
+
+fn explode(_: Context) -> EcsResult<()> {
+    panic!("boom");
+}
+
+fn ticks(ecs: &Ecs) -> u32 {
+    ecs.world().resource::<Ticks>().unwrap().0
+}
+
+#[test]
+fn caught_panics_become_errors() {
+    let mut ecs = Ecs::new();
+    ecs.with_panic_policy(PanicPolicy::Catch)
+        .insert_resource(Ticks::default())
+        .add_system(explode)
+        .add_system(tick);
+
+    for _ in 0..2 {
+        let Err(EcsError::SystemPanicked { system, message }) = ecs.update(Duration::ZERO) else {
+            panic!("Expected the system to panic");
+        };
+        assert!(system.ends_with("explode"));
+        assert_eq!(message, "boom");
+    }
+
+    assert_eq!(ticks(&ecs), 0);
+}
+
+#[test]
+fn panicking_systems_can_be_disabled() -> EcsResult<()> {
+    for executor in [ExecutorKind::SingleThreaded, ExecutorKind::MultiThreaded] {
+        let mut ecs = Ecs::new();
+        ecs.with_executor(executor)
+            .with_panic_policy(PanicPolicy::CatchAndDisable)
+            .with_error_policy(ErrorPolicy::Log)
+            .insert_resource(Ticks::default())
+            .add_system_to(Startup, |world: &mut World| {
+                world.spawn()?.with(Health(3))?.build();
+                Ok(())
+            })
+            .add_system(
+                (|mut ctx: Context| {
+                    let mut health = ctx.query::<&mut Health>().single();
+                    health.0 -= 1;
+                    if health.0 == 2 {
+                        panic!("health dropped to {}", health.0);
+                    }
+                    Ok(())
+                })
+                .writes::<Health>(),
+            )
+            .add_system(tick);
+
+        for _ in 0..3 {
+            ecs.update(Duration::ZERO)?;
+        }
+
+        // The world is still usable, and the panicking system only ran once
+        assert_eq!(ticks(&ecs), 3);
+        assert_eq!(ecs.world_mut().query::<&Health>().single().0, 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn schedules_survive_panics_in_nested_schedules() {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Nested;
+    impl ScheduleLabel for Nested {}
+
+    let mut ecs = Ecs::new();
+    ecs.with_panic_policy(PanicPolicy::Catch)
+        .add_system_to(Nested, explode)
+        .add_system(|world: &mut World| world.run_schedule(Nested));
+
+    // The nested schedule is still there the second time around
+    for _ in 0..2 {
+        let Err(EcsError::SystemFailed { source, .. }) = ecs.update(Duration::ZERO) else {
+            panic!("Expected the system to fail");
+        };
+        assert!(matches!(*source, EcsError::SystemPanicked { .. }));
+    }
+}
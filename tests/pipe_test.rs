@@ -0,0 +1,73 @@
+use fonehum::*;
+
+#[derive(Debug)]
+struct Position(i32);
+impl Component for Position {}
+
+fn spawn_position(world: &mut World) -> EcsResult<()> {
+    world.spawn()?.with(Position(1))?;
+    Ok(())
+}
+
+fn compute_step(mut ctx: Context) -> i32 {
+    ctx.query::<&Position>().single().0 * 10
+}
+
+fn apply_step(In(step): In<i32>, mut ctx: Context) -> EcsResult<()> {
+    ctx.query::<&mut Position>().single().0 += step;
+    Ok(())
+}
+
+fn fail(_: Context) -> EcsResult<()> {
+    Err(WorldError::EntityNotFound(7).into())
+}
+
+/// Records the errors of the piped system instead of stopping the schedule.
+fn count_errors(
+    In(result): In<EcsResult<()>>,
+    mut ctx: Context,
+    mut failures: Local<u32>,
+) -> EcsResult<()> {
+    if let Err(EcsError::WorldError(WorldError::EntityNotFound(7))) = result {
+        *failures += 1;
+        ctx.query::<&mut Position>().single().0 = *failures as i32;
+    }
+    Ok(())
+}
+
+#[test]
+fn piped_systems_pass_their_output_along() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Startup, spawn_position)
+        .add_system(compute_step.pipe(apply_step));
+
+    ecs.run()?;
+    ecs.run()?;
+
+    let position = ecs.world_mut().query::<&Position>().single().0;
+    assert_eq!(position, 121);
+    Ok(())
+}
+
+#[test]
+fn piped_systems_are_named_after_both_systems() {
+    let system = compute_step.pipe(apply_step).into_system();
+    assert!(system.name().contains("compute_step"));
+    assert!(system.name().contains(" | "));
+    assert!(system.name().ends_with("apply_step"));
+}
+
+#[test]
+fn results_can_be_piped_into_adapters() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Startup, spawn_position)
+        .add_system(fail.pipe(count_errors));
+
+    ecs.run()?;
+    ecs.run()?;
+    ecs.run()?;
+
+    let failures = ecs.world_mut().query::<&Position>().single().0;
+    assert_eq!(failures, 3);
+    Ok(())
+}
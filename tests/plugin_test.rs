@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug, Default)]
+struct Log(Vec<String>);
+impl Resource for Log {}
+
+fn log(world: &mut World, entry: &str) {
+    world.resource_mut::<Log>().unwrap().0.push(entry.into());
+}
+
+struct PhysicsPlugin;
+impl Plugin for PhysicsPlugin {
+    fn build(&self, ecs: &mut Ecs) {
+        ecs.insert_resource(Log::default())
+            .add_system(|world: &mut World| {
+                log(world, "physics");
+                Ok(())
+            });
+    }
+
+    fn finish(&self, ecs: &mut Ecs) {
+        // Finishing happens after every plugin was built
+        assert!(ecs.has_plugin(std::any::type_name::<AudioPlugin>()));
+        log(ecs.world_mut(), "physics finished");
+    }
+}
+
+struct AudioPlugin;
+impl Plugin for AudioPlugin {
+    fn build(&self, ecs: &mut Ecs) {
+        ecs.add_system(|world: &mut World| {
+            log(world, "audio");
+            Ok(())
+        });
+    }
+}
+
+struct LabelPlugin(&'static str);
+impl Plugin for LabelPlugin {
+    fn build(&self, ecs: &mut Ecs) {
+        let label = self.0;
+        ecs.add_system(move |world: &mut World| {
+            log(world, label);
+            Ok(())
+        });
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+struct EnginePlugins;
+impl PluginGroup for EnginePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::new()
+            .add_plugin(PhysicsPlugin)
+            .add_group((AudioPlugin, LabelPlugin("ui")))
+    }
+}
+
+#[test]
+fn plugins_register_systems_and_resources() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_plugins(EnginePlugins)
+        .add_plugin(LabelPlugin("game"));
+
+    ecs.update(Duration::ZERO)?;
+    ecs.update(Duration::ZERO)?;
+
+    assert_eq!(
+        ecs.world().resource::<Log>().unwrap().0,
+        vec![
+            "physics finished",
+            "physics",
+            "audio",
+            "ui",
+            "game",
+            "physics",
+            "audio",
+            "ui",
+            "game",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn non_unique_plugins_can_be_added_more_than_once() {
+    let mut ecs = Ecs::new();
+    ecs.add_plugins((LabelPlugin("a"), LabelPlugin("b")));
+
+    assert!(ecs.has_plugin(std::any::type_name::<LabelPlugin>()));
+}
+
+#[test]
+#[should_panic(expected = "was already added")]
+fn unique_plugins_cannot_be_added_twice() {
+    let mut ecs = Ecs::new();
+    ecs.add_plugin(AudioPlugin).add_plugin(AudioPlugin);
+}
+
+/// Logs its label when it's finished, and then adds a plugin with the second label (if any).
+struct FinishPlugin(&'static str, Option<&'static str>);
+impl Plugin for FinishPlugin {
+    fn build(&self, ecs: &mut Ecs) {
+        ecs.init_resource::<Log>();
+    }
+
+    fn finish(&self, ecs: &mut Ecs) {
+        log(ecs.world_mut(), self.0);
+        if let Some(label) = self.1 {
+            ecs.add_plugin(FinishPlugin(label, None));
+        }
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn plugins_added_late_are_still_finished() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_plugin(FinishPlugin("first", Some("nested")));
+    ecs.update(Duration::ZERO)?;
+
+    // Added after the first tick
+    ecs.add_plugin(FinishPlugin("late", None));
+
+    assert_eq!(
+        ecs.world().resource::<Log>().unwrap().0,
+        vec!["first", "nested", "late"]
+    );
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug)]
+struct RigidBody;
+impl Component for RigidBody {}
+
+#[derive(Debug)]
+struct Health;
+impl Component for Health {}
+
+/// The entities a system read in each run.
+#[derive(Debug, Default)]
+struct Removed(Vec<Vec<usize>>);
+impl Resource for Removed {}
+
+fn record_removed(ctx: Context, mut removed: RemovedComponents<RigidBody>) -> EcsResult<()> {
+    let entities = removed.read().collect();
+    ctx.resource_mut::<Removed>()?.0.push(entities);
+    Ok(())
+}
+
+#[test]
+fn removals_and_despawns_are_reported_once() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(record_removed);
+
+    let world = ecs.world_mut();
+    world.insert_resource(Removed::default());
+    let e0 = world.spawn()?.with(RigidBody)?.build();
+    let e1 = world.spawn()?.with(RigidBody)?.with(Health)?.build();
+    let e2 = world.spawn()?.with(Health)?.build();
+
+    world.remove::<RigidBody>(e0)?;
+    world.despawn(e1)?;
+    // Neither of these had a rigid body
+    world.remove::<RigidBody>(e2)?;
+    world.despawn(e2)?;
+    ecs.run()?;
+    ecs.run()?;
+
+    assert_eq!(
+        ecs.world_mut().resource::<Removed>().unwrap().0,
+        [vec![e0, e1], vec![]]
+    );
+    Ok(())
+}
+
+#[test]
+fn systems_see_removals_made_after_them_in_their_next_run() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(record_removed)
+        .add_system(|_: Context, mut commands: Commands, mut runs: Local<u32>| {
+            *runs += 1;
+            if *runs == 1 {
+                commands.remove::<RigidBody>(0);
+            }
+            Ok(())
+        })
+        .add_system(record_removed);
+
+    let world = ecs.world_mut();
+    world.insert_resource(Removed::default());
+    world.spawn()?.with(RigidBody)?;
+    ecs.run()?;
+    ecs.run()?;
+    ecs.run()?;
+
+    // Each system sees the removal exactly once: the one after the command right away (the
+    // command is applied before it runs), the one before it in the next tick
+    let removed = &ecs.world_mut().resource::<Removed>().unwrap().0;
+    assert_eq!(removed, &[vec![], vec![0], vec![0], vec![], vec![], vec![]]);
+    Ok(())
+}
+
+#[test]
+fn removals_are_tracked_per_component_type() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(
+        |_: Context,
+         mut bodies: RemovedComponents<RigidBody>,
+         health: RemovedComponents<Health>| {
+            assert_eq!(bodies.len(), 0);
+            assert_eq!(health.len(), 2);
+            assert!(bodies.read().next().is_none());
+            Ok(())
+        },
+    );
+
+    let world = ecs.world_mut();
+    let e0 = world.spawn()?.with(Health)?.build();
+    let e1 = world.spawn()?.with(Health)?.build();
+    world.remove::<Health>(e0)?;
+    world.despawn(e1)?;
+    ecs.run()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Cleanup;
+impl ScheduleLabel for Cleanup {}
+
+#[test]
+fn removals_made_while_a_system_runs_are_read_in_its_next_run() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Cleanup, |world: &mut World| {
+        // The first removal of the component creates its log
+        world.remove::<RigidBody>(0)?;
+        world.remove::<RigidBody>(1)?;
+        Ok(())
+    })
+    .add_system(
+        |mut ctx: Context, mut removed: RemovedComponents<RigidBody>, mut runs: Local<u32>| {
+            *runs += 1;
+            let entities = removed.read().collect();
+            if *runs == 1 {
+                ctx.run_schedule(Cleanup)?;
+                assert!(removed.is_empty());
+            }
+            ctx.resource_mut::<Removed>()?.0.push(entities);
+            Ok(())
+        },
+    );
+
+    let world = ecs.world_mut();
+    world.insert_resource(Removed::default());
+    world.spawn()?.with(RigidBody)?;
+    world.spawn()?.with(RigidBody)?;
+    ecs.run()?;
+    ecs.run()?;
+
+    assert_eq!(
+        ecs.world_mut().resource::<Removed>().unwrap().0,
+        [vec![], vec![0, 1]]
+    );
+    Ok(())
+}
+
+#[test]
+fn throttled_systems_see_removals_from_the_ticks_they_skipped() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system(record_removed.run_rate(RunRate::every_ticks(4)));
+
+    let world = ecs.world_mut();
+    world.insert_resource(Removed::default());
+    let entity = world.spawn()?.with(RigidBody)?.build();
+    ecs.run()?;
+
+    // Removed while the system skips three ticks
+    ecs.world_mut().remove::<RigidBody>(entity)?;
+    for _ in 0..4 {
+        ecs.run()?;
+    }
+    ecs.run()?;
+
+    assert_eq!(
+        ecs.world_mut().resource::<Removed>().unwrap().0,
+        [vec![], vec![entity]]
+    );
+    Ok(())
+}
+
+#[test]
+fn fixed_update_systems_see_removals_from_ticks_without_steps() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.with_fixed_timestep(Duration::from_millis(10))
+        .add_system_to(FixedUpdate, record_removed);
+
+    let world = ecs.world_mut();
+    world.insert_resource(Removed::default());
+    let entity = world.spawn()?.with(RigidBody)?.build();
+    ecs.update(Duration::from_millis(10))?;
+
+    // Removed on a tick without fixed steps, followed by more of them
+    ecs.world_mut().remove::<RigidBody>(entity)?;
+    for _ in 0..3 {
+        ecs.update(Duration::ZERO)?;
+    }
+    ecs.update(Duration::from_millis(10))?;
+    ecs.update(Duration::from_millis(10))?;
+
+    assert_eq!(
+        ecs.world_mut().resource::<Removed>().unwrap().0,
+        [vec![], vec![entity], vec![]]
+    );
+    Ok(())
+}
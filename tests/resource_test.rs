@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug, Default, PartialEq)]
+struct Score(u32);
+impl Resource for Score {}
+
+#[derive(Debug, Default)]
+struct Settings {
+    multiplier: u32,
+}
+impl Resource for Settings {}
+
+fn add_points(ctx: Context) -> EcsResult<()> {
+    let settings = ctx.resource::<Settings>()?;
+    ctx.resource_mut::<Score>()?.0 += 10 * settings.multiplier;
+    Ok(())
+}
+
+#[test]
+fn systems_can_read_and_write_resources() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Settings { multiplier: 2 })
+        .init_resource::<Score>()
+        .add_system(add_points);
+
+    ecs.run()?;
+    ecs.run()?;
+    assert_eq!(ecs.world().resource::<Score>(), Some(&Score(40)));
+
+    // Initializing a resource keeps the existing value
+    ecs.init_resource::<Score>();
+    assert_eq!(ecs.remove_resource::<Score>(), Some(Score(40)));
+    assert!(!ecs.world().contains_resource::<Score>());
+    Ok(())
+}
+
+#[test]
+fn missing_resources_are_an_error() {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Score>().add_system(add_points);
+
+    let Err(EcsError::SystemFailed { source, .. }) = ecs.run() else {
+        panic!("the system should fail");
+    };
+    assert!(matches!(
+        *source,
+        EcsError::ResourceError(ResourceError::ResourceNotFound(name)) if name.ends_with("Settings")
+    ));
+}
+
+#[test]
+fn conflicting_borrows_are_an_error() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Score>().add_system(|mut ctx: Context| {
+        {
+            let _score = ctx.resource_mut::<Score>()?;
+            assert!(matches!(
+                ctx.resource::<Score>(),
+                Err(EcsError::ResourceError(
+                    ResourceError::AlreadyBorrowedMutably(_)
+                ))
+            ));
+        }
+
+        let first = ctx.resource::<Score>()?;
+        let second = ctx.resource::<Score>()?;
+        assert_eq!(*first, *second);
+        assert!(matches!(
+            ctx.resource_mut::<Score>(),
+            Err(EcsError::ResourceError(ResourceError::AlreadyBorrowed(_)))
+        ));
+        drop((first, second));
+
+        // Resources can't be replaced or removed while they are borrowed
+        let score = ctx.resource::<Score>()?;
+        assert!(ctx.remove_resource::<Score>().is_err());
+        assert!(ctx.insert_resource(Score(1)).is_err());
+        drop(score);
+
+        ctx.insert_resource(Score(5))?;
+        ctx.resource_mut::<Score>()?.0 += 1;
+        Ok(())
+    });
+
+    ecs.run()?;
+    assert_eq!(ecs.world().resource::<Score>(), Some(&Score(6)));
+    Ok(())
+}
+
+#[test]
+fn declared_resource_access_prevents_conflicts() -> EcsResult<()> {
+    let hold_score = |ctx: Context| {
+        let mut score = ctx.resource_mut::<Score>()?;
+        std::thread::sleep(Duration::from_millis(20));
+        score.0 += 1;
+        Ok(())
+    };
+
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded)
+        .init_resource::<Score>()
+        .add_system(hold_score.writes_resource::<Score>().named("a"))
+        .add_system(hold_score.writes_resource::<Score>().named("b"))
+        .add_system(
+            (|ctx: Context| {
+                ctx.resource::<Score>()?;
+                Ok(())
+            })
+            .reads_resource::<Score>(),
+        );
+
+    ecs.run()?;
+    assert_eq!(ecs.world().resource::<Score>(), Some(&Score(2)));
+    Ok(())
+}
+
+#[test]
+fn undeclared_resource_access_panics() {
+    let mut ecs = Ecs::new();
+    ecs.with_panic_policy(PanicPolicy::Catch)
+        .init_resource::<Score>()
+        .init_resource::<Settings>()
+        .add_system(add_points.reads_resource::<Settings>());
+
+    let Err(EcsError::SystemPanicked { message, .. }) = ecs.run() else {
+        panic!("the system should panic");
+    };
+    assert!(message.contains("without declaring it"), "{message}");
+}
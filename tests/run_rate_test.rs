@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug, Default)]
+struct Ran(Vec<(&'static str, u64)>);
+impl Resource for Ran {}
+
+/// Creates a system that records how many times it ran before.
+fn record(entry: &'static str) -> impl FnMut(&mut World) -> EcsResult<()> {
+    let mut tick = 0;
+    move |world| {
+        world.resource_mut::<Ran>().unwrap().0.push((entry, tick));
+        tick += 1;
+        Ok(())
+    }
+}
+
+/// Gets the runs (counted by `record`) of the systems with the specified entry.
+fn ticks_of(ecs: &Ecs, entry: &str) -> Vec<u64> {
+    let ran = &ecs.world().resource::<Ran>().unwrap().0;
+    ran.iter()
+        .filter(|(name, _)| *name == entry)
+        .map(|(_, tick)| *tick)
+        .collect()
+}
+
+#[test]
+fn systems_can_run_every_few_ticks_with_a_phase() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Ran::default())
+        .add_system(
+            (|ctx: Context| {
+                assert_eq!(ctx.time().tick() % 4, 0);
+                Ok(())
+            })
+            .run_rate(RunRate::every_ticks(4)),
+        )
+        .add_system(
+            (|ctx: Context| {
+                assert_eq!(ctx.time().tick() % 4, 2);
+                Ok(())
+            })
+            .run_rate(RunRate::every_ticks(4).with_phase(0.5)),
+        )
+        .add_system(record("every"))
+        .add_system_to(
+            FixedUpdate,
+            record("fixed").run_rate(RunRate::every_ticks(2).with_phase(0.5)),
+        );
+
+    for _ in 0..8 {
+        ecs.update(Duration::from_millis(50))?;
+    }
+
+    // Throttled systems run at most once per tick, even in `FixedUpdate`
+    assert_eq!(ticks_of(&ecs, "every"), (0..8).collect::<Vec<_>>());
+    assert_eq!(ticks_of(&ecs, "fixed"), [0, 1, 2, 3]);
+    assert_eq!(ecs.world().resource::<Ran>().unwrap().0.len(), 12);
+    Ok(())
+}
+
+#[test]
+fn systems_can_run_at_a_frequency_of_virtual_time() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Ran::default())
+        .add_system(
+            (move |world: &mut World| {
+                world.resource_mut::<Ran>().unwrap().0.push(("10hz", 0));
+                Ok(())
+            })
+            .run_rate(RunRate::hz(10.0)),
+        )
+        .add_system(
+            (|ctx: Context| {
+                // Runs at 25ms, 125ms, ... (the long tick below ends at 1475ms)
+                let elapsed = ctx.time().elapsed().as_millis();
+                assert!(elapsed % 100 == 25 || elapsed == 1475, "ran at {elapsed}ms");
+                Ok(())
+            })
+            .run_rate(RunRate::hz(10.0).with_phase(0.25)),
+        );
+
+    // Runs at 25ms (the first tick), 100ms, 200ms, 300ms and 400ms
+    for _ in 0..19 {
+        ecs.update(Duration::from_millis(25))?;
+    }
+    assert_eq!(ecs.world().resource::<Ran>().unwrap().0.len(), 5);
+
+    // Runs missed during a long tick aren't made up for
+    ecs.update(Duration::from_secs(1))?;
+    ecs.update(Duration::from_millis(10))?;
+    assert_eq!(ecs.world().resource::<Ran>().unwrap().0.len(), 6);
+    Ok(())
+}
+
+#[test]
+fn long_ticks_skip_missed_runs_in_one_step() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Ran::default())
+        .add_system(record("fast").run_rate(RunRate::hz(1e9)));
+
+    // A nanosecond interval over a day of virtual time
+    ecs.update(Duration::from_secs(24 * 60 * 60))?;
+    ecs.update(Duration::from_nanos(1))?;
+    ecs.update(Duration::ZERO)?;
+
+    assert_eq!(ticks_of(&ecs, "fast"), [0, 1]);
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "interval must be non-zero")]
+fn frequencies_with_a_zero_interval_are_rejected() {
+    RunRate::hz(1e10);
+}
+
+#[derive(Debug)]
+struct Ready(bool);
+impl Resource for Ready {}
+
+#[test]
+fn conditions_that_fail_when_due_do_not_use_up_the_run() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Ran::default()).add_system(
+        (|ctx: Context| {
+            let tick = ctx.time().tick();
+            ctx.resource_mut::<Ran>()?.0.push(("ready", tick));
+            Ok(())
+        })
+        .run_rate(RunRate::every_ticks(4))
+        .run_if(|ctx: Context| ctx.resource::<Ready>().is_ok_and(|ready| ready.0)),
+    );
+
+    for tick in 0..13 {
+        ecs.insert_resource(Ready(![0, 8, 9].contains(&tick)));
+        ecs.run()?;
+    }
+
+    // Delayed runs happen as soon as the condition is met, and the rate stays on its ticks
+    assert_eq!(ticks_of(&ecs, "ready"), [1, 4, 10, 12]);
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use fonehum::*;
+
+#[derive(Debug, Default)]
+struct Ran(Vec<&'static str>);
+impl Resource for Ran {}
+
+fn record(entry: &'static str) -> impl FnMut(&mut World) -> EcsResult<()> {
+    move |world| {
+        world.resource_mut::<Ran>().unwrap().0.push(entry);
+        Ok(())
+    }
+}
+
+/// Takes the systems that ran since the last call.
+fn take_ran(ecs: &mut Ecs) -> Vec<&'static str> {
+    std::mem::take(&mut ecs.world_mut().resource_mut::<Ran>().unwrap().0)
+}
+
+/// Applies `edit` in the tick with the specified index (counting from zero).
+fn edit_in_tick(tick: u32, mut edit: impl FnMut(&mut World) + Send + 'static) -> SystemConfig {
+    let mut ticks = 0;
+    (move |world: &mut World| {
+        if ticks == tick {
+            edit(world);
+        }
+        ticks += 1;
+        Ok(())
+    })
+    .named("console")
+}
+
+#[test]
+fn systems_can_be_toggled_between_ticks() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Ran::default())
+        .add_system(record("a").named("a"))
+        .add_system(edit_in_tick(0, |world| world.disable_system(Update, "a")).before("a"))
+        .add_system_to(
+            PostUpdate,
+            edit_in_tick(1, |world| world.enable_system(Update, "a")),
+        );
+
+    // The change is only applied once the tick completes
+    ecs.run()?;
+    assert_eq!(take_ran(&mut ecs), ["a"]);
+    ecs.run()?;
+    assert_eq!(take_ran(&mut ecs), Vec::<&str>::new());
+    ecs.run()?;
+    assert_eq!(take_ran(&mut ecs), ["a"]);
+    Ok(())
+}
+
+#[test]
+fn systems_can_be_added_and_removed_between_ticks() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Ran::default())
+        .add_system(record("a").named("a"))
+        .add_system(edit_in_tick(0, |world| {
+            world.add_system_to(Update, record("b").named("b").after("a"));
+            world.add_system_to(PostUpdate, record("c"));
+        }))
+        .add_system(edit_in_tick(1, |world| world.remove_system(Update, "a")).named("remover"));
+
+    ecs.run()?;
+    assert_eq!(take_ran(&mut ecs), ["a"]);
+    ecs.run()?;
+    assert_eq!(take_ran(&mut ecs), ["a", "b", "c"]);
+
+    // Systems that are ordered relative to a removed system have to be removed as well
+    ecs.world_mut().remove_system(Update, "b");
+    ecs.run()?;
+    assert_eq!(take_ran(&mut ecs), ["c"]);
+    Ok(())
+}
+
+#[test]
+fn editing_missing_systems_is_an_error() {
+    let mut ecs = Ecs::new();
+    ecs.add_system(edit_in_tick(0, |world| {
+        world.disable_system(Update, "missing");
+        world.add_system_to(Update, |_: Context| Ok(()));
+    }));
+
+    let Err(EcsError::ScheduleError(ScheduleError::SystemNotFound { system, .. })) = ecs.run()
+    else {
+        panic!("disabling a missing system should fail");
+    };
+    assert_eq!(system, "missing");
+}
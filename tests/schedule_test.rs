@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug)]
+struct Log(Vec<&'static str>);
+impl Component for Log {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct LoadLevel;
+impl ScheduleLabel for LoadLevel {}
+
+fn log(ctx: &mut Context, entry: &'static str) {
+    ctx.query::<&mut Log>().single().0.push(entry);
+}
+
+fn spawn_log(mut ctx: Context) -> EcsResult<()> {
+    ctx.spawn()?.with(Log(vec!["startup"]))?.build();
+    Ok(())
+}
+
+#[test]
+fn startup_runs_once_before_per_frame_schedules() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Last, |mut ctx: Context| {
+        log(&mut ctx, "last");
+        Ok(())
+    })
+    .add_system_to(PostUpdate, |mut ctx: Context| {
+        log(&mut ctx, "post_update");
+        Ok(())
+    })
+    .add_system(|mut ctx: Context| {
+        log(&mut ctx, "update");
+        Ok(())
+    })
+    .add_system_to(PreUpdate, |mut ctx: Context| {
+        log(&mut ctx, "pre_update");
+        Ok(())
+    })
+    .add_system_to(Startup, spawn_log);
+
+    ecs.update(Duration::ZERO)?;
+    ecs.update(Duration::ZERO)?;
+
+    ecs.add_system_to(Last, |mut ctx: Context| {
+        let log = ctx.query::<&Log>().single();
+        assert_eq!(
+            log.0,
+            vec![
+                "startup",
+                "pre_update",
+                "update",
+                "post_update",
+                "last",
+                "pre_update",
+                "update",
+                "post_update",
+                "last",
+                "pre_update",
+                "update",
+                "post_update",
+                "last",
+            ]
+        );
+        Ok(())
+    })
+    .run()
+}
+
+#[test]
+fn custom_schedules_run_on_demand() -> EcsResult<()> {
+    Ecs::new()
+        .add_system_to(Startup, spawn_log)
+        .add_system_to(LoadLevel, |mut ctx: Context| {
+            log(&mut ctx, "load_level");
+            Ok(())
+        })
+        .add_system(|mut ctx: Context| {
+            log(&mut ctx, "update");
+            ctx.run_schedule(LoadLevel)?;
+            log(&mut ctx, "update_done");
+
+            let log = ctx.query::<&Log>().single();
+            assert_eq!(
+                log.0,
+                vec!["startup", "update", "load_level", "update_done"]
+            );
+            Ok(())
+        })
+        .run()
+}
+
+#[test]
+fn running_a_missing_schedule_fails() {
+    let result = Ecs::new()
+        .add_system(|mut ctx: Context| ctx.run_schedule(LoadLevel))
+        .run();
+
+    let Err(EcsError::SystemFailed { source, .. }) = result else {
+        panic!("Expected the system to fail");
+    };
+    assert!(matches!(*source, EcsError::ScheduleError(_)));
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum GameState {
+    MainMenu,
+    Playing,
+}
+impl States for GameState {}
+
+#[derive(Debug)]
+struct Log(Vec<&'static str>);
+impl Component for Log {}
+
+fn log(mut ctx: Context, entry: &'static str) -> EcsResult<()> {
+    ctx.query::<&mut Log>().single().0.push(entry);
+    Ok(())
+}
+
+#[test]
+fn transitions_run_on_enter_and_on_exit_schedules() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_state(GameState::MainMenu)
+        .add_system_to(Startup, |mut ctx: Context| {
+            ctx.spawn()?.with(Log(vec![]))?.build();
+            Ok(())
+        })
+        .add_system_to(OnEnter(GameState::MainMenu), |ctx: Context| {
+            log(ctx, "enter_menu")
+        })
+        .add_system_to(OnExit(GameState::MainMenu), |ctx: Context| {
+            log(ctx, "exit_menu")
+        })
+        .add_system_to(OnEnter(GameState::Playing), |ctx: Context| {
+            log(ctx, "enter_playing")
+        })
+        .add_system(
+            (|mut ctx: Context| {
+                ctx.set_next_state(GameState::Playing)?;
+                log(ctx, "menu")
+            })
+            .run_if(in_state(GameState::MainMenu)),
+        )
+        .add_system((|ctx: Context| log(ctx, "playing")).run_if(in_state(GameState::Playing)));
+
+    ecs.update(Duration::ZERO)?;
+    ecs.update(Duration::ZERO)?;
+
+    ecs.add_system_to(Last, |mut ctx: Context| {
+        assert_eq!(*ctx.state::<GameState>()?, GameState::Playing);
+        assert_eq!(ctx.next_state::<GameState>()?.get(), None);
+
+        let log = ctx.query::<&Log>().single();
+        assert_eq!(
+            log.0,
+            vec![
+                "enter_menu",
+                "menu",
+                "exit_menu",
+                "enter_playing",
+                "playing",
+                "playing"
+            ]
+        );
+        Ok(())
+    })
+    .run()
+}
+
+#[test]
+fn transitions_to_the_same_state_are_ignored() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_state(GameState::Playing)
+        .add_system_to(Startup, |mut ctx: Context| {
+            ctx.spawn()?.with(Log(vec![]))?.build();
+            Ok(())
+        })
+        .add_system_to(OnEnter(GameState::Playing), |ctx: Context| {
+            log(ctx, "enter_playing")
+        })
+        .add_system(|mut ctx: Context| ctx.set_next_state(GameState::Playing));
+
+    ecs.update(Duration::ZERO)?;
+
+    ecs.add_system_to(Last, |mut ctx: Context| {
+        assert_eq!(ctx.query::<&Log>().single().0, vec!["enter_playing"]);
+        Ok(())
+    })
+    .run()
+}
+
+#[test]
+fn missing_states_are_reported() {
+    let result = Ecs::new()
+        .add_system(|mut ctx: Context| ctx.set_next_state(GameState::Playing))
+        .run();
+
+    let Err(EcsError::SystemFailed { source, .. }) = result else {
+        panic!("Expected the system to fail");
+    };
+    assert!(matches!(*source, EcsError::StateError(_)));
+}
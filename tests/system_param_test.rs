@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug, Default)]
+struct Counts(Vec<(&'static str, u32)>);
+impl Resource for Counts {}
+
+fn push(world: &mut World, entry: (&'static str, u32)) {
+    world.resource_mut::<Counts>().unwrap().0.push(entry);
+}
+
+fn counts(ecs: &Ecs) -> &[(&'static str, u32)] {
+    &ecs.world().resource::<Counts>().unwrap().0
+}
+
+#[test]
+fn fn_mut_closures_keep_state_between_ticks() -> EcsResult<()> {
+    let mut ticks = 0;
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Counts::default())
+        .add_system(move |_: Context| {
+            ticks += 1;
+            assert!(ticks <= 3);
+            Ok(())
+        })
+        .add_system({
+            let mut seen = 0;
+            move |world: &mut World| {
+                seen += 1;
+                push(world, ("exclusive", seen));
+                Ok(())
+            }
+        });
+
+    for _ in 0..3 {
+        ecs.update(Duration::ZERO)?;
+    }
+
+    assert_eq!(
+        counts(&ecs),
+        [("exclusive", 1), ("exclusive", 2), ("exclusive", 3)]
+    );
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Log(Vec<(&'static str, u32)>);
+impl Component for Log {}
+
+fn counter(name: &'static str) -> impl FnMut(Context, Local<u32>) -> EcsResult<()> + Send {
+    move |mut ctx: Context, mut count: Local<u32>| {
+        *count += 1;
+        let count = *count;
+        ctx.query::<&mut Log>().single().0.push((name, count));
+        Ok(())
+    }
+}
+
+fn count_pairs(mut ctx: Context, mut evens: Local<u32>, mut odds: Local<u32>) -> EcsResult<()> {
+    let mut log = ctx.query::<&mut Log>().single();
+    if log.0.len().is_multiple_of(2) {
+        *evens += 1;
+        log.0.push(("evens", *evens));
+    } else {
+        *odds += 1;
+        log.0.push(("odds", *odds));
+    }
+    Ok(())
+}
+
+#[test]
+fn locals_are_private_to_each_system() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Startup, |world: &mut World| {
+        world.spawn()?.with(Log::default())?.build();
+        Ok(())
+    })
+    .add_system(counter("a"))
+    .add_system(counter("b"))
+    .add_system(count_pairs);
+
+    ecs.update(Duration::ZERO)?;
+    ecs.update(Duration::ZERO)?;
+
+    assert_eq!(
+        ecs.world_mut().query::<&Log>().single().0,
+        vec![
+            ("a", 1),
+            ("b", 1),
+            ("evens", 1),
+            ("a", 2),
+            ("b", 2),
+            ("odds", 1),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn closures_can_take_locals() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Counts::default()).add_system(
+        |_: Context, mut history: Local<Vec<u32>>, mut total: Local<u32>| {
+            *total += 10;
+            history.push(*total);
+            assert_eq!(history.len() as u32 * 10, *total);
+            Ok(())
+        },
+    );
+
+    for _ in 0..3 {
+        ecs.update(Duration::ZERO)?;
+    }
+    Ok(())
+}
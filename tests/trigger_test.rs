@@ -0,0 +1,138 @@
+use fonehum::*;
+
+#[derive(Debug)]
+struct Damage(u32);
+impl Event for Damage {}
+
+/// An event that bubbles up from an entity to its ancestors.
+#[derive(Debug)]
+struct Click;
+impl Event for Click {
+    const PROPAGATE: bool = true;
+}
+
+#[derive(Debug)]
+struct Health(u32);
+impl Component for Health {}
+
+#[derive(Debug)]
+struct Blocking;
+impl Component for Blocking {}
+
+#[derive(Debug, Default)]
+struct Log(Vec<String>);
+impl Resource for Log {}
+
+fn log(world: &mut World, entry: String) {
+    world.resource_mut::<Log>().unwrap().0.push(entry);
+}
+
+fn entries(ecs: &Ecs) -> &[String] {
+    &ecs.world().resource::<Log>().unwrap().0
+}
+
+#[test]
+fn systems_can_trigger_events_for_entities() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_observer(|mut trigger: Trigger<Damage>, world: &mut World| {
+        // Halve the damage of entities that are blocking
+        if world.get::<Blocking>(trigger.entity())?.is_some() {
+            trigger.event_mut().0 /= 2;
+        }
+        Ok(())
+    })
+    .add_observer(|trigger: Trigger<Damage>, world: &mut World| {
+        let health = world.get_mut::<Health>(trigger.entity())?.unwrap();
+        health.0 = health.0.saturating_sub(trigger.event().0);
+        Ok(())
+    })
+    .add_system(|mut ctx: Context| {
+        let knight = ctx.spawn()?.with(Health(100))?.with(Blocking)?.build();
+        let peasant = ctx.spawn()?.with(Health(100))?.build();
+        ctx.trigger(Damage(40), knight)?;
+        ctx.trigger(Damage(40), peasant)?;
+        ctx.trigger(Damage(40), peasant)
+    });
+
+    let health = |ecs: &Ecs, entity| ecs.world().get::<Health>(entity).unwrap().unwrap().0;
+    ecs.run()?;
+    assert_eq!(health(&ecs, 0), 80);
+    assert_eq!(health(&ecs, 1), 20);
+    Ok(())
+}
+
+#[test]
+fn triggers_propagate_up_the_parent_chain_until_stopped() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>()
+        .add_observer(|trigger: Trigger<Click>, world: &mut World| {
+            log(
+                world,
+                format!("global {} <- {}", trigger.entity(), trigger.target()),
+            );
+            Ok(())
+        });
+
+    let world = ecs.world_mut();
+    let window = world
+        .spawn()?
+        .observe(|_: Trigger<Click>, world: &mut World| {
+            log(world, "window".into());
+            Ok(())
+        })
+        .build();
+    let panel = world
+        .spawn()?
+        .with(Parent(window))?
+        .observe(|mut trigger: Trigger<Click>, world: &mut World| {
+            log(world, "panel".into());
+            if trigger.target() != trigger.entity() {
+                trigger.stop_propagation();
+            }
+            Ok(())
+        })
+        .build();
+    let button = world.spawn()?.with(Parent(panel))?.build();
+
+    world.trigger(Click, button)?;
+    world.trigger(Click, panel)?;
+
+    assert_eq!(
+        entries(&ecs),
+        [
+            "global 2 <- 2",
+            "global 1 <- 2",
+            "panel",
+            "global 1 <- 1",
+            "panel",
+            "global 0 <- 1",
+            "window",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn entity_observers_are_removed_with_their_entity() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.init_resource::<Log>();
+
+    let world = ecs.world_mut();
+    let entity = world.spawn()?.with(Health(1))?.build();
+    world.add_entity_observer(entity, |_: Trigger<OnRemove, Health>, world: &mut World| {
+        log(world, "removed".into());
+        Ok(())
+    })?;
+
+    // The observer still sees the components being removed by the despawn
+    world.despawn(entity)?;
+    assert!(matches!(
+        world.trigger(Damage(1), entity),
+        Err(EcsError::WorldError(WorldError::EntityNotFound(0)))
+    ));
+
+    let other = world.spawn()?.with(Health(1))?.build();
+    world.remove::<Health>(other)?;
+    assert_eq!(entries(&ecs), ["removed"]);
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use fonehum::*;
+
+#[derive(Debug, PartialEq)]
+struct Position(i32);
+impl Component for Position {}
+
+#[derive(Debug, PartialEq)]
+struct Velocity(i32);
+impl Component for Velocity {}
+
+#[derive(Debug, PartialEq)]
+struct Name(&'static str);
+impl Component for Name {}
+
+#[derive(Debug, PartialEq)]
+struct Level(u32);
+impl Resource for Level {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct LoadLevel;
+impl ScheduleLabel for LoadLevel {}
+
+#[test]
+fn exclusive_systems_can_restructure_the_world() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(Startup, |world: &mut World| {
+        for x in 0..3 {
+            world.spawn()?.with(Position(x))?.with(Velocity(1))?.build();
+        }
+        Ok(())
+    })
+    .add_system(|world: &mut World| {
+        let entities = (0..3).filter(|&entity| world.contains(entity));
+        for entity in entities.collect::<Vec<_>>() {
+            if world.get::<Position>(entity)? == Some(&Position(1)) {
+                world.despawn(entity)?;
+            } else {
+                world.remove::<Velocity>(entity)?;
+                world.insert(entity, Name("kept"))?;
+            }
+        }
+        Ok(())
+    })
+    .add_system_to(Last, |mut ctx: Context| {
+        let positions = ctx
+            .query::<(&Position, &Name)>()
+            .into_iter()
+            .map(|(position, _)| position.0)
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![0, 2]);
+        Ok(())
+    });
+
+    ecs.update(Duration::ZERO)?;
+
+    let world = ecs.world_mut();
+    assert!(!world.contains(1));
+    assert!(world.get::<Velocity>(0)?.is_none());
+    assert_eq!(world.get::<Name>(2)?, Some(&Name("kept")));
+    assert_eq!(world.query::<&Velocity>().num_entities(), 0);
+    Ok(())
+}
+
+#[test]
+fn exclusive_systems_can_run_schedules_and_use_resources() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.add_system_to(LoadLevel, |world: &mut World| {
+        world.resource_mut::<Level>().unwrap().0 += 1;
+        world.spawn()?.with(Name("enemy"))?.build();
+        Ok(())
+    })
+    .add_system(|world: &mut World| {
+        world.insert_resource(Level(0));
+        world.run_schedule(LoadLevel)?;
+        world.run_schedule(LoadLevel)
+    });
+
+    ecs.update(Duration::ZERO)?;
+
+    let world = ecs.world_mut();
+    assert_eq!(world.resource::<Level>(), Some(&Level(2)));
+    assert_eq!(world.query::<&Name>().num_entities(), 2);
+    assert_eq!(world.remove_resource::<Level>(), Some(Level(2)));
+    assert!(!world.contains_resource::<Level>());
+    Ok(())
+}
+
+#[test]
+fn exclusive_systems_run_alone_in_the_multi_threaded_executor() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    ecs.with_executor(ExecutorKind::MultiThreaded)
+        .add_system_to(Startup, |world: &mut World| {
+            world.spawn()?.with(Position(0))?.build();
+            Ok(())
+        })
+        .add_system(
+            (|mut ctx: Context| {
+                ctx.query::<&mut Position>().single().0 += 1;
+                Ok(())
+            })
+            .writes::<Position>(),
+        )
+        .add_system(|world: &mut World| {
+            let mut position = world.query::<&mut Position>().single();
+            assert_eq!(position.0, 1);
+            position.0 *= 10;
+            Ok(())
+        })
+        .add_system(
+            (|mut ctx: Context| {
+                assert_eq!(ctx.query::<&Position>().single().0, 10);
+                Ok(())
+            })
+            .reads::<Position>(),
+        );
+
+    ecs.update(Duration::ZERO)
+}
+
+#[test]
+fn despawned_entities_are_not_found() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let world = ecs.world_mut();
+
+    let entity = world.spawn()?.with(Position(0))?.build();
+    world.despawn(entity)?;
+
+    assert!(matches!(
+        world.insert(entity, Position(1)),
+        Err(EcsError::WorldError(WorldError::EntityNotFound(_)))
+    ));
+    assert!(world.despawn(entity).is_err());
+    Ok(())
+}
+
+#[test]
+fn queries_match_entities_with_extra_components() -> EcsResult<()> {
+    let mut ecs = Ecs::new();
+    let world = ecs.world_mut();
+
+    world.spawn()?.with(Position(1))?.with(Velocity(1))?.build();
+    world
+        .spawn()?
+        .with(Position(2))?
+        .with(Velocity(2))?
+        .with(Name("named"))?
+        .build();
+    world.spawn()?.with(Position(3))?.build();
+
+    let mut sums = world
+        .query::<(&Position, &Velocity)>()
+        .into_iter()
+        .map(|(position, velocity)| position.0 + velocity.0)
+        .collect::<Vec<_>>();
+    sums.sort();
+
+    assert_eq!(sums, vec![2, 4]);
+    assert_eq!(world.query::<&Position>().num_entities(), 3);
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "can only be queried once")]
+fn components_queried_mutably_cannot_be_queried_twice() {
+    let mut ecs = Ecs::new();
+    let world = ecs.world_mut();
+
+    world.query::<(&mut Position, &Position)>();
+}